@@ -0,0 +1,126 @@
+// build_config.rs - Per-project compiler settings
+//
+// Stored as `build.json` in the project's data dir and applied by
+// `compile_arduino` / `upload_arduino`. Defines, include paths and the
+// optimization level go through the `compiler.{c,cpp}.extra_flags` hooks that
+// platforms append after their own flags, so e.g. `-O2` overrides `-Os`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::cmd::sys::project::project_data_dir;
+
+const BUILD_CONFIG_FILE: &str = "build.json";
+const WARNING_LEVELS: [&str; 4] = ["none", "default", "more", "all"];
+const OPTIMIZATION_LEVELS: [&str; 6] = ["O0", "O1", "O2", "O3", "Os", "Og"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BuildConfig {
+    /// `DEBUG` or `NAME=value`, passed as `-D`
+    pub defines: Vec<String>,
+    /// Relative paths are resolved against the project file's folder
+    pub include_paths: Vec<String>,
+    /// One of `WARNING_LEVELS`, the platform default when unset
+    pub warnings: Option<String>,
+    /// One of `OPTIMIZATION_LEVELS`, the platform default (usually `Os`) when unset
+    pub optimization: Option<String>,
+    /// Raw `key=value` pairs for `--build-property`
+    pub build_properties: Vec<String>,
+    pub verbose: bool,
+    /// Install index libraries for unresolved #includes before compiling, see `libraries`
+    pub auto_install_libraries: bool,
+}
+
+/// A `BuildConfig` as arduino-cli arguments.
+#[derive(Debug)]
+pub struct CompileFlags {
+    pub build_properties: Vec<String>,
+    pub warnings: Option<String>,
+    pub verbose: bool,
+}
+
+impl BuildConfig {
+    pub fn load(project_path: &str) -> Result<Self, String> {
+        let file = project_data_dir(project_path)?.join(BUILD_CONFIG_FILE);
+        if !file.exists() {
+            return Ok(Self::default());
+        }
+        let text = fs::read_to_string(&file).map_err(|e| e.to_string())?;
+        serde_json::from_str(&text).map_err(|e| format!("Invalid {}: {}", BUILD_CONFIG_FILE, e))
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        for define in &self.defines {
+            let name = define.split('=').next().unwrap_or("");
+            let valid_name = name.chars().next().map_or(false, |c| c.is_ascii_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid_name || define.contains(['"', '\'']) {
+                return Err(format!("Invalid define: {}", define));
+            }
+        }
+        if let Some(p) = self.include_paths.iter().find(|p| p.contains(['"', '\''])) {
+            return Err(format!("Invalid include path: {}", p));
+        }
+        if let Some(w) = self.warnings.as_deref().filter(|w| !WARNING_LEVELS.contains(w)) {
+            return Err(format!("Invalid warning level {}, expected one of {}", w, WARNING_LEVELS.join(", ")));
+        }
+        if let Some(o) = self.optimization.as_deref().filter(|o| !OPTIMIZATION_LEVELS.contains(o)) {
+            return Err(format!("Invalid optimization level {}, expected one of {}", o, OPTIMIZATION_LEVELS.join(", ")));
+        }
+        if let Some(p) = self.build_properties.iter().find(|p| !p.contains('=')) {
+            return Err(format!("Invalid build property {}, expected key=value", p));
+        }
+        Ok(())
+    }
+
+    pub fn flags(&self, project_path: Option<&str>) -> CompileFlags {
+        let project_dir = project_path.and_then(|p| Path::new(p).parent()).map(Path::to_path_buf);
+
+        // Quoted, arduino-cli splits recipes respecting quotes
+        let mut extra = Vec::new();
+        extra.extend(self.defines.iter().map(|d| format!("\"-D{}\"", d)));
+        for include in &self.include_paths {
+            let path = PathBuf::from(include);
+            let path = match (&project_dir, path.is_relative()) {
+                (Some(dir), true) => dir.join(path),
+                _ => path,
+            };
+            extra.push(format!("\"-I{}\"", path.to_string_lossy()));
+        }
+        if let Some(level) = &self.optimization {
+            extra.push(format!("-{}", level));
+        }
+
+        let mut build_properties = Vec::new();
+        if !extra.is_empty() {
+            let extra = extra.join(" ");
+            build_properties.push(format!("compiler.c.extra_flags={}", extra));
+            build_properties.push(format!("compiler.cpp.extra_flags={}", extra));
+        }
+        // Raw properties last, so they win over the generated ones
+        build_properties.extend(self.build_properties.iter().cloned());
+
+        CompileFlags {
+            build_properties,
+            warnings: self.warnings.clone(),
+            verbose: self.verbose,
+        }
+    }
+}
+
+#[tauri::command]
+pub fn get_build_config(project_path: String) -> Result<BuildConfig, String> {
+    BuildConfig::load(&project_path)
+}
+
+#[tauri::command]
+pub fn set_build_config(project_path: String, config: BuildConfig) -> Result<(), String> {
+    config.validate()?;
+    let dir = project_data_dir(&project_path)?;
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+    let json = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+    fs::write(dir.join(BUILD_CONFIG_FILE), json).map_err(|e| e.to_string())?;
+    println!("[Arduino] ⚙️ Saved build config for {}", project_path);
+    Ok(())
+}
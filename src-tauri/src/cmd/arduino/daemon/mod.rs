@@ -0,0 +1,531 @@
+// daemon/mod.rs - Optional arduino-cli daemon (gRPC) backend
+//
+// When enabled, a single `arduino-cli daemon` is started lazily and reused for
+// board, compile, upload, library and core operations. If the daemon cannot be
+// started, the backend switches itself off and callers fall back to spawning
+// `arduino-cli` processes.
+
+pub mod proto;
+
+use tauri::{AppHandle, Runtime};
+use tauri::async_runtime::Mutex;
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::transport::{Channel, Endpoint};
+use std::future::Future;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use crate::cmd::sys::env_manager::arduino::get_config_file;
+use super::build_config::CompileFlags;
+use super::toolchain::OutputSink;
+
+pub const BACKEND_CLI: &str = "cli";
+pub const BACKEND_DAEMON: &str = "daemon";
+
+lazy_static! {
+    static ref DAEMON_ENABLED: AtomicBool = AtomicBool::new(false);
+    static ref DAEMON: Mutex<Option<DaemonClient>> = Mutex::new(None);
+    static ref DAEMON_PROCESS: std::sync::Mutex<Option<Child>> = std::sync::Mutex::new(None);
+}
+
+/// Payload of the `arduino-progress` event, shared by task and download progress.
+#[derive(Debug, Clone, Serialize)]
+pub struct ArduinoProgress {
+    pub name: String,
+    pub message: String,
+    pub percent: f32,
+    pub completed: bool,
+}
+
+impl From<proto::TaskProgress> for ArduinoProgress {
+    fn from(p: proto::TaskProgress) -> Self {
+        ArduinoProgress {
+            name: p.name,
+            message: p.message,
+            percent: p.percent,
+            completed: p.completed,
+        }
+    }
+}
+
+impl ArduinoProgress {
+    fn from_download(p: proto::DownloadProgress) -> Option<Self> {
+        use proto::download_progress::Message;
+        match p.message? {
+            Message::Start(s) => Some(ArduinoProgress {
+                name: s.label,
+                message: s.url,
+                percent: 0.0,
+                completed: false,
+            }),
+            Message::Update(u) => Some(ArduinoProgress {
+                name: String::new(),
+                message: String::new(),
+                percent: if u.total_size > 0 { u.downloaded as f32 * 100.0 / u.total_size as f32 } else { 0.0 },
+                completed: false,
+            }),
+            Message::End(e) => Some(ArduinoProgress {
+                name: String::new(),
+                message: e.message,
+                percent: 100.0,
+                completed: true,
+            }),
+        }
+    }
+}
+
+pub fn is_enabled() -> bool {
+    DAEMON_ENABLED.load(Ordering::SeqCst)
+}
+
+/// Returns a connected daemon client, starting the daemon on first use.
+/// `None` means the caller should use the process-spawning path.
+pub async fn client<R: Runtime>(app_handle: &AppHandle<R>) -> Option<DaemonClient> {
+    connect(get_config_file(app_handle)).await
+}
+
+/// Like `client`, for callers that only know the arduino-cli config file.
+pub async fn connect(config_file: PathBuf) -> Option<DaemonClient> {
+    if !is_enabled() {
+        return None;
+    }
+
+    let mut state = DAEMON.lock().await;
+    if state.is_some() && !process_alive() {
+        println!("[ArduinoDaemon] ⚠️ Daemon exited unexpectedly, restarting");
+        *state = None;
+    }
+
+    if state.is_none() {
+        match start(config_file).await {
+            Ok((child, client)) => {
+                *DAEMON_PROCESS.lock().unwrap() = Some(child);
+                *state = Some(client);
+            }
+            Err(e) => {
+                println!("[ArduinoDaemon] ❌ Failed to start daemon, falling back to arduino-cli processes: {}", e);
+                DAEMON_ENABLED.store(false, Ordering::SeqCst);
+                return None;
+            }
+        }
+    }
+
+    state.clone()
+}
+
+/// Kills the daemon process, if any. Safe to call from a non-async context.
+pub fn shutdown() {
+    if let Ok(mut guard) = DAEMON.try_lock() {
+        *guard = None;
+    }
+    if let Ok(mut process) = DAEMON_PROCESS.lock() {
+        if let Some(mut child) = process.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+            println!("[ArduinoDaemon] 🛑 Daemon stopped");
+        }
+    }
+}
+
+/// Runs a daemon future to completion from synchronous code (e.g. extension lifecycles),
+/// which may itself be running on a runtime worker thread.
+pub fn blocking<F>(fut: F) -> F::Output
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    std::thread::spawn(move || tauri::async_runtime::block_on(fut))
+        .join()
+        .expect("arduino daemon worker thread panicked")
+}
+
+fn process_alive() -> bool {
+    match DAEMON_PROCESS.lock() {
+        Ok(mut process) => match process.as_mut() {
+            Some(child) => matches!(child.try_wait(), Ok(None)),
+            None => false,
+        },
+        Err(_) => false,
+    }
+}
+
+async fn start(config_file: PathBuf) -> Result<(Child, DaemonClient), String> {
+    // Reserve a free local port for the daemon
+    let port = {
+        let listener = TcpListener::bind("127.0.0.1:0").map_err(|e| e.to_string())?;
+        listener.local_addr().map_err(|e| e.to_string())?.port()
+    };
+
+    println!("[ArduinoDaemon] 🚀 Starting arduino-cli daemon on port {}", port);
+    let mut child = Command::new("arduino-cli")
+        .arg("--config-file")
+        .arg(&config_file)
+        .arg("daemon")
+        .arg("--port")
+        .arg(port.to_string())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to execute arduino-cli: {}", e))?;
+
+    let endpoint = Endpoint::from_shared(format!("http://127.0.0.1:{}", port))
+        .map_err(|e| e.to_string())?;
+
+    let mut channel = None;
+    for _ in 0..50 {
+        match endpoint.connect().await {
+            Ok(c) => {
+                channel = Some(c);
+                break;
+            }
+            Err(_) => tokio::time::sleep(Duration::from_millis(200)).await,
+        }
+    }
+
+    let channel = match channel {
+        Some(c) => c,
+        None => {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err("Timed out waiting for arduino-cli daemon".to_string());
+        }
+    };
+
+    let mut client = DaemonClient { channel, instance: proto::Instance::default() };
+    let created: proto::CreateResponse = client.unary("Create", proto::CreateRequest {}).await?;
+    client.instance = created.instance.ok_or("Daemon did not return an instance")?;
+    client.init().await?;
+
+    println!("[ArduinoDaemon] ✅ Daemon ready (instance {})", client.instance.id);
+    Ok((child, client))
+}
+
+/// Splits streamed output chunks into complete lines.
+#[derive(Default)]
+struct LineBuffer {
+    buf: String,
+}
+
+impl LineBuffer {
+    fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buf.push_str(&String::from_utf8_lossy(chunk));
+        let mut lines = Vec::new();
+        while let Some(pos) = self.buf.find('\n') {
+            let line: String = self.buf.drain(..=pos).collect();
+            lines.push(line.trim_end_matches(&['\r', '\n'][..]).to_string());
+        }
+        lines
+    }
+
+    fn flush(&mut self) -> Option<String> {
+        if self.buf.is_empty() {
+            None
+        } else {
+            Some(std::mem::take(&mut self.buf))
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct DaemonClient {
+    channel: Channel,
+    instance: proto::Instance,
+}
+
+impl DaemonClient {
+    fn path(method: &str) -> Result<PathAndQuery, String> {
+        PathAndQuery::try_from(format!("{}/{}", proto::SERVICE, method))
+            .map_err(|e| e.to_string())
+    }
+
+    async fn unary<Req, Resp>(&self, method: &str, req: Req) -> Result<Resp, String>
+    where
+        Req: prost::Message + Send + Sync + 'static,
+        Resp: prost::Message + Default + Send + Sync + 'static,
+    {
+        let mut grpc = tonic::client::Grpc::new(self.channel.clone());
+        grpc.ready().await.map_err(|e| format!("arduino-cli daemon not ready: {}", e))?;
+        let codec = tonic::codec::ProstCodec::<Req, Resp>::default();
+        grpc.unary(tonic::Request::new(req), Self::path(method)?, codec)
+            .await
+            .map(|r| r.into_inner())
+            .map_err(|s| format!("arduino-cli daemon {} failed: {}", method, s.message()))
+    }
+
+    async fn streaming<Req, Resp>(&self, method: &str, req: Req) -> Result<tonic::Streaming<Resp>, String>
+    where
+        Req: prost::Message + Send + Sync + 'static,
+        Resp: prost::Message + Default + Send + Sync + 'static,
+    {
+        let mut grpc = tonic::client::Grpc::new(self.channel.clone());
+        grpc.ready().await.map_err(|e| format!("arduino-cli daemon not ready: {}", e))?;
+        let codec = tonic::codec::ProstCodec::<Req, Resp>::default();
+        grpc.server_streaming(tonic::Request::new(req), Self::path(method)?, codec)
+            .await
+            .map(|r| r.into_inner())
+            .map_err(|s| format!("arduino-cli daemon {} failed: {}", method, s.message()))
+    }
+
+    fn instance(&self) -> Option<proto::Instance> {
+        Some(self.instance.clone())
+    }
+
+    /// (Re)loads indexes, cores and libraries into the daemon instance.
+    async fn init(&self) -> Result<(), String> {
+        let mut stream: tonic::Streaming<proto::InitResponse> = self
+            .streaming("Init", proto::InitRequest { instance: self.instance() })
+            .await?;
+
+        while let Some(msg) = stream.message().await.map_err(|s| s.message().to_string())? {
+            if let Some(proto::init_response::Message::Error(status)) = msg.message {
+                // Missing indexes are reported here but do not make the instance unusable
+                println!("[ArduinoDaemon] ⚠️ Init: {}", status.message);
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn board_list(&self) -> Result<Vec<proto::DetectedPort>, String> {
+        let resp: proto::BoardListResponse = self
+            .unary("BoardList", proto::BoardListRequest { instance: self.instance(), timeout: 1000 })
+            .await?;
+        Ok(resp.ports)
+    }
+
+    pub async fn board_list_watch(&self) -> Result<tonic::Streaming<proto::BoardListWatchResponse>, String> {
+        self.streaming("BoardListWatch", proto::BoardListWatchRequest { instance: self.instance() })
+            .await
+    }
+
+    pub async fn compile(
+        &self,
+        sink: &dyn OutputSink,
+        fqbn: &str,
+        sketch_dir: &Path,
+        libs: &[String],
+        export_dir: &Path,
+        flags: &CompileFlags,
+    ) -> Result<(), String> {
+        let req = proto::CompileRequest {
+            instance: self.instance(),
+            fqbn: fqbn.to_string(),
+            sketch_path: sketch_dir.to_string_lossy().to_string(),
+            libraries: libs.to_vec(),
+            export_dir: export_dir.to_string_lossy().to_string(),
+            build_properties: flags.build_properties.clone(),
+            warnings: flags.warnings.clone().unwrap_or_default(),
+            verbose: flags.verbose,
+            ..Default::default()
+        };
+
+        let mut stream: tonic::Streaming<proto::CompileResponse> = self.streaming("Compile", req).await?;
+        let mut out = LineBuffer::default();
+        let mut stderr_text = String::new();
+
+        loop {
+            match stream.message().await {
+                Ok(Some(msg)) => match msg.message {
+                    Some(proto::compile_response::Message::OutStream(bytes)) => {
+                        for line in out.push(&bytes) {
+                            sink.output(line);
+                        }
+                    }
+                    Some(proto::compile_response::Message::ErrStream(bytes)) => {
+                        stderr_text.push_str(&String::from_utf8_lossy(&bytes));
+                    }
+                    Some(proto::compile_response::Message::Progress(p)) => {
+                        sink.progress(ArduinoProgress::from(p));
+                    }
+                    None => {}
+                },
+                Ok(None) => break,
+                Err(status) => {
+                    if let Some(line) = out.flush() {
+                        sink.output(line);
+                    }
+                    stderr_text.push_str(status.message());
+                    sink.stderr(stderr_text);
+                    return Err("Compilation failed".to_string());
+                }
+            }
+        }
+
+        if let Some(line) = out.flush() {
+            sink.output(line);
+        }
+        // Compiler warnings of a successful build
+        for line in stderr_text.lines() {
+            sink.output(line.to_string());
+        }
+        Ok(())
+    }
+
+    pub async fn upload(
+        &self,
+        sink: &dyn OutputSink,
+        fqbn: &str,
+        sketch_dir: &Path,
+        port: &str,
+        programmer: Option<&str>,
+        verbose: bool,
+    ) -> Result<(), String> {
+        let req = proto::UploadRequest {
+            instance: self.instance(),
+            fqbn: fqbn.to_string(),
+            sketch_path: sketch_dir.to_string_lossy().to_string(),
+            port: if port.is_empty() {
+                None
+            } else {
+                Some(proto::Port {
+                    address: port.to_string(),
+                    protocol: "serial".to_string(),
+                    ..Default::default()
+                })
+            },
+            programmer: programmer.unwrap_or_default().to_string(),
+            verbose,
+            ..Default::default()
+        };
+
+        let mut stream: tonic::Streaming<proto::UploadResponse> = self.streaming("Upload", req).await?;
+        let mut out = LineBuffer::default();
+        let mut err = LineBuffer::default();
+
+        loop {
+            match stream.message().await {
+                Ok(Some(msg)) => match msg.message {
+                    Some(proto::upload_response::Message::OutStream(bytes)) => {
+                        for line in out.push(&bytes) {
+                            sink.output(line);
+                        }
+                    }
+                    Some(proto::upload_response::Message::ErrStream(bytes)) => {
+                        for line in err.push(&bytes) {
+                            sink.stderr(line);
+                        }
+                    }
+                    None => {}
+                },
+                Ok(None) => break,
+                Err(status) => {
+                    sink.stderr(status.message().to_string());
+                    return Err(status.message().to_string());
+                }
+            }
+        }
+
+        if let Some(line) = out.flush() {
+            sink.output(line);
+        }
+        if let Some(line) = err.flush() {
+            sink.stderr(line);
+        }
+        Ok(())
+    }
+
+    pub async fn lib_list(&self) -> Result<Vec<proto::Library>, String> {
+        let resp: proto::LibraryListResponse = self
+            .unary("LibraryList", proto::LibraryListRequest { instance: self.instance(), all: true })
+            .await?;
+        Ok(resp.installed_libraries.into_iter().filter_map(|l| l.library).collect())
+    }
+
+    pub async fn lib_install(&self, name: &str, version: &str) -> Result<(), String> {
+        let req = proto::LibraryInstallRequest {
+            instance: self.instance(),
+            name: name.to_string(),
+            version: version.to_string(),
+        };
+        let mut stream: tonic::Streaming<proto::InstallResponse> = self.streaming("LibraryInstall", req).await?;
+        while let Some(msg) = stream.message().await.map_err(|s| s.message().to_string())? {
+            if let Some(task) = msg.task_progress {
+                if !task.message.is_empty() {
+                    println!("[ArduinoDaemon] 📦 {}", task.message);
+                }
+            }
+        }
+        self.init().await
+    }
+
+    pub async fn zip_lib_install(&self, zip_path: &Path) -> Result<(), String> {
+        let req = proto::ZipLibraryInstallRequest {
+            instance: self.instance(),
+            path: zip_path.to_string_lossy().to_string(),
+            overwrite: true,
+        };
+        let mut stream: tonic::Streaming<proto::ZipLibraryInstallResponse> = self.streaming("ZipLibraryInstall", req).await?;
+        while stream.message().await.map_err(|s| s.message().to_string())?.is_some() {}
+        self.init().await
+    }
+
+    pub async fn core_install(&self, sink: &dyn OutputSink, platform: &str, version: &str) -> Result<(), String> {
+        let (package, architecture) = platform
+            .split_once(':')
+            .ok_or_else(|| format!("Invalid platform '{}', expected <package>:<arch>", platform))?;
+
+        let req = proto::PlatformInstallRequest {
+            instance: self.instance(),
+            platform_package: package.to_string(),
+            architecture: architecture.to_string(),
+            version: version.to_string(),
+        };
+        let mut stream: tonic::Streaming<proto::InstallResponse> = self.streaming("PlatformInstall", req).await?;
+        while let Some(msg) = stream.message().await.map_err(|s| s.message().to_string())? {
+            if let Some(p) = msg.progress.and_then(ArduinoProgress::from_download) {
+                sink.progress(p);
+            }
+            if let Some(task) = msg.task_progress {
+                if !task.message.is_empty() {
+                    sink.output(task.message.clone());
+                }
+                sink.progress(ArduinoProgress::from(task));
+            }
+        }
+        self.init().await
+    }
+
+    pub async fn update_index(&self, sink: &dyn OutputSink) -> Result<(), String> {
+        for method in ["UpdateIndex", "UpdateLibrariesIndex"] {
+            let mut stream: tonic::Streaming<proto::UpdateIndexResponse> = self
+                .streaming(method, proto::UpdateIndexRequest { instance: self.instance() })
+                .await?;
+            while let Some(msg) = stream.message().await.map_err(|s| s.message().to_string())? {
+                if let Some(proto::update_index_response::Message::DownloadProgress(d)) = msg.message {
+                    if let Some(p) = ArduinoProgress::from_download(d) {
+                        sink.progress(p);
+                    }
+                }
+            }
+        }
+        self.init().await
+    }
+}
+
+#[tauri::command]
+pub async fn get_arduino_backend() -> Result<String, String> {
+    Ok(if is_enabled() { BACKEND_DAEMON } else { BACKEND_CLI }.to_string())
+}
+
+#[tauri::command]
+pub async fn set_arduino_backend(app_handle: AppHandle, backend: String) -> Result<String, String> {
+    match backend.as_str() {
+        BACKEND_DAEMON => {
+            DAEMON_ENABLED.store(true, Ordering::SeqCst);
+            if client(&app_handle).await.is_some() {
+                Ok("Arduino backend: daemon".to_string())
+            } else {
+                Err("Failed to start arduino-cli daemon, using arduino-cli processes".to_string())
+            }
+        }
+        BACKEND_CLI => {
+            DAEMON_ENABLED.store(false, Ordering::SeqCst);
+            shutdown();
+            Ok("Arduino backend: cli".to_string())
+        }
+        _ => Err(format!("Unknown Arduino backend: {}", backend)),
+    }
+}
@@ -0,0 +1,360 @@
+// proto.rs - Subset of the arduino-cli gRPC API (cc.arduino.cli.commands.v1)
+//
+// Only the messages and fields used by the daemon backend are declared here;
+// prost skips unknown fields, so newer arduino-cli versions stay compatible.
+
+use std::collections::HashMap;
+
+pub const SERVICE: &str = "/cc.arduino.cli.commands.v1.ArduinoCoreService";
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Instance {
+    #[prost(int32, tag = "1")]
+    pub id: i32,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Status {
+    #[prost(int32, tag = "1")]
+    pub code: i32,
+    #[prost(string, tag = "2")]
+    pub message: String,
+}
+
+// -- Progress --
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct TaskProgress {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(string, tag = "2")]
+    pub message: String,
+    #[prost(bool, tag = "3")]
+    pub completed: bool,
+    #[prost(float, tag = "4")]
+    pub percent: f32,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DownloadProgress {
+    #[prost(oneof = "download_progress::Message", tags = "1, 2, 3")]
+    pub message: Option<download_progress::Message>,
+}
+
+pub mod download_progress {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Message {
+        #[prost(message, tag = "1")]
+        Start(super::DownloadProgressStart),
+        #[prost(message, tag = "2")]
+        Update(super::DownloadProgressUpdate),
+        #[prost(message, tag = "3")]
+        End(super::DownloadProgressEnd),
+    }
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DownloadProgressStart {
+    #[prost(string, tag = "1")]
+    pub url: String,
+    #[prost(string, tag = "2")]
+    pub label: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DownloadProgressUpdate {
+    #[prost(int64, tag = "1")]
+    pub downloaded: i64,
+    #[prost(int64, tag = "2")]
+    pub total_size: i64,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DownloadProgressEnd {
+    #[prost(bool, tag = "1")]
+    pub success: bool,
+    #[prost(string, tag = "2")]
+    pub message: String,
+}
+
+// -- Instance lifecycle --
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CreateRequest {}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CreateResponse {
+    #[prost(message, optional, tag = "1")]
+    pub instance: Option<Instance>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InitRequest {
+    #[prost(message, optional, tag = "1")]
+    pub instance: Option<Instance>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InitResponse {
+    #[prost(oneof = "init_response::Message", tags = "1, 2")]
+    pub message: Option<init_response::Message>,
+}
+
+pub mod init_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Message {
+        #[prost(message, tag = "1")]
+        InitProgress(super::InitProgress),
+        #[prost(message, tag = "2")]
+        Error(super::Status),
+    }
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InitProgress {
+    #[prost(message, optional, tag = "1")]
+    pub download_progress: Option<DownloadProgress>,
+    #[prost(message, optional, tag = "2")]
+    pub task_progress: Option<TaskProgress>,
+}
+
+// -- Boards --
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Port {
+    #[prost(string, tag = "1")]
+    pub address: String,
+    #[prost(string, tag = "2")]
+    pub label: String,
+    #[prost(string, tag = "3")]
+    pub protocol: String,
+    #[prost(string, tag = "4")]
+    pub protocol_label: String,
+    #[prost(map = "string, string", tag = "5")]
+    pub properties: HashMap<String, String>,
+    #[prost(string, tag = "6")]
+    pub hardware_id: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BoardListItem {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(string, tag = "2")]
+    pub fqbn: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct DetectedPort {
+    #[prost(message, repeated, tag = "1")]
+    pub matching_boards: Vec<BoardListItem>,
+    #[prost(message, optional, tag = "2")]
+    pub port: Option<Port>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BoardListRequest {
+    #[prost(message, optional, tag = "1")]
+    pub instance: Option<Instance>,
+    #[prost(int64, tag = "2")]
+    pub timeout: i64,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BoardListResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub ports: Vec<DetectedPort>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BoardListWatchRequest {
+    #[prost(message, optional, tag = "1")]
+    pub instance: Option<Instance>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct BoardListWatchResponse {
+    #[prost(string, tag = "1")]
+    pub event_type: String,
+    #[prost(message, optional, tag = "2")]
+    pub port: Option<DetectedPort>,
+    #[prost(string, tag = "3")]
+    pub error: String,
+}
+
+// -- Compile / Upload --
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CompileRequest {
+    #[prost(message, optional, tag = "1")]
+    pub instance: Option<Instance>,
+    #[prost(string, tag = "2")]
+    pub fqbn: String,
+    #[prost(string, tag = "3")]
+    pub sketch_path: String,
+    #[prost(string, tag = "7")]
+    pub build_path: String,
+    #[prost(string, repeated, tag = "8")]
+    pub build_properties: Vec<String>,
+    #[prost(string, tag = "9")]
+    pub warnings: String,
+    #[prost(bool, tag = "10")]
+    pub verbose: bool,
+    #[prost(string, repeated, tag = "15")]
+    pub libraries: Vec<String>,
+    #[prost(string, tag = "18")]
+    pub export_dir: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct CompileResponse {
+    #[prost(oneof = "compile_response::Message", tags = "1, 2, 3")]
+    pub message: Option<compile_response::Message>,
+}
+
+pub mod compile_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Message {
+        #[prost(bytes, tag = "1")]
+        OutStream(Vec<u8>),
+        #[prost(bytes, tag = "2")]
+        ErrStream(Vec<u8>),
+        #[prost(message, tag = "3")]
+        Progress(super::TaskProgress),
+    }
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UploadRequest {
+    #[prost(message, optional, tag = "1")]
+    pub instance: Option<Instance>,
+    #[prost(string, tag = "2")]
+    pub fqbn: String,
+    #[prost(string, tag = "3")]
+    pub sketch_path: String,
+    #[prost(message, optional, tag = "4")]
+    pub port: Option<Port>,
+    #[prost(bool, tag = "5")]
+    pub verbose: bool,
+    #[prost(string, tag = "8")]
+    pub import_dir: String,
+    #[prost(string, tag = "9")]
+    pub programmer: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UploadResponse {
+    #[prost(oneof = "upload_response::Message", tags = "1, 2")]
+    pub message: Option<upload_response::Message>,
+}
+
+pub mod upload_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Message {
+        #[prost(bytes, tag = "1")]
+        OutStream(Vec<u8>),
+        #[prost(bytes, tag = "2")]
+        ErrStream(Vec<u8>),
+    }
+}
+
+// -- Libraries --
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct Library {
+    #[prost(string, tag = "1")]
+    pub name: String,
+    #[prost(string, tag = "21")]
+    pub version: String,
+    #[prost(string, repeated, tag = "27")]
+    pub provides_includes: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InstalledLibrary {
+    #[prost(message, optional, tag = "1")]
+    pub library: Option<Library>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LibraryListRequest {
+    #[prost(message, optional, tag = "1")]
+    pub instance: Option<Instance>,
+    #[prost(bool, tag = "2")]
+    pub all: bool,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LibraryListResponse {
+    #[prost(message, repeated, tag = "1")]
+    pub installed_libraries: Vec<InstalledLibrary>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct LibraryInstallRequest {
+    #[prost(message, optional, tag = "1")]
+    pub instance: Option<Instance>,
+    #[prost(string, tag = "2")]
+    pub name: String,
+    #[prost(string, tag = "3")]
+    pub version: String,
+}
+
+/// Shared by LibraryInstall and PlatformInstall, which stream the same shape.
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct InstallResponse {
+    #[prost(message, optional, tag = "1")]
+    pub progress: Option<DownloadProgress>,
+    #[prost(message, optional, tag = "2")]
+    pub task_progress: Option<TaskProgress>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ZipLibraryInstallRequest {
+    #[prost(message, optional, tag = "1")]
+    pub instance: Option<Instance>,
+    #[prost(string, tag = "2")]
+    pub path: String,
+    #[prost(bool, tag = "3")]
+    pub overwrite: bool,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct ZipLibraryInstallResponse {
+    #[prost(message, optional, tag = "1")]
+    pub task_progress: Option<TaskProgress>,
+}
+
+// -- Cores / indexes --
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PlatformInstallRequest {
+    #[prost(message, optional, tag = "1")]
+    pub instance: Option<Instance>,
+    #[prost(string, tag = "2")]
+    pub platform_package: String,
+    #[prost(string, tag = "3")]
+    pub architecture: String,
+    #[prost(string, tag = "4")]
+    pub version: String,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateIndexRequest {
+    #[prost(message, optional, tag = "1")]
+    pub instance: Option<Instance>,
+}
+
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct UpdateIndexResponse {
+    #[prost(oneof = "update_index_response::Message", tags = "1")]
+    pub message: Option<update_index_response::Message>,
+}
+
+pub mod update_index_response {
+    #[derive(Clone, PartialEq, ::prost::Oneof)]
+    pub enum Message {
+        #[prost(message, tag = "1")]
+        DownloadProgress(super::DownloadProgress),
+    }
+}
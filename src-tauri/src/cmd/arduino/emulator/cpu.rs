@@ -0,0 +1,763 @@
+// cpu.rs - ATmega328P instruction set (AVR5 core)
+//
+// Data space layout: 32 registers, 64 I/O registers, 160 extended I/O
+// registers, then 2 KB of SRAM. I/O accesses go through `io.rs`.
+
+use super::io::Peripherals;
+
+pub const F_CPU: u64 = 16_000_000;
+pub(super) const FLASH_WORDS: usize = 16 * 1024;
+const DATA_SIZE: usize = 0x900;
+const RAMEND: u16 = 0x8FF;
+
+const SPL: usize = 0x5D;
+const SPH: usize = 0x5E;
+pub(super) const SREG: usize = 0x5F;
+
+// SREG bits
+const C: u8 = 0;
+const Z: u8 = 1;
+const N: u8 = 2;
+const V: u8 = 3;
+const S: u8 = 4;
+const H: u8 = 5;
+const T: u8 = 6;
+pub(super) const I: u8 = 7;
+
+const X: usize = 26;
+const Y: usize = 28;
+const ZP: usize = 30;
+
+pub struct Avr {
+    flash: Vec<u16>,
+    pub(super) data: Vec<u8>,
+    pc: u32,
+    pub cycles: u64,
+    /// An instruction always runs after SEI/RETI before the next interrupt
+    irq_delay: bool,
+    pub(super) io: Peripherals,
+}
+
+impl Avr {
+    pub fn new(program: &[u8]) -> Result<Self, String> {
+        if program.len() > FLASH_WORDS * 2 {
+            return Err(format!("Program is too large for ATmega328P ({} bytes)", program.len()));
+        }
+        let mut flash = vec![0xFFFF; FLASH_WORDS];
+        for (i, chunk) in program.chunks(2).enumerate() {
+            flash[i] = chunk[0] as u16 | (*chunk.get(1).unwrap_or(&0xFF) as u16) << 8;
+        }
+
+        let mut avr = Avr {
+            flash,
+            data: vec![0; DATA_SIZE],
+            pc: 0,
+            cycles: 0,
+            irq_delay: false,
+            io: Peripherals::new(),
+        };
+        avr.set_sp(RAMEND);
+        avr.reset_io();
+        Ok(avr)
+    }
+
+    /// Executes one instruction, advances the peripherals and services interrupts.
+    pub fn step(&mut self) -> Result<(), String> {
+        let start = self.cycles;
+        let op = self.fetch();
+        self.execute(op)?;
+        self.tick(self.cycles - start);
+
+        if self.irq_delay {
+            self.irq_delay = false;
+        } else if self.flag(I) && self.io.irq_dirty {
+            match self.pending_interrupt() {
+                Some(vector) => {
+                    self.push_pc();
+                    self.set_flag(I, false);
+                    self.pc = vector * 2;
+                    self.cycles += 4;
+                }
+                None => self.io.irq_dirty = false,
+            }
+        }
+        Ok(())
+    }
+
+    fn fetch(&mut self) -> u16 {
+        let op = self.flash[self.pc as usize % FLASH_WORDS];
+        self.pc = (self.pc + 1) % FLASH_WORDS as u32;
+        op
+    }
+
+    // ----- memory -----
+
+    fn read(&mut self, addr: u16) -> u8 {
+        match addr as usize {
+            a if a < 0x20 => self.data[a],
+            a if a < 0x100 => self.read_io(a),
+            a if a < DATA_SIZE => self.data[a],
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        match addr as usize {
+            a if a < 0x20 => self.data[a] = value,
+            a if a < 0x100 => self.write_io(a, value),
+            a if a < DATA_SIZE => self.data[a] = value,
+            _ => {}
+        }
+    }
+
+    fn program_byte(&self, addr: u16) -> u8 {
+        let word = self.flash[(addr as usize >> 1) % FLASH_WORDS];
+        if addr & 1 == 0 { word as u8 } else { (word >> 8) as u8 }
+    }
+
+    fn reg16(&self, r: usize) -> u16 {
+        self.data[r] as u16 | (self.data[r + 1] as u16) << 8
+    }
+
+    fn set_reg16(&mut self, r: usize, value: u16) {
+        self.data[r] = value as u8;
+        self.data[r + 1] = (value >> 8) as u8;
+    }
+
+    fn sp(&self) -> u16 {
+        self.data[SPL] as u16 | (self.data[SPH] as u16) << 8
+    }
+
+    fn set_sp(&mut self, value: u16) {
+        self.data[SPL] = value as u8;
+        self.data[SPH] = (value >> 8) as u8;
+    }
+
+    fn push(&mut self, value: u8) {
+        let sp = self.sp();
+        self.write(sp, value);
+        self.set_sp(sp.wrapping_sub(1));
+    }
+
+    fn pop(&mut self) -> u8 {
+        let sp = self.sp().wrapping_add(1);
+        self.set_sp(sp);
+        self.read(sp)
+    }
+
+    fn push_pc(&mut self) {
+        self.push(self.pc as u8);
+        self.push((self.pc >> 8) as u8);
+    }
+
+    fn pop_pc(&mut self) {
+        let hi = self.pop() as u32;
+        let lo = self.pop() as u32;
+        self.pc = ((hi << 8) | lo) % FLASH_WORDS as u32;
+    }
+
+    /// Resolves an X/Y/Z access: mode 0 = plain, 1 = post-increment, 2 = pre-decrement.
+    fn pointer(&mut self, reg: usize, mode: u16) -> u16 {
+        let mut ptr = self.reg16(reg);
+        match mode {
+            1 => self.set_reg16(reg, ptr.wrapping_add(1)),
+            2 => {
+                ptr = ptr.wrapping_sub(1);
+                self.set_reg16(reg, ptr);
+            }
+            _ => {}
+        }
+        ptr
+    }
+
+    // ----- flags -----
+
+    pub(super) fn flag(&self, bit: u8) -> bool {
+        self.data[SREG] & (1 << bit) != 0
+    }
+
+    fn set_flag(&mut self, bit: u8, on: bool) {
+        if on {
+            self.data[SREG] |= 1 << bit;
+        } else {
+            self.data[SREG] &= !(1 << bit);
+        }
+    }
+
+    fn set_nzs(&mut self, result: u8, v: bool) {
+        let n = result & 0x80 != 0;
+        self.set_flag(N, n);
+        self.set_flag(V, v);
+        self.set_flag(S, n ^ v);
+        self.set_flag(Z, result == 0);
+    }
+
+    fn logic(&mut self, result: u8) -> u8 {
+        self.set_nzs(result, false);
+        result
+    }
+
+    fn add(&mut self, d: u8, r: u8, carry: bool) -> u8 {
+        let res = d.wrapping_add(r).wrapping_add(carry as u8);
+        let c = (d & r) | (r & !res) | (!res & d);
+        self.set_nzs(res, (d & r & !res | !d & !r & res) & 0x80 != 0);
+        self.set_flag(H, c & 0x08 != 0);
+        self.set_flag(C, c & 0x80 != 0);
+        res
+    }
+
+    /// SUB/SBC family. With `keep_z` (SBC, SBCI, CPC) Z can only be cleared.
+    fn sub(&mut self, d: u8, r: u8, carry: bool, keep_z: bool) -> u8 {
+        let res = d.wrapping_sub(r).wrapping_sub(carry as u8);
+        let b = (!d & r) | (r & res) | (res & !d);
+        let z = self.flag(Z);
+        self.set_nzs(res, (d & !r & !res | !d & r & res) & 0x80 != 0);
+        self.set_flag(H, b & 0x08 != 0);
+        self.set_flag(C, b & 0x80 != 0);
+        if keep_z {
+            self.set_flag(Z, res == 0 && z);
+        }
+        res
+    }
+
+    fn shift_right(&mut self, d: u8, top: u8) -> u8 {
+        let res = (d >> 1) | top;
+        let c = d & 1 != 0;
+        self.set_flag(C, c);
+        self.set_nzs(res, (res & 0x80 != 0) ^ c);
+        res
+    }
+
+    fn multiply(&mut self, product: u16, fractional: bool) {
+        let c = product & 0x8000 != 0;
+        let res = if fractional { product << 1 } else { product };
+        self.set_reg16(0, res);
+        self.set_flag(C, c);
+        self.set_flag(Z, res == 0);
+        self.cycles += 1;
+    }
+
+    // ----- control flow -----
+
+    fn skip_next(&mut self) {
+        let words = if is_two_word(self.fetch()) {
+            self.pc = (self.pc + 1) % FLASH_WORDS as u32;
+            2
+        } else {
+            1
+        };
+        self.cycles += words;
+    }
+
+    fn jump_relative(&mut self, offset: i32) {
+        self.pc = (self.pc as i32 + offset).rem_euclid(FLASH_WORDS as i32) as u32;
+    }
+
+    fn execute(&mut self, op: u16) -> Result<(), String> {
+        let d = ((op >> 4) & 0x1F) as usize;
+        let r = ((op & 0x0F) | ((op >> 5) & 0x10)) as usize;
+        let d_hi = 16 + ((op >> 4) & 0x0F) as usize;
+        let k = (((op >> 4) & 0xF0) | (op & 0x0F)) as u8;
+        self.cycles += 1;
+
+        match op >> 12 {
+            0x0 => match op & 0xFC00 {
+                0x0000 => match op & 0xFF00 {
+                    0x0000 if op == 0 => {}
+                    0x0100 => {
+                        let (d, r) = (((op >> 4) & 0x0F) as usize * 2, (op & 0x0F) as usize * 2);
+                        self.data[d] = self.data[r];
+                        self.data[d + 1] = self.data[r + 1];
+                    }
+                    0x0200 => {
+                        let (a, b) = (self.data[d_hi] as i8, self.data[16 + (op & 0x0F) as usize] as i8);
+                        self.multiply((a as i16 * b as i16) as u16, false);
+                    }
+                    0x0300 => {
+                        let a = self.data[16 + ((op >> 4) & 0x07) as usize];
+                        let b = self.data[16 + (op & 0x07) as usize];
+                        let (product, fractional) = match op & 0x88 {
+                            0x00 => ((a as i8 as i16 * b as i16) as u16, false),
+                            0x08 => (a as u16 * b as u16, true),
+                            0x80 => ((a as i8 as i16 * b as i8 as i16) as u16, true),
+                            _ => ((a as i8 as i16 * b as i16) as u16, true),
+                        };
+                        self.multiply(product, fractional);
+                    }
+                    _ => return Err(self.unsupported(op)),
+                },
+                0x0400 => {
+                    let c = self.flag(C);
+                    self.sub(self.data[d], self.data[r], c, true);
+                }
+                0x0800 => {
+                    let c = self.flag(C);
+                    self.data[d] = self.sub(self.data[d], self.data[r], c, true);
+                }
+                _ => self.data[d] = self.add(self.data[d], self.data[r], false),
+            },
+            0x1 => match op & 0xFC00 {
+                0x1000 => {
+                    if self.data[d] == self.data[r] {
+                        self.skip_next();
+                    }
+                }
+                0x1400 => {
+                    self.sub(self.data[d], self.data[r], false, false);
+                }
+                0x1800 => self.data[d] = self.sub(self.data[d], self.data[r], false, false),
+                _ => {
+                    let c = self.flag(C);
+                    self.data[d] = self.add(self.data[d], self.data[r], c);
+                }
+            },
+            0x2 => match op & 0xFC00 {
+                0x2000 => self.data[d] = self.logic(self.data[d] & self.data[r]),
+                0x2400 => self.data[d] = self.logic(self.data[d] ^ self.data[r]),
+                0x2800 => self.data[d] = self.logic(self.data[d] | self.data[r]),
+                _ => self.data[d] = self.data[r],
+            },
+            0x3 => {
+                self.sub(self.data[d_hi], k, false, false);
+            }
+            0x4 => {
+                let c = self.flag(C);
+                self.data[d_hi] = self.sub(self.data[d_hi], k, c, true);
+            }
+            0x5 => self.data[d_hi] = self.sub(self.data[d_hi], k, false, false),
+            0x6 => self.data[d_hi] = self.logic(self.data[d_hi] | k),
+            0x7 => self.data[d_hi] = self.logic(self.data[d_hi] & k),
+            0x8 | 0xA => {
+                // LDD/STD with displacement (LD/ST Y and Z are q = 0)
+                let q = (op & 0x07) | ((op >> 7) & 0x18) | ((op >> 8) & 0x20);
+                let base = if op & 0x08 != 0 { Y } else { ZP };
+                let addr = self.reg16(base).wrapping_add(q);
+                if op & 0x0200 != 0 {
+                    self.write(addr, self.data[d]);
+                } else {
+                    self.data[d] = self.read(addr);
+                }
+                self.cycles += 1;
+            }
+            0x9 => self.execute_9(op, d, r)?,
+            0xB => {
+                let addr = 0x20 + ((op & 0x0F) | ((op >> 5) & 0x30));
+                if op & 0x0800 != 0 {
+                    if addr as usize == SREG && self.data[d] & (1 << I) != 0 && !self.flag(I) {
+                        self.irq_delay = true;
+                    }
+                    self.write(addr, self.data[d]);
+                    self.io.irq_dirty = true;
+                } else {
+                    self.data[d] = self.read(addr);
+                }
+            }
+            0xC | 0xD => {
+                let offset = ((op << 4) as i16 >> 4) as i32;
+                if op & 0x1000 != 0 {
+                    self.push_pc();
+                    self.cycles += 2;
+                } else {
+                    self.cycles += 1;
+                }
+                self.jump_relative(offset);
+            }
+            0xE => self.data[d_hi] = k,
+            _ => {
+                let bit = (op & 0x07) as u8;
+                match op & 0xFE00 {
+                    0xF000..=0xF600 if op & 0x0800 == 0 => {
+                        // BRBS / BRBC
+                        let set = op & 0x0400 == 0;
+                        if self.flag(bit) == set {
+                            let offset = (((op >> 3) & 0x7F) << 9) as i16 >> 9;
+                            self.jump_relative(offset as i32);
+                            self.cycles += 1;
+                        }
+                    }
+                    0xF800 => {
+                        let t = self.flag(T);
+                        if t { self.data[d] |= 1 << bit } else { self.data[d] &= !(1 << bit) }
+                    }
+                    0xFA00 => self.set_flag(T, self.data[d] & (1 << bit) != 0),
+                    0xFC00 => {
+                        if self.data[d] & (1 << bit) == 0 {
+                            self.skip_next();
+                        }
+                    }
+                    _ => {
+                        if self.data[d] & (1 << bit) != 0 {
+                            self.skip_next();
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn execute_9(&mut self, op: u16, d: usize, r: usize) -> Result<(), String> {
+        match op & 0xFE00 {
+            0x9000 => {
+                let mode = op & 0x0F;
+                self.data[d] = match mode {
+                    0x0 => {
+                        let addr = self.fetch();
+                        self.read(addr)
+                    }
+                    0x4..=0x7 => {
+                        // LPM / ELPM (no RAMPZ on this part)
+                        let addr = self.pointer(ZP, mode & 1);
+                        self.cycles += 1;
+                        self.program_byte(addr)
+                    }
+                    0xF => self.pop(),
+                    _ => match pointer_mode(mode) {
+                        Some((reg, m)) => {
+                            let addr = self.pointer(reg, m);
+                            self.read(addr)
+                        }
+                        None => return Err(self.unsupported(op)),
+                    },
+                };
+                self.cycles += 1;
+            }
+            0x9200 => {
+                let mode = op & 0x0F;
+                let value = self.data[d];
+                match mode {
+                    0x0 => {
+                        let addr = self.fetch();
+                        self.write(addr, value);
+                    }
+                    0xF => self.push(value),
+                    _ => match pointer_mode(mode) {
+                        Some((reg, m)) => {
+                            let addr = self.pointer(reg, m);
+                            self.write(addr, value);
+                        }
+                        None => return Err(self.unsupported(op)),
+                    },
+                }
+                self.cycles += 1;
+            }
+            0x9400 => match op & 0x0F {
+                0x0 => {
+                    let res = !self.data[d];
+                    self.set_nzs(res, false);
+                    self.set_flag(C, true);
+                    self.data[d] = res;
+                }
+                0x1 => {
+                    let v = self.data[d];
+                    let res = 0u8.wrapping_sub(v);
+                    self.set_nzs(res, res == 0x80);
+                    self.set_flag(H, (res | v) & 0x08 != 0);
+                    self.set_flag(C, res != 0);
+                    self.data[d] = res;
+                }
+                0x2 => self.data[d] = self.data[d].rotate_left(4),
+                0x3 => {
+                    let res = self.data[d].wrapping_add(1);
+                    self.set_nzs(res, res == 0x80);
+                    self.data[d] = res;
+                }
+                0x5 => self.data[d] = self.shift_right(self.data[d], self.data[d] & 0x80),
+                0x6 => self.data[d] = self.shift_right(self.data[d], 0),
+                0x7 => {
+                    let top = if self.flag(C) { 0x80 } else { 0 };
+                    self.data[d] = self.shift_right(self.data[d], top);
+                }
+                0xA => {
+                    let res = self.data[d].wrapping_sub(1);
+                    self.set_nzs(res, res == 0x7F);
+                    self.data[d] = res;
+                }
+                0xC..=0xF => {
+                    // JMP / CALL with a 22-bit address
+                    let hi = (((op >> 3) & 0x3E) | (op & 0x01)) as u32;
+                    let target = ((hi << 16) | self.fetch() as u32) % FLASH_WORDS as u32;
+                    if op & 0x02 != 0 {
+                        self.push_pc();
+                        self.cycles += 3;
+                    } else {
+                        self.cycles += 2;
+                    }
+                    self.pc = target;
+                }
+                0x8 => self.execute_misc(op)?,
+                0x9 => {
+                    // IJMP / ICALL (EIJMP/EICALL behave the same without EIND)
+                    if op & 0x0100 != 0 {
+                        self.push_pc();
+                        self.cycles += 1;
+                    }
+                    self.pc = self.reg16(ZP) as u32 % FLASH_WORDS as u32;
+                    self.cycles += 1;
+                }
+                _ => return Err(self.unsupported(op)),
+            },
+            0x9600 => {
+                // ADIW / SBIW
+                let reg = 24 + ((op >> 4) & 0x03) as usize * 2;
+                let k = ((op & 0x0F) | ((op >> 2) & 0x30)) as u16;
+                let v = self.reg16(reg);
+                let (res, c, ov) = if op & 0x0100 == 0 {
+                    let res = v.wrapping_add(k);
+                    (res, !res & v & 0x8000 != 0, !v & res & 0x8000 != 0)
+                } else {
+                    let res = v.wrapping_sub(k);
+                    (res, res & !v & 0x8000 != 0, v & !res & 0x8000 != 0)
+                };
+                self.set_reg16(reg, res);
+                let n = res & 0x8000 != 0;
+                self.set_flag(N, n);
+                self.set_flag(V, ov);
+                self.set_flag(S, n ^ ov);
+                self.set_flag(Z, res == 0);
+                self.set_flag(C, c);
+                self.cycles += 1;
+            }
+            0x9800 | 0x9A00 => {
+                // CBI / SBIC / SBI / SBIS on the lower 32 I/O registers
+                let addr = 0x20 + ((op >> 3) & 0x1F);
+                let mask = 1u8 << (op & 0x07);
+                let value = self.read(addr);
+                match op & 0xFF00 {
+                    0x9800 => {
+                        self.write(addr, value & !mask);
+                        self.cycles += 1;
+                    }
+                    0x9A00 => {
+                        self.write(addr, value | mask);
+                        self.cycles += 1;
+                    }
+                    0x9900 => {
+                        if value & mask == 0 {
+                            self.skip_next();
+                        }
+                    }
+                    _ => {
+                        if value & mask != 0 {
+                            self.skip_next();
+                        }
+                    }
+                }
+            }
+            _ => {
+                let product = self.data[d] as u16 * self.data[r] as u16;
+                self.multiply(product, false);
+            }
+        }
+        Ok(())
+    }
+
+    fn execute_misc(&mut self, op: u16) -> Result<(), String> {
+        if op & 0x0100 == 0 {
+            // BSET / BCLR
+            let bit = ((op >> 4) & 0x07) as u8;
+            let set = op & 0x0080 == 0;
+            if bit == I && set && !self.flag(I) {
+                self.irq_delay = true;
+                self.io.irq_dirty = true;
+            }
+            self.set_flag(bit, set);
+            return Ok(());
+        }
+        match op {
+            0x9508 | 0x9518 => {
+                self.pop_pc();
+                self.cycles += 3;
+                if op == 0x9518 {
+                    self.set_flag(I, true);
+                    self.irq_delay = true;
+                    self.io.irq_dirty = true;
+                }
+            }
+            // SLEEP, BREAK, WDR: nothing to wait for, interrupts keep firing
+            0x9588 | 0x9598 | 0x95A8 => {}
+            0x95C8 | 0x95D8 => {
+                self.data[0] = self.program_byte(self.reg16(ZP));
+                self.cycles += 2;
+            }
+            // SPM: the bootloader isn't emulated, flash stays read-only
+            0x95E8 | 0x95F8 => {}
+            _ => return Err(self.unsupported(op)),
+        }
+        Ok(())
+    }
+
+    fn unsupported(&self, op: u16) -> String {
+        let addr = (self.pc.wrapping_sub(1) % FLASH_WORDS as u32) * 2;
+        format!("Unsupported instruction {:04X} at 0x{:04X}", op, addr)
+    }
+}
+
+/// LD/ST pointer addressing modes from the low opcode nibble.
+fn pointer_mode(mode: u16) -> Option<(usize, u16)> {
+    match mode {
+        0x1 => Some((ZP, 1)),
+        0x2 => Some((ZP, 2)),
+        0x9 => Some((Y, 1)),
+        0xA => Some((Y, 2)),
+        0xC => Some((X, 0)),
+        0xD => Some((X, 1)),
+        0xE => Some((X, 2)),
+        _ => None,
+    }
+}
+
+/// LDS, STS, JMP and CALL take a second opcode word.
+fn is_two_word(op: u16) -> bool {
+    (op & 0xFC0F) == 0x9000 || (op & 0xFE0C) == 0x940C
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOP: u16 = 0x0000;
+    const SEI: u16 = 0x9478;
+    const RETI: u16 = 0x9518;
+    const TIFR0: usize = 0x35;
+    const TIMSK0: usize = 0x6E;
+
+    fn ldi(d: u16, k: u8) -> u16 {
+        0xE000 | ((k as u16 & 0xF0) << 4) | ((d - 16) << 4) | (k as u16 & 0x0F)
+    }
+
+    /// Two-register instructions: `0000 11rd dddd rrrr` and friends.
+    fn rr(base: u16, d: u16, r: u16) -> u16 {
+        base | ((r & 0x10) << 5) | (d << 4) | (r & 0x0F)
+    }
+
+    fn add(d: u16, r: u16) -> u16 {
+        rr(0x0C00, d, r)
+    }
+
+    fn sub(d: u16, r: u16) -> u16 {
+        rr(0x1800, d, r)
+    }
+
+    fn cp(d: u16, r: u16) -> u16 {
+        rr(0x1400, d, r)
+    }
+
+    fn cpc(d: u16, r: u16) -> u16 {
+        rr(0x0400, d, r)
+    }
+
+    fn breq(k: i8) -> u16 {
+        0xF001 | ((k as u16 & 0x7F) << 3)
+    }
+
+    fn brne(k: i8) -> u16 {
+        0xF401 | ((k as u16 & 0x7F) << 3)
+    }
+
+    fn rjmp(k: i16) -> u16 {
+        0xC000 | (k as u16 & 0x0FFF)
+    }
+
+    fn avr(words: &[u16]) -> Avr {
+        let program: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+        Avr::new(&program).unwrap()
+    }
+
+    fn run(avr: &mut Avr, steps: usize) {
+        for _ in 0..steps {
+            avr.step().unwrap();
+        }
+    }
+
+    /// The flags set, as in a datasheet: e.g. "HSV" or "ZC"
+    fn flags(avr: &Avr) -> String {
+        [(I, 'I'), (T, 'T'), (H, 'H'), (S, 'S'), (V, 'V'), (N, 'N'), (Z, 'Z'), (C, 'C')]
+            .iter()
+            .filter(|(bit, _)| avr.flag(*bit))
+            .map(|(_, name)| name)
+            .collect()
+    }
+
+    #[test]
+    fn add_sets_overflow_carry_and_half_carry() {
+        let mut avr = avr(&[ldi(16, 0x7F), ldi(17, 0x01), add(16, 17), ldi(18, 0xFF), add(18, 17)]);
+        run(&mut avr, 3);
+        assert_eq!(avr.data[16], 0x80);
+        assert_eq!(flags(&avr), "HVN");
+        run(&mut avr, 2);
+        assert_eq!(avr.data[18], 0x00);
+        assert_eq!(flags(&avr), "HZC");
+    }
+
+    #[test]
+    fn sub_borrows_and_overflows() {
+        let mut avr = avr(&[ldi(16, 0x80), ldi(17, 0x01), sub(16, 17), ldi(18, 0x00), sub(18, 17)]);
+        run(&mut avr, 3);
+        assert_eq!(avr.data[16], 0x7F);
+        assert_eq!(flags(&avr), "HSV");
+        run(&mut avr, 2);
+        assert_eq!(avr.data[18], 0xFF);
+        assert_eq!(flags(&avr), "HSNC");
+    }
+
+    #[test]
+    fn cp_compares_without_storing_and_cpc_keeps_z_across_bytes() {
+        // 0x0101 vs 0x0100: the low bytes differ, the high bytes don't
+        let mut avr = avr(&[ldi(16, 1), ldi(17, 0), ldi(18, 1), ldi(19, 1), cp(16, 17), cpc(18, 19), cp(17, 17), cpc(18, 19), cp(17, 16)]);
+        run(&mut avr, 6);
+        assert_eq!((avr.data[16], avr.data[18]), (1, 1));
+        assert!(!avr.flag(Z));
+        run(&mut avr, 2);
+        assert!(avr.flag(Z));
+        run(&mut avr, 1);
+        assert_eq!(flags(&avr), "HSNC");
+    }
+
+    #[test]
+    fn branches_follow_the_flags() {
+        let program = |branch| avr(&[ldi(16, 5), ldi(17, 5), cp(16, 17), branch, ldi(20, 1), ldi(21, 2)]);
+
+        let mut taken = program(breq(1));
+        run(&mut taken, 5);
+        assert_eq!((taken.data[20], taken.data[21]), (0, 2));
+        // A taken branch costs two cycles
+        assert_eq!(taken.cycles, 6);
+
+        let mut not_taken = program(brne(1));
+        run(&mut not_taken, 5);
+        assert_eq!((not_taken.data[20], not_taken.data[21]), (1, 0));
+        assert_eq!(not_taken.cycles, 5);
+
+        let mut jump_back = avr(&[NOP, rjmp(-2)]);
+        run(&mut jump_back, 2);
+        assert_eq!(jump_back.pc, 0);
+    }
+
+    #[test]
+    fn interrupts_wait_for_the_i_bit_and_one_instruction_after_sei() {
+        let mut program = vec![NOP, SEI, NOP, NOP];
+        program.resize(33, NOP);
+        // TIMER0 OVF is vector 16, two words each
+        program[32] = RETI;
+        let mut avr = avr(&program);
+        avr.data[TIMSK0] = 0x01;
+        avr.data[TIFR0] = 0x01;
+        avr.io.irq_dirty = true;
+
+        run(&mut avr, 1);
+        assert_eq!(avr.pc, 1, "no interrupts while I is clear");
+        run(&mut avr, 1);
+        assert_eq!((avr.pc, flags(&avr).as_str()), (2, "I"), "SEI lets one more instruction run");
+        run(&mut avr, 1);
+        assert_eq!(avr.pc, 32);
+        assert!(!avr.flag(I));
+        assert_eq!(avr.data[TIFR0], 0, "the flag is cleared when the interrupt is taken");
+        assert_eq!(avr.sp(), RAMEND - 2);
+
+        run(&mut avr, 1);
+        assert_eq!(avr.pc, 3, "RETI returns to the interrupted code");
+        assert!(avr.flag(I));
+        assert_eq!(avr.sp(), RAMEND);
+    }
+}
@@ -0,0 +1,120 @@
+// hex.rs - Intel HEX loader for the emulator's program memory
+
+use super::cpu::FLASH_WORDS;
+
+/// Parses Intel HEX text into a flat program image starting at address 0.
+pub fn parse_hex(text: &str) -> Result<Vec<u8>, String> {
+    let mut image: Vec<u8> = Vec::new();
+    let mut base: u32 = 0;
+
+    for (index, raw) in text.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let line_no = index + 1;
+        let record = line.strip_prefix(':')
+            .ok_or_else(|| format!("Line {}: missing ':' record mark", line_no))?;
+
+        if record.len() < 10 || record.len() % 2 != 0 {
+            return Err(format!("Line {}: malformed record", line_no));
+        }
+        // Slicing by byte below would split a multi-byte character
+        if !record.is_ascii() {
+            return Err(format!("Line {}: invalid hex digit", line_no));
+        }
+        let bytes = (0..record.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&record[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| format!("Line {}: invalid hex digit", line_no))?;
+
+        let len = bytes[0] as usize;
+        if bytes.len() != len + 5 {
+            return Err(format!("Line {}: length mismatch", line_no));
+        }
+        let checksum = bytes.iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+        if checksum != 0 {
+            return Err(format!("Line {}: checksum mismatch", line_no));
+        }
+
+        let offset = u16::from_be_bytes([bytes[1], bytes[2]]) as u32;
+        let data = &bytes[4..4 + len];
+        match bytes[3] {
+            0x00 => {
+                let start = (base + offset) as usize;
+                // Extended addresses reach up to 4 GB, don't allocate past the flash
+                if start + len > FLASH_WORDS * 2 {
+                    return Err(format!("Line {}: data at 0x{:X} is beyond the {} KB flash", line_no, start, FLASH_WORDS * 2 / 1024));
+                }
+                if image.len() < start + len {
+                    image.resize(start + len, 0xFF);
+                }
+                image[start..start + len].copy_from_slice(data);
+            }
+            0x01 => break,
+            0x02 if len == 2 => base = (u16::from_be_bytes([data[0], data[1]]) as u32) << 4,
+            0x04 if len == 2 => base = (u16::from_be_bytes([data[0], data[1]]) as u32) << 16,
+            // Start address records don't matter, AVR always resets to 0
+            0x03 | 0x05 => {}
+            t => return Err(format!("Line {}: unsupported record type {:02X}", line_no, t)),
+        }
+    }
+
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One record with its checksum.
+    fn record(kind: u8, addr: u16, data: &[u8]) -> String {
+        let mut bytes = vec![data.len() as u8, (addr >> 8) as u8, addr as u8, kind];
+        bytes.extend_from_slice(data);
+        let checksum = bytes.iter().fold(0u8, |acc, b| acc.wrapping_sub(*b));
+        bytes.push(checksum);
+        format!(":{}", bytes.iter().map(|b| format!("{:02X}", b)).collect::<String>())
+    }
+
+    fn eof() -> String {
+        record(0x01, 0, &[])
+    }
+
+    #[test]
+    fn fills_gaps_between_records_with_erased_flash() {
+        let text = [record(0x00, 0, &[0x0C, 0x94]), record(0x00, 4, &[0x01, 0x02]), eof(), record(0x00, 8, &[0xAA])].join("\n");
+        assert_eq!(parse_hex(&text).unwrap(), [0x0C, 0x94, 0xFF, 0xFF, 0x01, 0x02]);
+    }
+
+    #[test]
+    fn accepts_data_up_to_the_end_of_flash() {
+        let text = [record(0x00, 0x7FFE, &[0x01, 0x02]), eof()].join("\n");
+        assert_eq!(parse_hex(&text).unwrap().len(), 32 * 1024);
+    }
+
+    #[test]
+    fn rejects_data_beyond_the_flash() {
+        for base in [record(0x04, 0, &[0x00, 0x01]), record(0x02, 0, &[0x10, 0x00])] {
+            let text = [base, record(0x00, 0, &[0x01]), eof()].join("\n");
+            let error = parse_hex(&text).unwrap_err();
+            assert!(error.contains("beyond the 32 KB flash"), "{}", error);
+        }
+        assert!(parse_hex(&record(0x00, 0x7FFF, &[0x01, 0x02])).is_err());
+    }
+
+    #[test]
+    fn rejects_corrupt_records() {
+        let good = record(0x00, 0, &[0x0C, 0x94]);
+        let mut bad_checksum = good.clone();
+        bad_checksum.replace_range(good.len() - 2.., "00");
+        assert!(parse_hex(&bad_checksum).unwrap_err().contains("checksum"));
+        assert!(parse_hex(&good[1..]).unwrap_err().contains("record mark"));
+        assert!(parse_hex(&record(0x06, 0, &[])).unwrap_err().contains("unsupported record type"));
+
+        // A two-byte character where a hex digit pair would start halfway into it
+        let non_ascii = format!(":0{}{}", 'é', &good[4..]);
+        assert_eq!(non_ascii.len(), good.len());
+        assert!(parse_hex(&non_ascii).unwrap_err().contains("invalid hex digit"));
+    }
+}
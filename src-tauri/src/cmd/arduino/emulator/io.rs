@@ -0,0 +1,621 @@
+// io.rs - ATmega328P peripherals: GPIO, timers 0/1/2, ADC, USART0, EEPROM
+//
+// Register values live in the CPU's data space. Only registers with side
+// effects (flags cleared by writing 1, UDR0, ADCSRA, 16-bit timer registers,
+// PINx toggling, ...) are special-cased here.
+
+use std::collections::VecDeque;
+use serde::Serialize;
+use super::cpu::Avr;
+
+// GPIO
+const PINB: usize = 0x23;
+const PORTD: usize = 0x2B;
+// Interrupt flags and masks
+const TIFR0: usize = 0x35;
+const TIFR1: usize = 0x36;
+const TIFR2: usize = 0x37;
+const PCIFR: usize = 0x3B;
+const EIFR: usize = 0x3C;
+const EIMSK: usize = 0x3D;
+const PCICR: usize = 0x68;
+const EICRA: usize = 0x69;
+const PCMSK0: usize = 0x6B;
+const TIMSK0: usize = 0x6E;
+const TIMSK1: usize = 0x6F;
+const TIMSK2: usize = 0x70;
+// EEPROM
+const EECR: usize = 0x3F;
+const EEDR: usize = 0x40;
+const EEARL: usize = 0x41;
+const EEARH: usize = 0x42;
+// ADC
+const ADCL: usize = 0x78;
+const ADCH: usize = 0x79;
+const ADCSRA: usize = 0x7A;
+const ADCSRB: usize = 0x7B;
+const ADMUX: usize = 0x7C;
+// Timer/counter 1 (16-bit registers share the TEMP latch)
+const TCCR1A: usize = 0x80;
+const TCCR1B: usize = 0x81;
+const TCNT1L: usize = 0x84;
+const ICR1L: usize = 0x86;
+const OCR1AL: usize = 0x88;
+const OCR1BL: usize = 0x8A;
+// USART0
+const UCSR0A: usize = 0xC0;
+const UCSR0B: usize = 0xC1;
+const UBRR0L: usize = 0xC4;
+const UBRR0H: usize = 0xC5;
+const UDR0: usize = 0xC6;
+
+const RXC: u8 = 0x80;
+const TXC: u8 = 0x40;
+const UDRE: u8 = 0x20;
+
+const EEPROM_SIZE: usize = 1024;
+/// Arduino pins 0-7 are PD0-7, 8-13 are PB0-5, A0-A5 (14-19) are PC0-5
+pub const PIN_COUNT: usize = 20;
+
+struct Timer8 {
+    tccra: usize,
+    tccrb: usize,
+    tcnt: usize,
+    ocra: usize,
+    ocrb: usize,
+    tifr: usize,
+    prescalers: [u64; 8],
+}
+
+const TIMER0: Timer8 = Timer8 {
+    tccra: 0x44, tccrb: 0x45, tcnt: 0x46, ocra: 0x47, ocrb: 0x48, tifr: TIFR0,
+    prescalers: [0, 1, 8, 64, 256, 1024, 0, 0],
+};
+
+const TIMER2: Timer8 = Timer8 {
+    tccra: 0xB0, tccrb: 0xB1, tcnt: 0xB2, ocra: 0xB3, ocrb: 0xB4, tifr: TIFR2,
+    prescalers: [0, 1, 8, 32, 64, 128, 256, 1024],
+};
+
+const TIMER1_PRESCALERS: [u64; 8] = [0, 1, 8, 64, 256, 1024, 0, 0];
+
+/// (vector, flag register, flag bit, mask register, mask bit, cleared when serviced), in priority order
+const INTERRUPTS: [(u32, usize, u8, usize, u8, bool); 19] = [
+    (1, EIFR, 0, EIMSK, 0, true),       // INT0
+    (2, EIFR, 1, EIMSK, 1, true),       // INT1
+    (3, PCIFR, 0, PCICR, 0, true),      // PCINT0
+    (4, PCIFR, 1, PCICR, 1, true),      // PCINT1
+    (5, PCIFR, 2, PCICR, 2, true),      // PCINT2
+    (7, TIFR2, 1, TIMSK2, 1, true),     // TIMER2 COMPA
+    (8, TIFR2, 2, TIMSK2, 2, true),     // TIMER2 COMPB
+    (9, TIFR2, 0, TIMSK2, 0, true),     // TIMER2 OVF
+    (10, TIFR1, 5, TIMSK1, 5, true),    // TIMER1 CAPT
+    (11, TIFR1, 1, TIMSK1, 1, true),    // TIMER1 COMPA
+    (12, TIFR1, 2, TIMSK1, 2, true),    // TIMER1 COMPB
+    (13, TIFR1, 0, TIMSK1, 0, true),    // TIMER1 OVF
+    (14, TIFR0, 1, TIMSK0, 1, true),    // TIMER0 COMPA
+    (15, TIFR0, 2, TIMSK0, 2, true),    // TIMER0 COMPB
+    (16, TIFR0, 0, TIMSK0, 0, true),    // TIMER0 OVF
+    (18, UCSR0A, 7, UCSR0B, 7, false),  // USART RX
+    (19, UCSR0A, 5, UCSR0B, 5, false),  // USART UDRE
+    (20, UCSR0A, 6, UCSR0B, 6, true),   // USART TX
+    (21, ADCSRA, 4, ADCSRA, 3, true),   // ADC
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PinMode {
+    Input,
+    Output,
+    Pwm,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PinState {
+    pub mode: PinMode,
+    /// 0/1 for digital pins, 0-255 duty cycle for PWM
+    pub value: u16,
+}
+
+pub struct Peripherals {
+    pub(super) irq_dirty: bool,
+    timer_acc: [u64; 3],
+    /// Phase correct PWM counts down after reaching TOP
+    timer_down: [bool; 3],
+    temp: u8,
+    adc_done_at: Option<u64>,
+    eeprom: Vec<u8>,
+    tx: Vec<u8>,
+    tx_done_at: Option<u64>,
+    rx: VecDeque<u8>,
+    rx_data: u8,
+    rx_next_at: u64,
+    /// Externally driven levels, `None` when nothing is connected
+    inputs: [Option<bool>; PIN_COUNT],
+    analog: [u16; 8],
+    levels: [u8; 3],
+}
+
+impl Peripherals {
+    pub fn new() -> Self {
+        Peripherals {
+            irq_dirty: false,
+            timer_acc: [0; 3],
+            timer_down: [false; 3],
+            temp: 0,
+            adc_done_at: None,
+            eeprom: vec![0xFF; EEPROM_SIZE],
+            tx: Vec::new(),
+            tx_done_at: None,
+            rx: VecDeque::new(),
+            rx_data: 0,
+            rx_next_at: 0,
+            inputs: [None; PIN_COUNT],
+            analog: [0; 8],
+            levels: [0; 3],
+        }
+    }
+}
+
+/// Maps an Arduino pin to its port index (B = 0, C = 1, D = 2) and bit.
+fn pin_location(pin: usize) -> (usize, u8) {
+    match pin {
+        0..=7 => (2, pin as u8),
+        8..=13 => (0, pin as u8 - 8),
+        _ => (1, pin as u8 - 14),
+    }
+}
+
+fn arduino_pin(port: usize, bit: u8) -> Option<usize> {
+    match (port, bit) {
+        (0, 0..=5) => Some(8 + bit as usize),
+        (1, 0..=5) => Some(14 + bit as usize),
+        (2, _) => Some(bit as usize),
+        _ => None,
+    }
+}
+
+impl Avr {
+    pub(super) fn reset_io(&mut self) {
+        self.data[UCSR0A] = UDRE;
+        self.data[UCSR0B] = 0;
+        self.data[UCSR0B + 1] = 0x06;
+    }
+
+    pub(super) fn read_io(&mut self, addr: usize) -> u8 {
+        match addr {
+            0x23 | 0x26 | 0x29 => self.pin_register((addr - PINB) / 3),
+            UDR0 => {
+                self.data[UCSR0A] &= !RXC;
+                self.io.rx_data
+            }
+            TCNT1L | ICR1L | OCR1AL | OCR1BL => {
+                self.io.temp = self.data[addr + 1];
+                self.data[addr]
+            }
+            a if a == TCNT1L + 1 || a == ICR1L + 1 || a == OCR1AL + 1 || a == OCR1BL + 1 => self.io.temp,
+            _ => self.data[addr],
+        }
+    }
+
+    pub(super) fn write_io(&mut self, addr: usize, value: u8) {
+        match addr {
+            // Writing 1 to a PINx bit toggles PORTx
+            0x23 | 0x26 | 0x29 => {
+                self.data[addr + 2] ^= value;
+                self.update_pin_levels();
+            }
+            0x24..=PORTD => {
+                self.data[addr] = value;
+                self.update_pin_levels();
+            }
+            TIFR0 | TIFR1 | TIFR2 | PCIFR | EIFR => self.data[addr] &= !value,
+            EECR => self.write_eecr(value),
+            ADCSRA => self.write_adcsra(value),
+            TCNT1L | ICR1L | OCR1AL | OCR1BL => {
+                self.data[addr] = value;
+                self.data[addr + 1] = self.io.temp;
+            }
+            a if a == TCNT1L + 1 || a == ICR1L + 1 || a == OCR1AL + 1 || a == OCR1BL + 1 => self.io.temp = value,
+            UCSR0A => {
+                let keep = self.data[addr] & (RXC | UDRE | TXC);
+                self.data[addr] = (keep & !(value & TXC)) | (value & 0x03);
+            }
+            UDR0 => self.transmit(value),
+            ADCL | ADCH => {}
+            _ => self.data[addr] = value,
+        }
+        self.io.irq_dirty = true;
+    }
+
+    pub(super) fn tick(&mut self, cycles: u64) {
+        self.tick_timer8(&TIMER0, 0, cycles);
+        self.tick_timer8(&TIMER2, 2, cycles);
+        self.tick_timer1(cycles);
+
+        if let Some(done) = self.io.adc_done_at {
+            if self.cycles >= done {
+                self.finish_conversion();
+            }
+        }
+
+        if let Some(done) = self.io.tx_done_at {
+            if self.cycles >= done {
+                self.io.tx_done_at = None;
+                self.data[UCSR0A] |= UDRE | TXC;
+                self.io.irq_dirty = true;
+            }
+        }
+
+        let rx_enabled = self.data[UCSR0B] & 0x10 != 0;
+        if rx_enabled && !self.io.rx.is_empty() && self.cycles >= self.io.rx_next_at {
+            // Bytes arrive at the configured baud rate; the sketch has to keep up
+            if let Some(byte) = self.io.rx.pop_front() {
+                self.io.rx_data = byte;
+                self.data[UCSR0A] |= RXC;
+                self.io.rx_next_at = self.cycles + self.frame_cycles();
+                self.io.irq_dirty = true;
+            }
+        }
+    }
+
+    pub(super) fn pending_interrupt(&mut self) -> Option<u32> {
+        for &(vector, flag_reg, flag_bit, mask_reg, mask_bit, clear) in INTERRUPTS.iter() {
+            if self.data[flag_reg] & (1 << flag_bit) != 0 && self.data[mask_reg] & (1 << mask_bit) != 0 {
+                if clear {
+                    self.data[flag_reg] &= !(1 << flag_bit);
+                }
+                return Some(vector);
+            }
+        }
+        None
+    }
+
+    // ----- timers -----
+
+    fn tick_timer8(&mut self, timer: &Timer8, index: usize, cycles: u64) {
+        let prescaler = timer.prescalers[(self.data[timer.tccrb] & 0x07) as usize];
+        if prescaler == 0 {
+            return;
+        }
+        self.io.timer_acc[index] += cycles;
+        let ticks = self.io.timer_acc[index] / prescaler;
+        self.io.timer_acc[index] %= prescaler;
+
+        let wgm = (self.data[timer.tccra] & 0x03) | ((self.data[timer.tccrb] >> 1) & 0x04);
+        let top = match wgm {
+            2 | 5 | 7 => self.data[timer.ocra],
+            _ => 0xFF,
+        };
+
+        for _ in 0..ticks {
+            let mut count = self.data[timer.tcnt];
+            let mut flags = 0u8;
+            if wgm == 1 || wgm == 5 {
+                if self.io.timer_down[index] {
+                    count = count.wrapping_sub(1);
+                    if count == 0 {
+                        self.io.timer_down[index] = false;
+                        flags |= 0x01;
+                    }
+                } else if count >= top {
+                    self.io.timer_down[index] = top > 0;
+                    count = top.saturating_sub(1);
+                } else {
+                    count += 1;
+                }
+            } else if count == top {
+                count = 0;
+                if wgm != 2 {
+                    flags |= 0x01;
+                }
+            } else {
+                count = count.wrapping_add(1);
+            }
+
+            if count == self.data[timer.ocra] {
+                flags |= 0x02;
+            }
+            if count == self.data[timer.ocrb] {
+                flags |= 0x04;
+            }
+            self.data[timer.tcnt] = count;
+            if flags != 0 {
+                self.data[timer.tifr] |= flags;
+                self.io.irq_dirty = true;
+            }
+        }
+    }
+
+    fn timer1_wgm(&self) -> u8 {
+        (self.data[TCCR1A] & 0x03) | ((self.data[TCCR1B] >> 1) & 0x0C)
+    }
+
+    fn timer1_top(&self, wgm: u8) -> u16 {
+        let reg16 = |addr: usize| self.data[addr] as u16 | (self.data[addr + 1] as u16) << 8;
+        match wgm {
+            1 | 5 => 0xFF,
+            2 | 6 => 0x1FF,
+            3 | 7 => 0x3FF,
+            4 | 9 | 11 | 15 => reg16(OCR1AL),
+            8 | 10 | 12 | 14 => reg16(ICR1L),
+            _ => 0xFFFF,
+        }
+    }
+
+    fn tick_timer1(&mut self, cycles: u64) {
+        let prescaler = TIMER1_PRESCALERS[(self.data[TCCR1B] & 0x07) as usize];
+        if prescaler == 0 {
+            return;
+        }
+        self.io.timer_acc[1] += cycles;
+        let ticks = self.io.timer_acc[1] / prescaler;
+        self.io.timer_acc[1] %= prescaler;
+
+        let wgm = self.timer1_wgm();
+        let top = self.timer1_top(wgm);
+        let phase_correct = matches!(wgm, 1 | 2 | 3 | 8 | 9 | 10 | 11);
+        let ctc = matches!(wgm, 4 | 12);
+        let ocra = self.data[OCR1AL] as u16 | (self.data[OCR1AL + 1] as u16) << 8;
+        let ocrb = self.data[OCR1BL] as u16 | (self.data[OCR1BL + 1] as u16) << 8;
+
+        for _ in 0..ticks {
+            let mut count = self.data[TCNT1L] as u16 | (self.data[TCNT1L + 1] as u16) << 8;
+            let mut flags = 0u8;
+            if phase_correct {
+                if self.io.timer_down[1] {
+                    count = count.wrapping_sub(1);
+                    if count == 0 {
+                        self.io.timer_down[1] = false;
+                        flags |= 0x01;
+                    }
+                } else if count >= top {
+                    self.io.timer_down[1] = top > 0;
+                    count = top.saturating_sub(1);
+                } else {
+                    count += 1;
+                }
+            } else if count == top {
+                count = 0;
+                if !ctc {
+                    flags |= 0x01;
+                }
+            } else {
+                count = count.wrapping_add(1);
+            }
+
+            if count == ocra {
+                flags |= 0x02;
+            }
+            if count == ocrb {
+                flags |= 0x04;
+            }
+            self.data[TCNT1L] = count as u8;
+            self.data[TCNT1L + 1] = (count >> 8) as u8;
+            if flags != 0 {
+                self.data[TIFR1] |= flags;
+                self.io.irq_dirty = true;
+            }
+        }
+    }
+
+    // ----- ADC -----
+
+    fn write_adcsra(&mut self, value: u8) {
+        let old = self.data[ADCSRA];
+        // ADIF is cleared by writing 1, ADSC can't be cleared by software
+        let mut new = (value & !0x10) | (old & 0x10 & !value);
+        if self.io.adc_done_at.is_some() {
+            new |= 0x40;
+        }
+        self.data[ADCSRA] = new;
+
+        if new & 0xC0 == 0xC0 && self.io.adc_done_at.is_none() {
+            self.start_conversion();
+        }
+    }
+
+    fn start_conversion(&mut self) {
+        let prescaler = 1u64 << (self.data[ADCSRA] & 0x07).max(1);
+        self.io.adc_done_at = Some(self.cycles + 13 * prescaler);
+    }
+
+    fn finish_conversion(&mut self) {
+        self.io.adc_done_at = None;
+        let admux = self.data[ADMUX];
+        let value = match admux & 0x0F {
+            ch @ 0..=7 => self.io.analog[ch as usize].min(1023),
+            // Internal temperature sensor at roughly 25 °C
+            8 => 352,
+            // 1.1 V bandgap against AVcc
+            14 => 225,
+            _ => 0,
+        };
+        if admux & 0x20 != 0 {
+            self.data[ADCL] = ((value & 0x03) << 6) as u8;
+            self.data[ADCH] = (value >> 2) as u8;
+        } else {
+            self.data[ADCL] = value as u8;
+            self.data[ADCH] = (value >> 8) as u8;
+        }
+        self.data[ADCSRA] = (self.data[ADCSRA] & !0x40) | 0x10;
+        self.io.irq_dirty = true;
+
+        // Free running mode keeps converting
+        if self.data[ADCSRA] & 0xA0 == 0xA0 && self.data[ADCSRB] & 0x07 == 0 {
+            self.data[ADCSRA] |= 0x40;
+            self.start_conversion();
+        }
+    }
+
+    // ----- EEPROM -----
+
+    fn write_eecr(&mut self, value: u8) {
+        let old = self.data[EECR];
+        let addr = (self.data[EEARL] as usize | (self.data[EEARH] as usize & 0x03) << 8) % EEPROM_SIZE;
+        if value & 0x01 != 0 {
+            self.data[EEDR] = self.io.eeprom[addr];
+            self.cycles += 4;
+        }
+        if value & 0x02 != 0 && old & 0x04 != 0 {
+            let data = self.data[EEDR];
+            self.io.eeprom[addr] = match (value >> 4) & 0x03 {
+                1 => 0xFF,
+                2 => self.io.eeprom[addr] & data,
+                _ => data,
+            };
+        }
+        // Reads and writes complete immediately
+        self.data[EECR] = value & 0x3C;
+    }
+
+    // ----- USART0 -----
+
+    fn frame_cycles(&self) -> u64 {
+        let ubrr = self.data[UBRR0L] as u64 | (self.data[UBRR0H] as u64 & 0x0F) << 8;
+        let divider = if self.data[UCSR0A] & 0x02 != 0 { 8 } else { 16 };
+        10 * divider * (ubrr + 1)
+    }
+
+    fn transmit(&mut self, value: u8) {
+        if self.data[UCSR0B] & 0x08 == 0 {
+            return;
+        }
+        self.io.tx.push(value);
+        self.data[UCSR0A] &= !(UDRE | TXC);
+        self.io.tx_done_at = Some(self.cycles + self.frame_cycles());
+    }
+
+    /// Bytes written to UDR0 since the last call.
+    pub fn take_uart_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.io.tx)
+    }
+
+    pub fn receive_uart(&mut self, bytes: &[u8]) {
+        self.io.rx.extend(bytes);
+    }
+
+    // ----- GPIO -----
+
+    fn pin_register(&self, port: usize) -> u8 {
+        let ddr = self.data[PINB + port * 3 + 1];
+        let out = self.data[PINB + port * 3 + 2];
+        let mut value = 0u8;
+        for bit in 0..8 {
+            let mask = 1 << bit;
+            let external = arduino_pin(port, bit).and_then(|pin| self.io.inputs[pin]);
+            let high = if ddr & mask != 0 {
+                out & mask != 0
+            } else {
+                // Floating inputs read low unless the pull-up is on
+                external.unwrap_or(out & mask != 0)
+            };
+            if high {
+                value |= mask;
+            }
+        }
+        value
+    }
+
+    /// Raises INT0/INT1 and pin change flags for edges since the last update.
+    fn update_pin_levels(&mut self) {
+        for port in 0..3 {
+            let level = self.pin_register(port);
+            let changed = level ^ self.io.levels[port];
+            if changed == 0 {
+                continue;
+            }
+            self.io.levels[port] = level;
+
+            // PCINT0 = port B, PCINT1 = port C, PCINT2 = port D
+            if changed & self.data[PCMSK0 + port] != 0 {
+                self.data[PCIFR] |= 1 << port;
+                self.io.irq_dirty = true;
+            }
+
+            if port == 2 {
+                for (int, bit) in [(0u8, 2u8), (1, 3)] {
+                    if changed & (1 << bit) == 0 {
+                        continue;
+                    }
+                    let rising = level & (1 << bit) != 0;
+                    let fire = match (self.data[EICRA] >> (int * 2)) & 0x03 {
+                        0 => !rising,
+                        1 => true,
+                        2 => !rising,
+                        _ => rising,
+                    };
+                    if fire {
+                        self.data[EIFR] |= 1 << int;
+                        self.io.irq_dirty = true;
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn set_digital_input(&mut self, pin: usize, level: Option<bool>) {
+        if pin < PIN_COUNT {
+            self.io.inputs[pin] = level;
+            self.update_pin_levels();
+        }
+    }
+
+    pub fn set_analog_input(&mut self, channel: usize, value: u16) {
+        if channel < self.io.analog.len() {
+            self.io.analog[channel] = value.min(1023);
+        }
+    }
+
+    /// Current state of every Arduino pin, including PWM duty cycles.
+    pub fn pin_states(&self) -> [PinState; PIN_COUNT] {
+        let levels = [self.pin_register(0), self.pin_register(1), self.pin_register(2)];
+        let mut states = [PinState { mode: PinMode::Input, value: 0 }; PIN_COUNT];
+        for (pin, state) in states.iter_mut().enumerate() {
+            let (port, bit) = pin_location(pin);
+            let output = self.data[PINB + port * 3 + 1] & (1 << bit) != 0;
+            let high = levels[port] & (1 << bit) != 0;
+            *state = match (output, self.pwm_duty(pin)) {
+                (true, Some(duty)) => PinState { mode: PinMode::Pwm, value: duty },
+                (true, None) => PinState { mode: PinMode::Output, value: high as u16 },
+                _ => PinState { mode: PinMode::Input, value: high as u16 },
+            };
+        }
+        states
+    }
+
+    /// Duty cycle (0-255) of a pin driven by a timer compare output, if any.
+    fn pwm_duty(&self, pin: usize) -> Option<u16> {
+        let (tccra, com_shift, top, ocr) = match pin {
+            3 | 5 | 6 | 11 => {
+                let timer = if pin == 5 || pin == 6 { &TIMER0 } else { &TIMER2 };
+                let wgm = (self.data[timer.tccra] & 0x03) | ((self.data[timer.tccrb] >> 1) & 0x04);
+                if !matches!(wgm, 1 | 3 | 5 | 7) {
+                    return None;
+                }
+                let channel_a = pin == 6 || pin == 11;
+                let ocr = if channel_a { self.data[timer.ocra] } else { self.data[timer.ocrb] };
+                let top = if wgm >= 5 { self.data[timer.ocra] as u32 } else { 0xFF };
+                (timer.tccra, if channel_a { 6 } else { 4 }, top, ocr as u32)
+            }
+            9 | 10 => {
+                let wgm = self.timer1_wgm();
+                if matches!(wgm, 0 | 4 | 12 | 13) {
+                    return None;
+                }
+                let reg = if pin == 9 { OCR1AL } else { OCR1BL };
+                let ocr = self.data[reg] as u32 | (self.data[reg + 1] as u32) << 8;
+                (TCCR1A, if pin == 9 { 6 } else { 4 }, self.timer1_top(wgm) as u32, ocr)
+            }
+            _ => return None,
+        };
+
+        // COMnx = 2 is non-inverting, 3 inverting; 0/1 leave the pin to PORTx
+        let com = (self.data[tccra] >> com_shift) & 0x03;
+        if com < 2 {
+            return None;
+        }
+        let duty = (ocr.min(top) * 255 / top.max(1)) as u16;
+        Some(if com == 3 { 255 - duty } else { duty })
+    }
+}
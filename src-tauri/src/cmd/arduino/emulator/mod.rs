@@ -0,0 +1,237 @@
+// emulator.rs - Run compiled sketches on an emulated ATmega328P (Uno / Nano)
+//
+// The `.hex` exported by `compile_arduino` is executed in real time on a
+// background thread. UART0 output is emitted as `serial-data`, like a real
+// serial monitor, and pin changes as `arduino-pin-change` so the UI can draw
+// LEDs. Buttons and potentiometers are fed in with `set_emulator_*_input`.
+
+mod cpu;
+mod hex;
+mod io;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::Serialize;
+use tauri::{Emitter, Window};
+
+use self::cpu::{Avr, F_CPU};
+use self::io::PIN_COUNT;
+pub use self::io::PinMode;
+use super::sketch;
+
+/// Emulated time advances in slices, each followed by a sync with the wall clock
+const SLICE_MS: u64 = 10;
+const CYCLES_PER_SLICE: u64 = F_CPU * SLICE_MS / 1000;
+
+/// Boards built around a 16 MHz ATmega328P
+const EMULATED_BOARDS: [&str; 3] = ["arduino:avr:uno", "arduino:avr:nano", "arduino:avr:pro"];
+
+enum EmulatorInput {
+    Digital(usize, Option<bool>),
+    Analog(usize, u16),
+    Serial(Vec<u8>),
+}
+
+struct EmulatorHandle {
+    stop: Arc<AtomicBool>,
+    inputs: Sender<EmulatorInput>,
+}
+
+lazy_static::lazy_static! {
+    static ref EMULATOR: Mutex<Option<EmulatorHandle>> = Mutex::new(None);
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PinChange {
+    pub pin: u8,
+    pub mode: PinMode,
+    pub value: u16,
+    /// Time since the sketch started, in emulated milliseconds
+    pub time_ms: u64,
+}
+
+fn is_emulated_board(fqbn: &str) -> bool {
+    let mut parts = fqbn.splitn(4, ':');
+    let base = parts.by_ref().take(3).collect::<Vec<_>>().join(":");
+    let options = parts.next().unwrap_or("");
+    EMULATED_BOARDS.contains(&base.as_str())
+        && options.split(',').all(|opt| match opt.split_once('=') {
+            Some(("cpu", cpu)) => cpu.starts_with("atmega328") || cpu == "16MHzatmega328",
+            _ => true,
+        })
+}
+
+/// Sends input to the running emulator. Returns false when it isn't running.
+fn send_input(input: EmulatorInput) -> bool {
+    let guard = EMULATOR.lock().unwrap();
+    match guard.as_ref() {
+        Some(handle) => handle.inputs.send(input).is_ok(),
+        None => false,
+    }
+}
+
+/// Feeds bytes to the emulated UART0, as if typed in the serial monitor.
+pub fn write_uart(data: &[u8]) -> bool {
+    send_input(EmulatorInput::Serial(data.to_vec()))
+}
+
+pub fn stop() {
+    if let Some(handle) = EMULATOR.lock().unwrap().take() {
+        handle.stop.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Runs the sketch last compiled for `fqbn` (see `compile_arduino`).
+#[tauri::command]
+pub fn run_arduino_emulator(window: Window, fqbn: String) -> Result<(), String> {
+    if !is_emulated_board(&fqbn) {
+        return Err(format!("The emulator only supports ATmega328P boards (Uno, Nano), not {}", fqbn));
+    }
+
+    let hex_file = sketch::output_dir(&fqbn).join(format!("{}.ino.hex", sketch::SKETCH_NAME));
+    let text = std::fs::read_to_string(&hex_file)
+        .map_err(|_| "No compiled sketch found, compile it for this board first".to_string())?;
+    let program = hex::parse_hex(&text).map_err(|e| format!("Invalid hex file: {}", e))?;
+    let avr = Avr::new(&program)?;
+
+    stop();
+    let stop_flag = Arc::new(AtomicBool::new(false));
+    let (sender, receiver) = mpsc::channel();
+    *EMULATOR.lock().unwrap() = Some(EmulatorHandle { stop: stop_flag.clone(), inputs: sender });
+
+    println!("[Emulator] ▶️ Running {:?} ({} bytes)", hex_file, program.len());
+    window.emit("serial-data", "\n[System] Emulator started.\n").unwrap_or(());
+    thread::spawn(move || run(window, avr, stop_flag, receiver));
+    Ok(())
+}
+
+#[tauri::command]
+pub fn stop_arduino_emulator() -> Result<(), String> {
+    stop();
+    Ok(())
+}
+
+/// Drives a digital pin from outside; `None` disconnects it (the pin floats).
+#[tauri::command]
+pub fn set_emulator_digital_input(pin: u8, high: Option<bool>) -> Result<(), String> {
+    if pin as usize >= PIN_COUNT {
+        return Err(format!("Invalid pin {}", pin));
+    }
+    if !send_input(EmulatorInput::Digital(pin as usize, high)) {
+        return Err("Emulator is not running".to_string());
+    }
+    Ok(())
+}
+
+/// Sets the voltage on analog channel A0-A7 as a 0-1023 reading.
+#[tauri::command]
+pub fn set_emulator_analog_input(channel: u8, value: u16) -> Result<(), String> {
+    if channel > 7 {
+        return Err(format!("Invalid analog channel A{}", channel));
+    }
+    if !send_input(EmulatorInput::Analog(channel as usize, value)) {
+        return Err("Emulator is not running".to_string());
+    }
+    Ok(())
+}
+
+fn run(window: Window, mut avr: Avr, stop_flag: Arc<AtomicBool>, inputs: Receiver<EmulatorInput>) {
+    let mut pins = avr.pin_states();
+    let mut pending_text: Vec<u8> = Vec::new();
+    let mut start = Instant::now();
+    let mut slices: u64 = 0;
+    let mut error = None;
+
+    for (pin, state) in pins.iter().enumerate() {
+        emit_pin(&window, &avr, pin, state.mode, state.value);
+    }
+
+    'outer: while !stop_flag.load(Ordering::SeqCst) {
+        while let Ok(input) = inputs.try_recv() {
+            match input {
+                EmulatorInput::Digital(pin, level) => avr.set_digital_input(pin, level),
+                EmulatorInput::Analog(channel, value) => avr.set_analog_input(channel, value),
+                EmulatorInput::Serial(bytes) => avr.receive_uart(&bytes),
+            }
+        }
+
+        let target = avr.cycles + CYCLES_PER_SLICE;
+        while avr.cycles < target {
+            if let Err(e) = avr.step() {
+                error = Some(e);
+                break 'outer;
+            }
+        }
+
+        pending_text.extend(avr.take_uart_output());
+        if let Some(text) = take_complete_utf8(&mut pending_text) {
+            window.emit("serial-data", text).unwrap_or(());
+        }
+
+        let current = avr.pin_states();
+        for (pin, (old, new)) in pins.iter().zip(current.iter()).enumerate() {
+            if old != new {
+                emit_pin(&window, &avr, pin, new.mode, new.value);
+            }
+        }
+        pins = current;
+
+        slices += 1;
+        let due = start + Duration::from_millis(slices * SLICE_MS);
+        let now = Instant::now();
+        if due > now {
+            thread::sleep(due - now);
+        } else if now - due > Duration::from_secs(1) {
+            // The host can't keep up, run as fast as possible without trying to catch up
+            start = now;
+            slices = 0;
+        }
+    }
+
+    // Only clear the global state if no newer emulator replaced this one
+    {
+        let mut guard = EMULATOR.lock().unwrap();
+        if guard.as_ref().map_or(false, |h| Arc::ptr_eq(&h.stop, &stop_flag)) {
+            *guard = None;
+        }
+    }
+
+    let msg = match error {
+        Some(e) => {
+            println!("[Emulator] ❌ {}", e);
+            window.emit("arduino-stderr", format!("Emulator error: {}", e)).unwrap_or(());
+            format!("Emulator stopped: {}", e)
+        }
+        None => "Emulator stopped.".to_string(),
+    };
+    window.emit("arduino-finished", msg).unwrap_or(());
+}
+
+fn emit_pin(window: &Window, avr: &Avr, pin: usize, mode: PinMode, value: u16) {
+    let time_ms = avr.cycles / (F_CPU / 1000);
+    let change = PinChange { pin: pin as u8, mode, value, time_ms };
+    window.emit("arduino-pin-change", change).unwrap_or(());
+}
+
+/// Splits off the longest valid UTF-8 prefix, keeping a truncated trailing character for later.
+pub(super) fn take_complete_utf8(buf: &mut Vec<u8>) -> Option<String> {
+    if buf.is_empty() {
+        return None;
+    }
+    let valid = match std::str::from_utf8(buf) {
+        Ok(_) => buf.len(),
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        // Invalid bytes are shown as replacement characters
+        Err(_) => buf.len(),
+    };
+    if valid == 0 {
+        return None;
+    }
+    let rest = buf.split_off(valid);
+    let text = String::from_utf8_lossy(buf).to_string();
+    *buf = rest;
+    Some(text)
+}
@@ -0,0 +1,232 @@
+// libraries.rs - Find the libraries behind a sketch's #include lines
+//
+// Before compiling, every included header that no installed library (user,
+// platform bundled or extension supplied) provides is looked up in the library
+// index with `lib search provides:<header>`. Hits are reported through
+// `arduino-missing-libraries` and installed right away when the project's
+// build config has `auto_install_libraries`. Headers the index doesn't know
+// are assumed to come from the core or the toolchain.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use serde::Serialize;
+use tauri::{Emitter, Manager, Window};
+use super::sketch::included_headers;
+use super::toolchain::{self, ArduinoToolchain};
+
+/// Toolchain and core headers, never worth an index search
+const SYSTEM_HEADERS: [&str; 14] = [
+    "Arduino.h", "stdio.h", "stdlib.h", "string.h", "stdint.h", "stdbool.h", "stddef.h",
+    "math.h", "ctype.h", "limits.h", "inttypes.h", "assert.h", "time.h", "pins_arduino.h",
+];
+
+lazy_static::lazy_static! {
+    /// Index lookups by header, negative results included
+    static ref SEARCH_CACHE: Mutex<HashMap<String, Option<IndexLibrary>>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexLibrary {
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingLibrary {
+    pub header: String,
+    pub library: IndexLibrary,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingLibrariesEvent {
+    pub libraries: Vec<MissingLibrary>,
+    /// True when they are being installed before the build
+    pub installing: bool,
+}
+
+/// Headers provided by the libraries available when compiling for `fqbn`.
+async fn available_headers(toolchain: &dyn ArduinoToolchain, fqbn: &str, libs: &[String]) -> Result<HashSet<String>, String> {
+    let mut headers: HashSet<String> = toolchain.list_libraries(true, Some(fqbn)).await?
+        .into_iter()
+        .flat_map(|lib| lib.provides_includes)
+        .collect();
+
+    // Extension library folders passed with --libraries: <dir>/<Library>/[src/]*.h
+    for dir in libs {
+        for lib in fs::read_dir(dir).into_iter().flatten().flatten() {
+            for sub in [lib.path(), lib.path().join("src")] {
+                collect_headers(&sub, &mut headers);
+            }
+        }
+    }
+    Ok(headers)
+}
+
+fn collect_headers(dir: &Path, headers: &mut HashSet<String>) {
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.ends_with(".h") || name.ends_with(".hpp") {
+            headers.insert(name);
+        }
+    }
+}
+
+/// Looks up the index library providing `header`.
+async fn search_index(toolchain: &dyn ArduinoToolchain, header: &str) -> Option<IndexLibrary> {
+    if let Some(hit) = SEARCH_CACHE.lock().unwrap().get(header) {
+        return hit.clone();
+    }
+
+    // Don't cache failures, the index may just not be downloaded yet
+    let candidates: Vec<_> = toolchain.search_libraries(&format!("provides:{}", header)).await.ok()?
+        .into_iter()
+        .filter(|lib| lib.provides_includes.iter().any(|h| h == header))
+        .collect();
+    // Prefer a library named after the header, e.g. Servo for Servo.h
+    let stem = header.trim_end_matches(".h");
+    let hit = candidates.iter()
+        .find(|lib| lib.name.replace(' ', "_").eq_ignore_ascii_case(stem))
+        .or(candidates.first())
+        .map(|lib| IndexLibrary {
+            name: lib.name.clone(),
+            version: lib.latest.clone(),
+        });
+
+    SEARCH_CACHE.lock().unwrap().insert(header.to_string(), hit.clone());
+    hit
+}
+
+/// Index libraries for the headers of `code` that nothing installed provides.
+pub async fn find_missing_libraries(toolchain: &dyn ArduinoToolchain, code: &str, fqbn: &str, libs: &[String]) -> Result<Vec<MissingLibrary>, String> {
+    let headers: Vec<String> = included_headers(code)
+        .into_iter()
+        // Paths like "src/<ext>/x.h" or <avr/io.h> are sketch or toolchain headers
+        .filter(|h| !h.contains('/') && !SYSTEM_HEADERS.contains(&h.as_str()))
+        .collect();
+    if headers.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let available = available_headers(toolchain, fqbn, libs).await?;
+    let mut missing: Vec<MissingLibrary> = Vec::new();
+    for header in headers.into_iter().filter(|h| !available.contains(h)) {
+        if let Some(library) = search_index(toolchain, &header).await {
+            if !missing.iter().any(|m| m.library.name == library.name) {
+                missing.push(MissingLibrary { header, library });
+            }
+        }
+    }
+    Ok(missing)
+}
+
+/// Reports the missing libraries of the sketch and installs them when `auto_install` is set.
+/// Never fails the build, compiling shows the real error if a header is still missing.
+pub async fn check_sketch_libraries(window: &Window, code: &str, fqbn: &str, libs: &[String], auto_install: bool) {
+    let toolchain = toolchain::current(window.app_handle());
+    let missing = match find_missing_libraries(toolchain.as_ref(), code, fqbn, libs).await {
+        Ok(missing) => missing,
+        Err(e) => {
+            println!("[Arduino] ⚠️ Library check skipped: {}", e);
+            return;
+        }
+    };
+    if missing.is_empty() {
+        return;
+    }
+
+    let names: Vec<String> = missing.iter().map(|m| m.library.name.clone()).collect();
+    println!("[Arduino] 🔎 Missing libraries: {}", names.join(", "));
+    window.emit("arduino-missing-libraries", MissingLibrariesEvent { libraries: missing, installing: auto_install }).unwrap_or(());
+
+    if auto_install {
+        if let Err(e) = install_libraries(window, &names).await {
+            window.emit("arduino-output", format!("Library installation failed: {}", e)).unwrap_or(());
+        }
+    }
+}
+
+async fn install_libraries(window: &Window, names: &[String]) -> Result<(), String> {
+    let toolchain = toolchain::current(window.app_handle());
+    for name in names {
+        window.emit("arduino-output", format!("Installing library {}...", name)).unwrap_or(());
+        toolchain.install_library(name, None).await?;
+    }
+
+    // The extension loader caches the installed library names
+    let session = window.state::<crate::SessionState>();
+    *session.arduino_libraries_cache.lock().unwrap() = None;
+    Ok(())
+}
+
+/// Installs libraries offered through `arduino-missing-libraries`.
+#[tauri::command]
+pub async fn install_arduino_libraries(window: Window, libraries: Vec<String>) -> Result<(), String> {
+    install_libraries(&window, &libraries).await?;
+    window.emit("arduino-finished", format!("Installed {}", libraries.join(", "))).unwrap_or(());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use tauri::async_runtime::block_on;
+    use super::super::toolchain::{FakeToolchain, IndexEntry};
+    use super::*;
+
+    fn entry(name: &str, header: &str) -> IndexEntry {
+        IndexEntry {
+            name: name.to_string(),
+            latest: "1.0.0".to_string(),
+            versions: vec!["1.0.0".to_string()],
+            provides_includes: vec![header.to_string()],
+        }
+    }
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hanx_libraries_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn finds_the_index_library_of_each_missing_header() {
+        let fake = FakeToolchain::new();
+        fake.state().index.extend([
+            entry("Adafruit NeoPixel", "Adafruit_NeoPixel.h"),
+            entry("DHT sensor library", "DHT_Test.h"),
+            entry("DHT Test", "DHT_Test.h"),
+            entry("Bundled", "ExtensionLib.h"),
+        ]);
+        // An extension library folder passed with --libraries
+        let libs = scratch_dir("ext");
+        fs::create_dir_all(libs.join("ExtensionLib").join("src")).unwrap();
+        fs::write(libs.join("ExtensionLib").join("src").join("ExtensionLib.h"), "").unwrap();
+
+        let code = "#include <Arduino.h>\n#include <avr/io.h>\n#include <Servo.h>\n#include \"ExtensionLib.h\"\n\
+            #include <Adafruit_NeoPixel.h>\n#include <DHT_Test.h>\n#include <NotInTheIndex.h>\n";
+        let missing = block_on(find_missing_libraries(&fake, code, "arduino:avr:uno", &[libs.to_string_lossy().to_string()])).unwrap();
+
+        let found: Vec<(&str, &str)> = missing.iter().map(|m| (m.header.as_str(), m.library.name.as_str())).collect();
+        // The library named after the header wins
+        assert_eq!(found, [("Adafruit_NeoPixel.h", "Adafruit NeoPixel"), ("DHT_Test.h", "DHT Test")]);
+        let calls = fake.state().calls.clone();
+        assert_eq!(calls, [
+            "lib list",
+            "lib search provides:Adafruit_NeoPixel.h",
+            "lib search provides:DHT_Test.h",
+            "lib search provides:NotInTheIndex.h",
+        ]);
+        let _ = fs::remove_dir_all(&libs);
+    }
+
+    #[test]
+    fn sketches_without_library_headers_skip_the_toolchain() {
+        let fake = FakeToolchain::new();
+        let code = "#include <Arduino.h>\n#include \"src/ext/helper.h\"\nvoid setup() {}\n";
+
+        assert!(block_on(find_missing_libraries(&fake, code, "arduino:avr:uno", &[])).unwrap().is_empty());
+        assert!(fake.state().calls.is_empty());
+    }
+}
@@ -1,91 +1,153 @@
 // arduino.rs - Handle Arduino compilation and uploading via arduino-cli
 
-use tauri::{Window, Emitter};
-use std::process::{Command, Stdio};
-use std::io::{BufRead, BufReader, Write};
-use std::fs::File;
-use std::thread;
+use tauri::{AppHandle, Manager, Window, Emitter};
 use crate::cmd::serial::OPEN_PORT;
 use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-#[derive(Debug, Serialize, Deserialize)]
+pub mod daemon;
+pub mod usb_ids;
+pub mod sketch;
+pub mod emulator;
+pub mod simulator;
+pub mod profile;
+pub mod platformio;
+pub mod build_config;
+pub mod libraries;
+pub mod pins;
+pub mod uf2;
+pub mod toolchain;
+
+use self::usb_ids::{BoardCandidate, BoardConfidence};
+use self::sketch::prepare_sketch;
+use self::build_config::BuildConfig;
+use self::toolchain::{CompileRequest, UploadRequest};
+
+static WATCHING_BOARDS: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DetectedBoard {
     pub port: String,
     pub label: String,
     pub board_name: Option<String>,
     pub fqbn: Option<String>,
+    #[serde(default)]
+    pub vid: Option<String>,
+    #[serde(default)]
+    pub pid: Option<String>,
+    #[serde(default)]
+    pub serial_number: Option<String>,
+    /// Identifies the device for `remember_board_choice`
+    #[serde(default)]
+    pub choice_key: Option<String>,
+    #[serde(default)]
+    pub candidates: Vec<BoardCandidate>,
+    #[serde(default)]
+    pub confidence: BoardConfidence,
 }
 
-#[derive(Debug, Deserialize)]
-struct CliBoard {
-    matching_boards: Option<Vec<CliMatchingBoard>>,
-    port: CliPort,
-}
-
-#[derive(Debug, Deserialize)]
-struct CliMatchingBoard {
-    name: String,
-    fqbn: String,
-}
+impl DetectedBoard {
+    fn new(port: String, label: String, properties: &HashMap<String, String>, matches: Vec<BoardCandidate>) -> Self {
+        let prop = |key: &str| properties.get(key).filter(|v| !v.is_empty()).cloned();
+        let vid = prop("vid").map(|v| usb_ids::normalize_id(&v));
+        let pid = prop("pid").map(|v| usb_ids::normalize_id(&v));
+        let serial_number = prop("serialNumber");
+        let choice_key = usb_ids::choice_key(serial_number.as_deref(), vid.as_deref(), pid.as_deref(), &port);
+        let first = matches.first().cloned();
 
-#[derive(Debug, Deserialize)]
-struct CliPort {
-    address: String,
-    label: String,
+        DetectedBoard {
+            port,
+            label,
+            board_name: first.as_ref().map(|b| b.name.clone()),
+            fqbn: first.map(|b| b.fqbn),
+            vid,
+            pid,
+            serial_number,
+            choice_key,
+            candidates: matches,
+            confidence: BoardConfidence::None,
+        }
+    }
 }
 
-#[derive(Debug, Deserialize)]
-struct CliBoardList {
-    detected_ports: Option<Vec<CliBoard>>,
+#[derive(Debug, Clone, Serialize)]
+pub struct ArduinoProgrammer {
+    pub id: String,
+    pub name: String,
+    pub platform: String,
+    pub is_default: bool,
 }
 
 #[tauri::command]
-pub async fn compile_arduino(window: Window, code: String, fqbn: String, libs: Vec<String>) -> Result<(), String> {
-    let sketch_dir = prepare_sketch(&code)?;
+pub async fn compile_arduino(
+    window: Window,
+    code: String,
+    fqbn: String,
+    libs: Vec<String>,
+    project_path: Option<String>,
+) -> Result<(), String> {
+    let sketch_dir = prepare_sketch(window.app_handle(), &code)?;
     
     window.emit("arduino-output", format!("Compiling for {}...", fqbn)).unwrap_or(());
-    
-    let mut compile_cmd = Command::new("arduino-cli");
-    compile_cmd.arg("compile")
-        .arg("--fqbn")
-        .arg(&fqbn);
-    
-    for lib in libs {
-        compile_cmd.arg("--libraries").arg(lib);
-    }
-
-    let compile_output = compile_cmd.arg(sketch_dir.to_str().ok_or("Invalid path")?)
-        .output()
-        .map_err(|e| format!("Failed to execute arduino-cli: {}. Is it installed?", e))?;
-
-    if !compile_output.status.success() {
-        let stderr = String::from_utf8_lossy(&compile_output.stderr);
-        window.emit("arduino-stderr", stderr.to_string()).unwrap_or(());
-        return Err("Compilation failed".to_string());
-    }
+    compile_sketch(&window, &sketch_dir, &fqbn, &libs, project_path.as_deref()).await?;
     
     window.emit("arduino-output", "Compilation success!").unwrap_or(());
     window.emit("arduino-finished", "Done.").unwrap_or(());
     Ok(())
 }
 
-fn prepare_sketch(code: &str) -> Result<std::path::PathBuf, String> {
-    let mut temp_dir = std::env::temp_dir();
-    temp_dir.push("hanx_sketch");
-    if !temp_dir.exists() {
-        std::fs::create_dir(&temp_dir).map_err(|e| e.to_string())?;
+/// Compiles the prepared sketch with the current toolchain.
+/// Binaries are exported to `sketch::output_dir`, compiler errors are emitted on `arduino-stderr`.
+/// The project's build config is applied; returns the sketch.yaml profile it pinned for `fqbn`, if any.
+async fn compile_sketch(
+    window: &Window,
+    sketch_dir: &Path,
+    fqbn: &str,
+    libs: &[String],
+    project_path: Option<&str>,
+) -> Result<Option<String>, String> {
+    let output_dir = sketch::output_dir(fqbn);
+    let profile = profile::apply_profile(project_path, fqbn, sketch_dir)?;
+    let config = project_config(project_path)?;
+    let flags = config.flags(project_path);
+
+    // Profiles pin their own libraries
+    if profile.is_none() {
+        let code = std::fs::read_to_string(sketch_dir.join(format!("{}.ino", sketch::SKETCH_NAME))).unwrap_or_default();
+        libraries::check_sketch_libraries(window, &code, fqbn, libs, config.auto_install_libraries).await;
     }
-    let sketch_dir = temp_dir.clone();
-    temp_dir.push("hanx_sketch.ino");
 
-    let mut file = File::create(&temp_dir).map_err(|e| e.to_string())?;
-    file.write_all(code.as_bytes()).map_err(|e| e.to_string())?;
-    
-    Ok(sketch_dir)
+    let request = CompileRequest {
+        fqbn: fqbn.to_string(),
+        sketch_dir: sketch_dir.to_path_buf(),
+        libs: libs.to_vec(),
+        output_dir,
+        profile: profile.clone(),
+        flags,
+    };
+    toolchain::current(window.app_handle()).compile(&toolchain::window_output(window), &request).await?;
+    Ok(profile)
+}
+
+fn project_config(project_path: Option<&str>) -> Result<BuildConfig, String> {
+    match project_path.filter(|p| !p.is_empty()) {
+        Some(path) => BuildConfig::load(path),
+        None => Ok(BuildConfig::default()),
+    }
 }
 
 #[tauri::command]
-pub async fn upload_arduino(window: Window, code: String, port: String, fqbn: String, libs: Vec<String>) -> Result<(), String> {
+pub async fn upload_arduino(
+    window: Window,
+    code: String,
+    port: String,
+    fqbn: String,
+    libs: Vec<String>,
+    programmer: Option<String>,
+    project_path: Option<String>,
+) -> Result<(), String> {
     // 0. Close serial if open (avoids conflict with upload)
     {
         let mut open_port = OPEN_PORT.lock().unwrap();
@@ -94,120 +156,117 @@ pub async fn upload_arduino(window: Window, code: String, port: String, fqbn: St
     window.emit("serial-data", "\n[System] Auto-closed serial for upload.\n").unwrap_or(());
 
     // 1. Prepare and Compile
-    let sketch_dir = prepare_sketch(&code)?;
-    let sketch_file = sketch_dir.join("hanx_sketch.ino");
+    let sketch_dir = prepare_sketch(window.app_handle(), &code)?;
 
     window.emit("arduino-output", format!("Compiling for {}...", fqbn)).unwrap_or(());
+    let profile = compile_sketch(&window, &sketch_dir, &fqbn, &libs, project_path.as_deref()).await?;
     
-    let mut compile_cmd = Command::new("arduino-cli");
-    compile_cmd.arg("compile")
-        .arg("--fqbn")
-        .arg(&fqbn);
-    
-    for lib in libs {
-        compile_cmd.arg("--libraries").arg(lib);
+    let programmer = programmer.filter(|p| !p.is_empty());
+    match &programmer {
+        Some(p) => window.emit("arduino-output", format!("Compilation success! Uploading via programmer {}...", p)).unwrap_or(()),
+        None => window.emit("arduino-output", "Compilation success! Uploading...").unwrap_or(()),
     }
 
-    let compile_output = compile_cmd.arg(sketch_dir.to_str().ok_or("Invalid path")?)
-        .output()
-        .map_err(|e| format!("Failed to execute arduino-cli: {}. Is it installed?", e))?;
+    // 2. Upload
+    let request = UploadRequest {
+        fqbn,
+        sketch_dir,
+        port,
+        programmer,
+        profile,
+        verbose: project_config(project_path.as_deref())?.verbose,
+    };
+    let result = toolchain::current(window.app_handle()).upload(&toolchain::window_output(&window), &request).await;
+    finish(&window, "Upload", result)
+}
+
+/// Reports the outcome of `action` through `arduino-finished`.
+fn finish(window: &Window, action: &str, result: Result<(), String>) -> Result<(), String> {
+    let msg = match &result {
+        Ok(_) => format!("{} successful!", action),
+        Err(e) => format!("{} failed: {}", action, e),
+    };
+    window.emit("arduino-finished", msg).unwrap_or(());
+    result
+}
+
+#[tauri::command]
+pub async fn discover_arduino_boards(app_handle: AppHandle) -> Result<Vec<DetectedBoard>, String> {
+    let mut detected = toolchain::current(&app_handle).list_boards().await?;
+    usb_ids::identify(&app_handle, &mut detected);
+    Ok(detected)
+}
 
-    if !compile_output.status.success() {
-        let stderr = String::from_utf8_lossy(&compile_output.stderr);
-        window.emit("arduino-stderr", stderr.to_string()).unwrap_or(());
-        return Err("Compilation failed".to_string());
+#[tauri::command]
+pub async fn list_arduino_programmers(app_handle: AppHandle, fqbn: String) -> Result<Vec<ArduinoProgrammer>, String> {
+    Ok(toolchain::current(&app_handle).board_details(&fqbn).await?.programmers)
+}
+
+#[tauri::command]
+pub async fn burn_bootloader(window: Window, port: String, fqbn: String, programmer: String) -> Result<(), String> {
+    if programmer.is_empty() {
+        return Err("A programmer is required to burn the bootloader".to_string());
     }
-    
-    window.emit("arduino-output", "Compilation success! Uploading...").unwrap_or(());
 
-    // 2. Upload
-    let mut child = Command::new("arduino-cli")
-        .arg("upload")
-        .arg("-p")
-        .arg(&port)
-        .arg("--fqbn")
-        .arg(&fqbn)
-        .arg(sketch_file.to_str().ok_or("Invalid path")?)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|e| format!("Failed to start upload: {}. Is it installed?", e))?;
-
-    let stdout = child.stdout.take().ok_or("Failed to open stdout")?;
-    let stderr = child.stderr.take().ok_or("Failed to open stderr")?;
-
-    let window_clone_out = window.clone();
-    let window_clone_err = window.clone();
-    let window_clone_exit = window.clone();
-
-    // Stream stdout
-    thread::spawn(move || {
-        let reader = BufReader::new(stdout);
-        for line in reader.lines() {
-            if let Ok(l) = line {
-                window_clone_out.emit("arduino-output", l).unwrap_or(());
-            }
-        }
-    });
+    // The programmer may sit on the same serial port as the monitor (e.g. ArduinoISP)
+    {
+        let mut open_port = OPEN_PORT.lock().unwrap();
+        *open_port = None;
+    }
 
-    // Stream stderr
-    thread::spawn(move || {
-        let reader = BufReader::new(stderr);
-        for line in reader.lines() {
-            if let Ok(l) = line {
-                window_clone_err.emit("arduino-stderr", l).unwrap_or(());
-            }
-        }
-    });
+    window.emit("arduino-output", format!("Burning bootloader for {} via {}...", fqbn, programmer)).unwrap_or(());
 
-    // Wait for exit
-    thread::spawn(move || {
-        let status = child.wait();
-        let msg = match status {
-            Ok(s) => if s.success() { "Upload successful!".to_string() } else { format!("Upload failed with {}", s) },
-            Err(e) => format!("Process error: {}", e),
-        };
-        window_clone_exit.emit("arduino-finished", msg).unwrap_or(());
-    });
+    let result = toolchain::current(window.app_handle()).burn_bootloader(&toolchain::window_output(&window), &fqbn, &port, &programmer).await;
+    finish(&window, "Burn bootloader", result)
+}
 
-    Ok(())
+#[derive(Debug, Clone, Serialize)]
+pub struct BoardEvent {
+    pub event_type: String,
+    pub board: Option<DetectedBoard>,
+    pub error: Option<String>,
 }
 
+/// Streams board attach/detach events as `arduino-board-event`, if the toolchain supports it
+/// (arduino-cli needs the daemon backend).
 #[tauri::command]
-pub async fn discover_arduino_boards() -> Result<Vec<DetectedBoard>, String> {
-    let output = Command::new("arduino-cli")
-        .args(&["board", "list", "--format", "json"])
-        .output()
-        .map_err(|e| format!("Failed to execute arduino-cli: {}", e))?;
-
-    if !output.status.success() {
-        return Err("Failed to list boards via arduino-cli".to_string());
+pub async fn watch_arduino_boards(window: Window) -> Result<(), String> {
+    if WATCHING_BOARDS.swap(true, Ordering::SeqCst) {
+        return Ok(());
     }
 
-    let board_list: CliBoardList = serde_json::from_slice(&output.stdout)
-        .map_err(|e| format!("Failed to parse arduino-cli output: {}", e))?;
-
-    let mut detected = Vec::new();
-    if let Some(ports) = board_list.detected_ports {
-        for p in ports {
-            let mut board_name = None;
-            let mut fqbn = None;
+    let mut events = match toolchain::current(window.app_handle()).watch_boards().await {
+        Ok(events) => events,
+        Err(e) => {
+            WATCHING_BOARDS.store(false, Ordering::SeqCst);
+            return Err(e);
+        }
+    };
 
-            if let Some(matches) = p.matching_boards {
-                if !matches.is_empty() {
-                    board_name = Some(matches[0].name.clone());
-                    fqbn = Some(matches[0].fqbn.clone());
-                }
+    tauri::async_runtime::spawn(async move {
+        while let Some(mut event) = events.recv().await {
+            if let Some(b) = event.board.as_mut() {
+                usb_ids::identify(window.app_handle(), std::slice::from_mut(b));
             }
-
-            detected.push(DetectedBoard {
-                port: p.port.address,
-                label: p.port.label,
-                board_name,
-                fqbn,
-            });
+            window.emit("arduino-board-event", event).unwrap_or(());
         }
-    }
+        WATCHING_BOARDS.store(false, Ordering::SeqCst);
+    });
 
-    Ok(detected)
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn install_arduino_core(window: Window, platform: String, version: Option<String>) -> Result<(), String> {
+    window.emit("arduino-output", format!("Installing core {}...", platform)).unwrap_or(());
+    let version = version.filter(|v| !v.is_empty());
+    let result = toolchain::current(window.app_handle()).install_core(&toolchain::window_output(&window), &platform, version.as_deref()).await;
+    finish(&window, "Core install", result)
+}
+
+#[tauri::command]
+pub async fn update_arduino_index(window: Window) -> Result<(), String> {
+    window.emit("arduino-output", "Updating core and library indexes...").unwrap_or(());
+    let result = toolchain::current(window.app_handle()).update_index(&toolchain::window_output(&window)).await;
+    finish(&window, "Index update", result)
 }
@@ -0,0 +1,369 @@
+// pins.rs - Pin capabilities per board, for validating blocks in the editor
+//
+// Sources, in order of precedence: `arduino/pins.json` of Arduino extensions,
+// the bundled `pins.json`, and the installed core's `pins_arduino.h` for the
+// board's variant (located through `board details`). Each source only fills
+// what the previous ones left empty, so a dataset entry can describe just the
+// pins a variant header gets wrong.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use serde::{Deserialize, Deserializer, Serialize};
+use crate::cmd::sys::extensions::get_platform_extensions_dir;
+use super::toolchain::{self, ArduinoToolchain};
+
+const BUNDLED_PINS: &str = include_str!("pins.json");
+
+lazy_static::lazy_static! {
+    /// Parsed variant headers by board, `board details` is slow
+    static ref VARIANT_CACHE: Mutex<HashMap<String, Option<BoardPins>>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalogPin {
+    pub name: String,
+    pub pin: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct I2cPins {
+    pub sda: u8,
+    pub scl: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpiPins {
+    pub mosi: u8,
+    pub miso: u8,
+    pub sck: u8,
+    pub ss: u8,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UartPins {
+    pub name: String,
+    pub rx: u8,
+    pub tx: u8,
+}
+
+/// Pin lists accept numbers and ranges: `[0, 1, "2-13"]`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BoardPins {
+    pub fqbn: String,
+    pub name: Option<String>,
+    #[serde(deserialize_with = "pin_list")]
+    pub digital: Vec<u8>,
+    /// Digital pins that can't be outputs
+    #[serde(deserialize_with = "pin_list")]
+    pub input_only: Vec<u8>,
+    pub analog: Vec<AnalogPin>,
+    #[serde(deserialize_with = "pin_list")]
+    pub pwm: Vec<u8>,
+    #[serde(deserialize_with = "pin_list")]
+    pub interrupt: Vec<u8>,
+    pub i2c: Option<I2cPins>,
+    pub spi: Option<SpiPins>,
+    pub uart: Vec<UartPins>,
+    pub led_builtin: Option<u8>,
+    /// Where the data came from: "extension", "bundled", "variant"
+    #[serde(skip_deserializing)]
+    pub sources: Vec<String>,
+}
+
+fn pin_list<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum PinSpec {
+        Pin(u8),
+        Range(String),
+    }
+
+    let mut pins = Vec::new();
+    for spec in Vec::<PinSpec>::deserialize(deserializer)? {
+        match spec {
+            PinSpec::Pin(pin) => pins.push(pin),
+            PinSpec::Range(range) => {
+                let (from, to) = range.split_once('-').unwrap_or((&range, &range));
+                let from: u8 = from.trim().parse().map_err(serde::de::Error::custom)?;
+                let to: u8 = to.trim().parse().map_err(serde::de::Error::custom)?;
+                pins.extend(from..=to);
+            }
+        }
+    }
+    Ok(pins)
+}
+
+impl BoardPins {
+    /// Fills the fields still empty from `other`.
+    fn merge_missing(&mut self, other: BoardPins, source: &str) {
+        macro_rules! fill {
+            ($($field:ident),*) => {$(
+                if self.$field.is_empty() { self.$field = other.$field; }
+            )*};
+        }
+        macro_rules! fill_option {
+            ($($field:ident),*) => {$(
+                if self.$field.is_none() { self.$field = other.$field; }
+            )*};
+        }
+        fill!(digital, input_only, analog, pwm, interrupt, uart);
+        fill_option!(name, i2c, spi, led_builtin);
+        self.sources.push(source.to_string());
+    }
+
+    fn is_complete(&self) -> bool {
+        !self.digital.is_empty() && !self.analog.is_empty() && !self.pwm.is_empty() && !self.interrupt.is_empty()
+            && !self.uart.is_empty() && self.i2c.is_some() && self.spi.is_some() && self.led_builtin.is_some()
+    }
+}
+
+/// "arduino:avr:mega:cpu=atmega2560" -> "arduino:avr:mega"
+fn base_fqbn(fqbn: &str) -> String {
+    fqbn.split(':').take(3).collect::<Vec<_>>().join(":")
+}
+
+fn parse_dataset(text: &str) -> Result<Vec<BoardPins>, String> {
+    serde_json::from_str(text).map_err(|e| e.to_string())
+}
+
+fn dataset_entries(app_handle: &tauri::AppHandle, fqbn: &str) -> Vec<(BoardPins, &'static str)> {
+    let mut entries = Vec::new();
+
+    let extensions_dir = get_platform_extensions_dir(app_handle, "arduino");
+    for entry in fs::read_dir(&extensions_dir).into_iter().flatten().flatten() {
+        let file = entry.path().join("arduino").join("pins.json");
+        if !file.exists() {
+            continue;
+        }
+        match fs::read_to_string(&file).map_err(|e| e.to_string()).and_then(|s| parse_dataset(&s)) {
+            Ok(boards) => entries.extend(boards.into_iter().filter(|b| base_fqbn(&b.fqbn) == fqbn).map(|b| (b, "extension"))),
+            Err(e) => println!("[Arduino] ⚠️ Ignoring invalid {:?}: {}", file, e),
+        }
+    }
+
+    match parse_dataset(BUNDLED_PINS) {
+        Ok(boards) => entries.extend(boards.into_iter().filter(|b| base_fqbn(&b.fqbn) == fqbn).map(|b| (b, "bundled"))),
+        Err(e) => println!("[Arduino] ❌ Failed to parse bundled pin table: {}", e),
+    }
+    entries
+}
+
+/// Finds the board's `pins_arduino.h` through `board details` and reads what it can from it.
+async fn variant_pins(toolchain: &dyn ArduinoToolchain, fqbn: &str) -> Option<BoardPins> {
+    if let Some(cached) = VARIANT_CACHE.lock().unwrap().get(fqbn) {
+        return cached.clone();
+    }
+
+    // Not cached on failure, the core may get installed later
+    let details = toolchain.board_details(fqbn).await.ok()?;
+    let properties = &details.build_properties;
+
+    let header = properties.get("build.variant.path").map(PathBuf::from)
+        .or_else(|| {
+            let variant = properties.get("build.variant").filter(|v| !v.contains(':'))?;
+            Some(PathBuf::from(properties.get("runtime.platform.path")?).join("variants").join(variant))
+        })
+        .map(|dir| dir.join("pins_arduino.h"));
+    let pins = header.and_then(|h| fs::read_to_string(h).ok()).map(|text| {
+        let mut pins = parse_variant_header(&text);
+        pins.name = Some(details.name.clone()).filter(|n| !n.is_empty());
+        pins
+    });
+
+    VARIANT_CACHE.lock().unwrap().insert(fqbn.to_string(), pins.clone());
+    pins
+}
+
+/// Reads `#define NAME value` and `static const uint8_t NAME = value;` pins from a variant header.
+fn parse_variant_header(text: &str) -> BoardPins {
+    let mut defines: HashMap<String, String> = HashMap::new();
+    let mut macros: HashMap<String, String> = HashMap::new();
+    let mut lines = text.lines();
+    while let Some(line) = lines.next() {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("#define") {
+            // Function-like macros may span several lines
+            let mut body = rest.trim().to_string();
+            while body.ends_with('\\') {
+                body.pop();
+                body.push_str(lines.next().unwrap_or(""));
+            }
+            if let Some((name, value)) = body.split_once(|c: char| c.is_whitespace()) {
+                match name.split_once('(') {
+                    Some((macro_name, _)) => macros.insert(macro_name.to_string(), body[name.len()..].to_string()),
+                    None => defines.insert(name.to_string(), value.trim().to_string()),
+                };
+            } else if let Some((macro_name, _)) = body.split_once('(') {
+                macros.insert(macro_name.to_string(), body.clone());
+            }
+        } else if let Some(rest) = line.strip_prefix("static const uint8_t") {
+            if let Some((name, value)) = rest.split_once('=') {
+                defines.insert(name.trim().to_string(), value.trim().trim_end_matches(';').trim().to_string());
+            }
+        }
+    }
+
+    let value = |name: &str| -> Option<u8> {
+        let mut current = defines.get(name)?;
+        for _ in 0..4 {
+            let cleaned = current.trim_matches(|c: char| c == '(' || c == ')' || c.is_whitespace());
+            if let Ok(n) = cleaned.parse() {
+                return Some(n);
+            }
+            current = defines.get(cleaned)?;
+        }
+        None
+    };
+    let first = |names: &[&str]| names.iter().find_map(|n| value(n));
+
+    let mut pins = BoardPins::default();
+    let digital_count = value("NUM_DIGITAL_PINS").unwrap_or(0);
+    pins.digital = (0..digital_count).collect();
+    // Some cores number analog pins sparsely (ESP32 has A0, A3-A7, A10-A19)
+    for i in 0..value("NUM_ANALOG_INPUTS").unwrap_or(0).max(20) {
+        let name = format!("A{}", i);
+        if let Some(pin) = first(&[name.as_str(), format!("PIN_A{}", i).as_str()]) {
+            pins.analog.push(AnalogPin { name, pin });
+        }
+    }
+    pins.led_builtin = first(&["LED_BUILTIN", "PIN_LED_BUILTIN"]);
+    if let (Some(sda), Some(scl)) = (first(&["PIN_WIRE_SDA", "SDA"]), first(&["PIN_WIRE_SCL", "SCL"])) {
+        pins.i2c = Some(I2cPins { sda, scl });
+    }
+    if let (Some(mosi), Some(miso), Some(sck), Some(ss)) = (
+        first(&["PIN_SPI_MOSI", "MOSI"]),
+        first(&["PIN_SPI_MISO", "MISO"]),
+        first(&["PIN_SPI_SCK", "SCK"]),
+        first(&["PIN_SPI_SS", "SS"]),
+    ) {
+        pins.spi = Some(SpiPins { mosi, miso, sck, ss });
+    }
+    if let (Some(rx), Some(tx)) = (first(&["PIN_SERIAL_RX", "RX"]), first(&["PIN_SERIAL_TX", "TX"])) {
+        pins.uart.push(UartPins { name: "Serial".to_string(), rx, tx });
+    }
+
+    let limit = if digital_count > 0 { digital_count } else { u8::MAX };
+    if let Some(body) = macros.get("digitalPinHasPWM") {
+        pins.pwm = pins_matching(body, limit);
+    }
+    if let Some(body) = macros.get("digitalPinToInterrupt") {
+        pins.interrupt = pins_matching(body, limit);
+    }
+    pins
+}
+
+/// Pins a macro like `((p) == 3 || ((p) >= 9 && (p) <= 11))` or `(p < 34)` tests for.
+fn pins_matching(body: &str, limit: u8) -> Vec<u8> {
+    let mut comparisons: Vec<(&str, u8)> = Vec::new();
+    let bytes = body.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        let op = match (bytes[i], bytes.get(i + 1)) {
+            (b'=', Some(b'=')) => "==",
+            (b'>', Some(b'=')) => ">=",
+            (b'<', Some(b'=')) => "<=",
+            (b'<', Some(b'<')) | (b'>', Some(b'>')) => {
+                i += 2;
+                continue;
+            }
+            (b'<', _) => "<",
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        i += op.len();
+        let digits: String = body[i..].trim_start().chars().take_while(|c| c.is_ascii_digit()).collect();
+        if let Ok(n) = digits.parse() {
+            comparisons.push((op, n));
+        }
+    }
+
+    let mut pins = Vec::new();
+    let mut iter = comparisons.into_iter().peekable();
+    while let Some((op, n)) = iter.next() {
+        match op {
+            "==" => pins.push(n),
+            ">=" => {
+                if let Some(&("<=", to)) = iter.peek() {
+                    pins.extend(n..=to);
+                    iter.next();
+                }
+            }
+            "<" => pins.extend(0..n),
+            _ => {}
+        }
+    }
+    pins.retain(|p| *p < limit);
+    pins.sort_unstable();
+    pins.dedup();
+    pins
+}
+
+/// Pin capabilities of the board, for checks like "analogWrite needs a PWM pin".
+#[tauri::command]
+pub async fn get_board_pins(app_handle: tauri::AppHandle, fqbn: String) -> Result<BoardPins, String> {
+    let base = base_fqbn(&fqbn);
+    let mut pins = BoardPins { fqbn: fqbn.clone(), ..Default::default() };
+
+    for (entry, source) in dataset_entries(&app_handle, &base) {
+        pins.merge_missing(entry, source);
+    }
+    if !pins.is_complete() {
+        if let Some(variant) = variant_pins(toolchain::current(&app_handle).as_ref(), &fqbn).await {
+            pins.merge_missing(variant, "variant");
+        }
+    }
+
+    if pins.sources.is_empty() {
+        return Err(format!("No pin information for {}", fqbn));
+    }
+    Ok(pins)
+}
+
+#[cfg(test)]
+mod tests {
+    use tauri::async_runtime::block_on;
+    use super::super::toolchain::{BoardDetails, FakeToolchain};
+    use super::*;
+
+    const VARIANT: &str = "#define NUM_DIGITAL_PINS 20\n\
+        #define NUM_ANALOG_INPUTS 6\n\
+        #define LED_BUILTIN 13\n\
+        #define digitalPinHasPWM(p) ((p) == 3 || (p) == 5 || ((p) >= 9 && (p) <= 11))\n\
+        static const uint8_t A0 = 14;\n\
+        static const uint8_t A1 = 15;\n";
+
+    #[test]
+    fn reads_the_variant_header_board_details_point_to() {
+        let dir = std::env::temp_dir().join(format!("hanx_pins_variant_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("pins_arduino.h"), VARIANT).unwrap();
+        let fake = FakeToolchain::new();
+        let fqbn = "fake:avr:variant";
+
+        // Without the core there are no details, and nothing is cached
+        assert!(block_on(variant_pins(&fake, fqbn)).is_none());
+        let details = BoardDetails {
+            name: "Fake Board".to_string(),
+            build_properties: HashMap::from([("build.variant.path".to_string(), dir.to_string_lossy().to_string())]),
+            ..Default::default()
+        };
+        fake.state().details.insert(fqbn.to_string(), details);
+
+        let pins = block_on(variant_pins(&fake, fqbn)).unwrap();
+        assert_eq!(pins.name.as_deref(), Some("Fake Board"));
+        assert_eq!(pins.digital.len(), 20);
+        assert_eq!(pins.led_builtin, Some(13));
+        assert_eq!(pins.pwm, [3, 5, 9, 10, 11]);
+        assert_eq!(pins.analog.iter().map(|a| (a.name.as_str(), a.pin)).collect::<Vec<_>>(), [("A0", 14), ("A1", 15)]);
+
+        // Cached from now on
+        block_on(variant_pins(&fake, fqbn)).unwrap();
+        assert_eq!(fake.state().calls, [format!("board details {}", fqbn), format!("board details {}", fqbn)]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,194 @@
+// platformio.rs - Export the generated sketch as a PlatformIO project
+//
+// Writes `platformio.ini`, `src/main.cpp` and the extension helpers so the
+// project opens in VS Code + PlatformIO. Libraries the extensions used by the
+// sketch install (`lib/libraries.txt`, manifest `dependencies.arduino`)
+// become `lib_deps`; PlatformIO fetches them on the first build.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use crate::cmd::sys::extensions::{get_platform_extensions_dir, ExtensionMetadata};
+use super::simulator::ino_to_cpp;
+use super::sketch::{copy_extension_sources, uses_extension};
+
+/// Libraries that come with the Arduino framework of every PlatformIO platform
+const FRAMEWORK_LIBRARIES: [&str; 5] = ["SPI", "Wire", "EEPROM", "SoftwareSerial", "HID"];
+
+#[derive(Debug, Serialize)]
+pub struct PlatformioExport {
+    pub dir: String,
+    pub env: String,
+    pub lib_deps: Vec<String>,
+    pub extensions: Vec<String>,
+}
+
+struct PioBoard {
+    platform: &'static str,
+    board: String,
+}
+
+/// Maps an FQBN (including its menu options) to a PlatformIO platform and board id.
+fn map_fqbn(fqbn: &str) -> Option<PioBoard> {
+    let mut parts = fqbn.splitn(4, ':');
+    let vendor = parts.next()?;
+    let arch = parts.next()?;
+    let board = parts.next()?;
+    let options = parts.next().unwrap_or("");
+    let option = |key: &str| {
+        options.split(',')
+            .find_map(|opt| opt.strip_prefix(key)?.strip_prefix('='))
+            .map(|v| v.to_string())
+    };
+
+    let (platform, pio_board) = match (vendor, arch, board) {
+        ("arduino", "avr", "uno") => ("atmelavr", "uno".to_string()),
+        ("arduino", "avr", "nano") => ("atmelavr", match option("cpu").as_deref() {
+            Some("atmega328old") => "nanoatmega328".to_string(),
+            Some("atmega168") => "nanoatmega168".to_string(),
+            _ => "nanoatmega328new".to_string(),
+        }),
+        ("arduino", "avr", "mega") => ("atmelavr", match option("cpu").as_deref() {
+            Some("atmega1280") => "megaatmega1280".to_string(),
+            _ => "megaatmega2560".to_string(),
+        }),
+        ("arduino", "avr", "pro") => ("atmelavr", format!("pro{}", option("cpu").unwrap_or_else(|| "16MHzatmega328".to_string()))),
+        ("arduino", "avr", "mini") => ("atmelavr", "miniatmega328".to_string()),
+        ("arduino", "avr", "leonardo") => ("atmelavr", "leonardo".to_string()),
+        ("arduino", "avr", "micro") => ("atmelavr", "micro".to_string()),
+        ("arduino", "avr", "yun") => ("atmelavr", "yun".to_string()),
+        ("arduino", "megaavr", "uno2018") => ("atmelmegaavr", "uno_wifi_rev2".to_string()),
+        ("arduino", "megaavr", "nona4809") => ("atmelmegaavr", "nano_every".to_string()),
+        ("arduino", "samd", "mkr1000") => ("atmelsam", "mkr1000USB".to_string()),
+        ("arduino", "samd", "nano_33_iot") => ("atmelsam", "nano_33_iot".to_string()),
+        ("arduino", "samd", "arduino_zero_edbg") => ("atmelsam", "zero".to_string()),
+        ("arduino", "renesas_uno", "unor4wifi") => ("renesas-ra", "uno_r4_wifi".to_string()),
+        ("arduino", "renesas_uno", "minima") => ("renesas-ra", "uno_r4_minima".to_string()),
+        ("arduino", "mbed_nano", "nano33ble") => ("nordicnrf52", "nano33ble".to_string()),
+        ("arduino", "mbed_rp2040", "pico") | ("rp2040", "rp2040", "rpipico") => ("raspberrypi", "pico".to_string()),
+        ("esp32", "esp32", "esp32") => ("espressif32", "esp32dev".to_string()),
+        ("esp32", "esp32", "esp32s3") => ("espressif32", "esp32-s3-devkitc-1".to_string()),
+        ("esp32", "esp32", "esp32c3") => ("espressif32", "esp32-c3-devkitm-1".to_string()),
+        ("esp32", "esp32", b) => ("espressif32", b.to_string()),
+        ("esp8266", "esp8266", "generic") => ("espressif8266", "esp01_1m".to_string()),
+        ("esp8266", "esp8266", b) => ("espressif8266", b.to_string()),
+        _ => return None,
+    };
+    Some(PioBoard { platform, board: pio_board })
+}
+
+/// Converts "Name", "Name (1.2.3)", "Name@1.2.3" or "Name@>=1.0, <2.0" to a
+/// PlatformIO library spec.
+fn to_lib_dep(entry: &str) -> Option<String> {
+    let entry = entry.trim();
+    if entry.is_empty() || entry.starts_with('#') {
+        return None;
+    }
+    // Split like `library_requirements::parse_requirement`
+    let (name, version) = if let Some((name, version)) = entry.rsplit_once('@') {
+        (name.trim(), Some(version.trim()))
+    } else if let (Some(open), true) = (entry.rfind('('), entry.ends_with(')')) {
+        (entry[..open].trim(), Some(entry[open + 1..entry.len() - 1].trim()))
+    } else {
+        (entry, None)
+    };
+    if FRAMEWORK_LIBRARIES.contains(&name) {
+        return None;
+    }
+    Some(match version.filter(|v| !v.is_empty()) {
+        // PlatformIO splits lib_deps entries on ", ", ranges must not contain it
+        Some(v) => format!("{} @ {}", name, v.split(',').map(str::trim).collect::<Vec<_>>().join(",")),
+        None => name.to_string(),
+    })
+}
+
+/// Libraries required by the Arduino extensions `code` uses, the same ones
+/// `copy_extension_sources` copies.
+fn extension_lib_deps(app_handle: &tauri::AppHandle, code: &str) -> Vec<String> {
+    let mut deps: Vec<String> = Vec::new();
+    let extensions_dir = get_platform_extensions_dir(app_handle, "arduino");
+    for entry in fs::read_dir(&extensions_dir).into_iter().flatten().flatten() {
+        if !uses_extension(code, &entry.file_name().to_string_lossy()) {
+            continue;
+        }
+        let path = entry.path();
+        let mut entries = Vec::new();
+
+        if let Ok(content) = fs::read_to_string(path.join("lib").join("libraries.txt")) {
+            entries.extend(content.lines().map(|l| l.to_string()));
+        }
+        let metadata = fs::read_to_string(path.join("manifest.json")).ok()
+            .and_then(|s| serde_json::from_str::<ExtensionMetadata>(&s).ok());
+        if let Some(arduino) = metadata.and_then(|m| m.dependencies).and_then(|d| d.arduino) {
+            entries.extend(arduino);
+        }
+
+        for dep in entries.iter().filter_map(|e| to_lib_dep(e)) {
+            if !deps.contains(&dep) {
+                deps.push(dep);
+            }
+        }
+    }
+    deps
+}
+
+/// Baud rate of the first `Serial.begin()` call, for the PlatformIO serial monitor.
+fn monitor_speed(code: &str) -> Option<u32> {
+    let start = code.find("Serial.begin(")? + "Serial.begin(".len();
+    let digits: String = code[start..].chars().skip_while(|c| c.is_whitespace()).take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok()
+}
+
+/// Writes a PlatformIO project for `fqbn` into `dest_dir`.
+#[tauri::command]
+pub fn export_platformio_project(app_handle: tauri::AppHandle, code: String, fqbn: String, dest_dir: String) -> Result<PlatformioExport, String> {
+    let board = map_fqbn(&fqbn)
+        .ok_or_else(|| format!("Board {} has no PlatformIO equivalent", fqbn))?;
+    let dest = PathBuf::from(&dest_dir);
+    let src_dir = dest.join("src");
+    fs::create_dir_all(&src_dir).map_err(|e| format!("Failed to create {:?}: {}", src_dir, e))?;
+
+    // Helpers are included as "src/<id>/..." relative to the sketch, main.cpp already lives in src/
+    let extensions = copy_extension_sources(&app_handle, &code, &src_dir)?;
+    let lib_deps = extension_lib_deps(&app_handle, &code);
+    let mut code = code;
+    for id in &extensions {
+        code = code.replace(&format!("\"src/{}/", id), &format!("\"{}/", id));
+    }
+    fs::write(src_dir.join("main.cpp"), ino_to_cpp(&code, None)).map_err(|e| e.to_string())?;
+
+    let env = board.board.clone();
+    fs::write(dest.join("platformio.ini"), platformio_ini(&fqbn, &board, &lib_deps, monitor_speed(&code)))
+        .map_err(|e| e.to_string())?;
+    write_if_missing(&dest.join(".gitignore"), ".pio\n.vscode\n")?;
+    for dir in ["include", "lib"] {
+        fs::create_dir_all(dest.join(dir)).map_err(|e| e.to_string())?;
+    }
+
+    println!("[Arduino] 📤 Exported PlatformIO project to {:?} (env {})", dest, env);
+    Ok(PlatformioExport { dir: dest_dir, env, lib_deps, extensions })
+}
+
+fn platformio_ini(fqbn: &str, board: &PioBoard, lib_deps: &[String], monitor_speed: Option<u32>) -> String {
+    let mut ini = format!(
+        "; Exported from HanX Blockly ({})\n\n[platformio]\ndefault_envs = {}\n\n[env:{}]\nplatform = {}\nboard = {}\nframework = arduino\n",
+        fqbn, board.board, board.board, board.platform, board.board
+    );
+    if let Some(speed) = monitor_speed {
+        ini.push_str(&format!("monitor_speed = {}\n", speed));
+    }
+    if !lib_deps.is_empty() {
+        ini.push_str("lib_deps =\n");
+        for dep in lib_deps {
+            ini.push_str(&format!("    {}\n", dep));
+        }
+    }
+    ini
+}
+
+fn write_if_missing(path: &Path, content: &str) -> Result<(), String> {
+    if path.exists() {
+        return Ok(());
+    }
+    fs::write(path, content).map_err(|e| e.to_string())
+}
@@ -0,0 +1,229 @@
+// profile.rs - Reproducible builds with sketch.yaml profiles
+//
+// A project's `sketch.yaml` (in its data dir) pins the platform and library
+// versions per board. When it has a profile for the board being built, the
+// file is copied into the sketch and arduino-cli builds with `--profile`,
+// fetching the pinned versions if they aren't the installed ones.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::cmd::sys::project::project_data_dir;
+use super::sketch::included_headers;
+use super::toolchain::{self, InstalledLibrary};
+
+pub const PROJECT_FILE: &str = "sketch.yaml";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SketchProject {
+    #[serde(default)]
+    profiles: BTreeMap<String, SketchProfile>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default_profile: Option<String>,
+    /// Keys we don't manage (default_fqbn, default_port, ...) are kept as-is
+    #[serde(flatten)]
+    other: BTreeMap<String, serde_yaml::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SketchProfile {
+    fqbn: String,
+    #[serde(default)]
+    platforms: Vec<ProfilePlatform>,
+    /// "Name (version)" entries, or local `dir:` libraries
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    libraries: Vec<serde_yaml::Value>,
+    #[serde(flatten)]
+    other: BTreeMap<String, serde_yaml::Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ProfilePlatform {
+    platform: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    platform_index_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyStatus {
+    /// The installed version is the pinned one
+    Ok,
+    /// Another version is installed, the build will fetch the pinned one
+    Different,
+    Missing,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProfileDependency {
+    pub profile: String,
+    pub kind: String,
+    pub name: String,
+    pub required: Option<String>,
+    pub installed: Option<String>,
+    pub status: DependencyStatus,
+}
+
+fn project_file(project_path: &str) -> Result<PathBuf, String> {
+    Ok(project_data_dir(project_path)?.join(PROJECT_FILE))
+}
+
+fn load_project(file: &Path) -> Result<SketchProject, String> {
+    if !file.exists() {
+        return Ok(SketchProject::default());
+    }
+    let text = fs::read_to_string(file).map_err(|e| e.to_string())?;
+    serde_yaml::from_str(&text).map_err(|e| format!("Invalid {}: {}", PROJECT_FILE, e))
+}
+
+/// Splits "Servo (1.2.1)" into its name and version.
+fn split_versioned(entry: &str) -> (String, Option<String>) {
+    let entry = entry.trim();
+    match (entry.rfind('('), entry.ends_with(')')) {
+        (Some(open), true) => (
+            entry[..open].trim().to_string(),
+            Some(entry[open + 1..entry.len() - 1].trim().to_string()),
+        ),
+        _ => (entry.to_string(), None),
+    }
+}
+
+fn platform_id(fqbn: &str) -> String {
+    fqbn.split(':').take(2).collect::<Vec<_>>().join(":")
+}
+
+async fn installed_platforms(app_handle: &tauri::AppHandle) -> Result<HashMap<String, String>, String> {
+    Ok(toolchain::current(app_handle).list_cores().await?
+        .into_iter()
+        .map(|core| (core.id, core.version))
+        .collect())
+}
+
+/// User-installed libraries; the ones bundled with a platform come with its pinned version.
+async fn installed_libraries(app_handle: &tauri::AppHandle) -> Result<Vec<InstalledLibrary>, String> {
+    toolchain::current(app_handle).list_libraries(false, None).await
+}
+
+/// Copies the project's sketch.yaml into the sketch folder and returns the
+/// profile to build `fqbn` with, if the project has one for that board.
+pub fn apply_profile(project_path: Option<&str>, fqbn: &str, sketch_dir: &Path) -> Result<Option<String>, String> {
+    // The sketch folder is shared by all projects, drop a previous project's file
+    let target = sketch_dir.join(PROJECT_FILE);
+    if target.exists() {
+        fs::remove_file(&target).map_err(|e| e.to_string())?;
+    }
+
+    let Some(project_path) = project_path.filter(|p| !p.is_empty()) else {
+        return Ok(None);
+    };
+    let file = project_file(project_path)?;
+    let project = load_project(&file)?;
+
+    let default = project.default_profile.as_ref()
+        .filter(|name| project.profiles.get(*name).map_or(false, |p| p.fqbn == fqbn));
+    let profile = default.or_else(|| {
+        project.profiles.iter().find(|(_, p)| p.fqbn == fqbn).map(|(name, _)| name)
+    });
+
+    match profile {
+        Some(name) => {
+            fs::copy(&file, &target).map_err(|e| format!("Failed to copy {}: {}", PROJECT_FILE, e))?;
+            println!("[Arduino] 📌 Building with profile {}", name);
+            Ok(Some(name.clone()))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Pins the installed platform and the libraries the code includes for `fqbn`
+/// in the project's sketch.yaml. Returns the profile name.
+#[tauri::command]
+pub async fn generate_arduino_profile(app_handle: tauri::AppHandle, project_path: String, code: String, fqbn: String) -> Result<String, String> {
+    let platform = platform_id(&fqbn);
+    let platform_version = installed_platforms(&app_handle).await?
+        .remove(&platform)
+        .ok_or_else(|| format!("Platform {} is not installed", platform))?;
+
+    let headers = included_headers(&code);
+    let libraries = installed_libraries(&app_handle).await?
+        .into_iter()
+        .filter(|lib| lib.provides_includes.iter().any(|inc| headers.contains(inc)))
+        .map(|lib| serde_yaml::Value::String(format!("{} ({})", lib.name, lib.version)))
+        .collect();
+
+    let name = fqbn.split(':').nth(2).unwrap_or("default").to_string();
+    let file = project_file(&project_path)?;
+    let mut project = load_project(&file)?;
+    let other = project.profiles.remove(&name).map(|p| p.other).unwrap_or_default();
+    project.profiles.insert(name.clone(), SketchProfile {
+        fqbn,
+        platforms: vec![ProfilePlatform {
+            platform: format!("{} ({})", platform, platform_version),
+            platform_index_url: None,
+        }],
+        libraries,
+        other,
+    });
+    project.default_profile.get_or_insert_with(|| name.clone());
+
+    if let Some(dir) = file.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let yaml = serde_yaml::to_string(&project).map_err(|e| e.to_string())?;
+    fs::write(&file, yaml).map_err(|e| e.to_string())?;
+
+    println!("[Arduino] 📌 Saved profile {} to {:?}", name, file);
+    Ok(name)
+}
+
+/// Compares every pinned platform and library of the project against the installed versions.
+#[tauri::command]
+pub async fn check_arduino_profile(app_handle: tauri::AppHandle, project_path: String) -> Result<Vec<ProfileDependency>, String> {
+    let project = load_project(&project_file(&project_path)?)?;
+    if project.profiles.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let platforms = installed_platforms(&app_handle).await?;
+    let libraries: HashMap<String, String> = installed_libraries(&app_handle).await?
+        .into_iter()
+        .map(|lib| (lib.name, lib.version))
+        .collect();
+
+    let status = |required: &Option<String>, installed: &Option<String>| match (required, installed) {
+        (_, None) => DependencyStatus::Missing,
+        (Some(r), Some(i)) if r != i => DependencyStatus::Different,
+        _ => DependencyStatus::Ok,
+    };
+
+    let mut result = Vec::new();
+    for (profile, p) in &project.profiles {
+        for platform in &p.platforms {
+            let (name, required) = split_versioned(&platform.platform);
+            let installed = platforms.get(&name).cloned();
+            result.push(ProfileDependency {
+                profile: profile.clone(),
+                kind: "platform".to_string(),
+                status: status(&required, &installed),
+                name,
+                required,
+                installed,
+            });
+        }
+        // Local `dir:` libraries aren't versioned
+        for entry in p.libraries.iter().filter_map(|l| l.as_str()) {
+            let (name, required) = split_versioned(entry);
+            let installed = libraries.get(&name).cloned();
+            result.push(ProfileDependency {
+                profile: profile.clone(),
+                kind: "library".to_string(),
+                status: status(&required, &installed),
+                name,
+                required,
+                installed,
+            });
+        }
+    }
+    Ok(result)
+}
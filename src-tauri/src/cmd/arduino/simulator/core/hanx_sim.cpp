@@ -0,0 +1,416 @@
+// hanx_sim.cpp - Mock Arduino core runtime for host simulation
+//
+// Time is virtual: delay() returns immediately and every API call advances the
+// clock by roughly what it costs on an Uno, so a sketch runs its configured
+// duration in a fraction of a second. Serial output goes to stdout, pin
+// activity to stderr as "@hanx:pin <ms> <pin> <mode> <value>" lines.
+//
+// Usage: sketch [--duration-ms N] [--digital PIN=0|1]... [--analog CH=0..1023]...
+// Serial input is read from stdin.
+
+#include "Arduino.h"
+#include <stdio.h>
+#include <ctype.h>
+#include <algorithm>
+
+#define PIN_COUNT 22
+
+HardwareSerial Serial;
+
+static uint64_t now_us = 0;
+static uint64_t limit_us = 10ULL * 1000 * 1000;
+
+static uint8_t pin_modes[PIN_COUNT];
+static uint8_t out_levels[PIN_COUNT];
+static int input_levels[PIN_COUNT];
+static int analog_inputs[8];
+static uint64_t tone_end_us[PIN_COUNT];
+
+// What was last reported for each pin, to only emit changes
+static const char *reported_mode[PIN_COUNT];
+static int reported_value[PIN_COUNT];
+
+static std::string serial_in;
+static size_t serial_pos = 0;
+static unsigned long long rng_state = 1;
+
+static void finish() {
+    fflush(stdout);
+    fprintf(stderr, "@hanx:end %llu\n", (unsigned long long)(now_us / 1000));
+    fflush(stderr);
+    exit(0);
+}
+
+static void report(uint8_t pin, const char *mode, int value) {
+    if (pin >= PIN_COUNT) return;
+    if (reported_mode[pin] && strcmp(reported_mode[pin], mode) == 0 && reported_value[pin] == value) return;
+    reported_mode[pin] = mode;
+    reported_value[pin] = value;
+    fflush(stdout);
+    fprintf(stderr, "@hanx:pin %llu %d %s %d\n", (unsigned long long)(now_us / 1000), pin, mode, value);
+}
+
+static void advance(uint64_t us) {
+    now_us += us;
+    for (int pin = 0; pin < PIN_COUNT; pin++) {
+        if (tone_end_us[pin] && now_us >= tone_end_us[pin]) noTone(pin);
+    }
+    if (now_us >= limit_us) finish();
+}
+
+// ----- digital / analog I/O -----
+
+void pinMode(uint8_t pin, uint8_t mode) {
+    if (pin >= PIN_COUNT) return;
+    pin_modes[pin] = mode;
+    if (mode == OUTPUT) {
+        report(pin, "output", out_levels[pin]);
+    } else {
+        // Writing HIGH to an input enables the pull-up, like on the AVR
+        out_levels[pin] = mode == INPUT_PULLUP;
+        report(pin, "input", digitalRead(pin));
+    }
+}
+
+void digitalWrite(uint8_t pin, uint8_t value) {
+    advance(4);
+    if (pin >= PIN_COUNT) return;
+    out_levels[pin] = value ? HIGH : LOW;
+    if (pin_modes[pin] == OUTPUT) {
+        report(pin, "output", out_levels[pin]);
+    }
+}
+
+int digitalRead(uint8_t pin) {
+    advance(4);
+    if (pin >= PIN_COUNT) return LOW;
+    if (pin_modes[pin] == OUTPUT) return out_levels[pin];
+    if (input_levels[pin] >= 0) return input_levels[pin];
+    return out_levels[pin];
+}
+
+int analogRead(uint8_t pin) {
+    advance(112);
+    int channel = pin >= A0 ? pin - A0 : pin;
+    return channel >= 0 && channel < 8 ? analog_inputs[channel] : 0;
+}
+
+void analogWrite(uint8_t pin, int value) {
+    advance(4);
+    if (pin >= PIN_COUNT) return;
+    pin_modes[pin] = OUTPUT;
+    value = constrain(value, 0, 255);
+    if (value == 0 || value == 255) {
+        out_levels[pin] = value ? HIGH : LOW;
+        report(pin, "output", out_levels[pin]);
+    } else {
+        report(pin, "pwm", value);
+    }
+}
+
+void analogReference(uint8_t mode) { (void)mode; }
+
+// ----- time -----
+
+// Reading the clock costs time too, or `while (millis() - t < 500) {}` never ends
+unsigned long millis() {
+    advance(4);
+    return (unsigned long)(now_us / 1000);
+}
+
+unsigned long micros() {
+    advance(4);
+    return (unsigned long)now_us;
+}
+void delay(unsigned long ms) { advance((uint64_t)ms * 1000); }
+void delayMicroseconds(unsigned int us) { advance(us); }
+void yield() {}
+
+void tone(uint8_t pin, unsigned int frequency, unsigned long duration) {
+    if (pin >= PIN_COUNT) return;
+    (void)frequency;
+    report(pin, "pwm", 128);
+    tone_end_us[pin] = duration ? now_us + (uint64_t)duration * 1000 : 0;
+}
+
+void noTone(uint8_t pin) {
+    if (pin >= PIN_COUNT) return;
+    tone_end_us[pin] = 0;
+    out_levels[pin] = LOW;
+    report(pin, "output", LOW);
+}
+
+unsigned long pulseIn(uint8_t pin, uint8_t state, unsigned long timeout) {
+    (void)pin;
+    (void)state;
+    // No pulses arrive in the simulation, e.g. an ultrasonic sensor without echo
+    advance(timeout);
+    return 0;
+}
+
+void attachInterrupt(uint8_t interrupt, void (*handler)(void), int mode) {
+    // Inputs are constant during a simulation, so interrupts never fire
+    (void)interrupt;
+    (void)handler;
+    (void)mode;
+}
+
+void detachInterrupt(uint8_t interrupt) { (void)interrupt; }
+
+// ----- math -----
+
+long random(long max) {
+    if (max <= 0) return 0;
+    rng_state = rng_state * 6364136223846793005ULL + 1442695040888963407ULL;
+    return (long)((rng_state >> 33) % (unsigned long long)max);
+}
+
+long random(long min, long max) {
+    if (min >= max) return min;
+    return random(max - min) + min;
+}
+
+void randomSeed(unsigned long seed) {
+    if (seed != 0) rng_state = seed;
+}
+
+long map(long x, long in_min, long in_max, long out_min, long out_max) {
+    if (in_max == in_min) return out_min;
+    return (x - in_min) * (out_max - out_min) / (in_max - in_min) + out_min;
+}
+
+// ----- String -----
+
+static std::string format_unsigned(unsigned long n, int base) {
+    if (base < 2) base = 10;
+    std::string digits;
+    do {
+        int d = n % base;
+        digits.insert(digits.begin(), (char)(d < 10 ? '0' + d : 'A' + d - 10));
+        n /= base;
+    } while (n);
+    return digits;
+}
+
+static std::string format_signed(long n, int base) {
+    if (base == 10 && n < 0) return "-" + format_unsigned((unsigned long)(-(n + 1)) + 1, 10);
+    return format_unsigned((unsigned long)n, base);
+}
+
+static std::string format_double(double n, int digits) {
+    if (isnan(n)) return "nan";
+    if (isinf(n)) return "inf";
+    char buf[64];
+    snprintf(buf, sizeof(buf), "%.*f", digits, n);
+    return buf;
+}
+
+String::String(int value, unsigned char base) : s_(format_signed(value, base)) {}
+String::String(unsigned int value, unsigned char base) : s_(format_unsigned(value, base)) {}
+String::String(long value, unsigned char base) : s_(format_signed(value, base)) {}
+String::String(unsigned long value, unsigned char base) : s_(format_unsigned(value, base)) {}
+String::String(double value, unsigned char decimals) : s_(format_double(value, decimals)) {}
+
+bool String::equalsIgnoreCase(const String &other) const {
+    if (s_.length() != other.s_.length()) return false;
+    for (size_t i = 0; i < s_.length(); i++) {
+        if (tolower((unsigned char)s_[i]) != tolower((unsigned char)other.s_[i])) return false;
+    }
+    return true;
+}
+
+bool String::endsWith(const String &suffix) const {
+    return s_.length() >= suffix.s_.length()
+        && s_.compare(s_.length() - suffix.s_.length(), suffix.s_.length(), suffix.s_) == 0;
+}
+
+int String::indexOf(char c, unsigned int from) const {
+    size_t pos = s_.find(c, from);
+    return pos == std::string::npos ? -1 : (int)pos;
+}
+
+int String::indexOf(const String &str, unsigned int from) const {
+    size_t pos = s_.find(str.s_, from);
+    return pos == std::string::npos ? -1 : (int)pos;
+}
+
+int String::lastIndexOf(char c) const {
+    size_t pos = s_.rfind(c);
+    return pos == std::string::npos ? -1 : (int)pos;
+}
+
+int String::lastIndexOf(const String &str) const {
+    size_t pos = s_.rfind(str.s_);
+    return pos == std::string::npos ? -1 : (int)pos;
+}
+
+String String::substring(unsigned int from) const {
+    return from < s_.length() ? String(s_.substr(from)) : String();
+}
+
+String String::substring(unsigned int from, unsigned int to) const {
+    if (from > to) std::swap(from, to);
+    if (from >= s_.length()) return String();
+    return String(s_.substr(from, std::min<size_t>(to, s_.length()) - from));
+}
+
+void String::replace(const String &find, const String &replacement) {
+    if (find.s_.empty()) return;
+    size_t pos = 0;
+    while ((pos = s_.find(find.s_, pos)) != std::string::npos) {
+        s_.replace(pos, find.s_.length(), replacement.s_);
+        pos += replacement.s_.length();
+    }
+}
+
+void String::remove(unsigned int index) {
+    if (index < s_.length()) s_.erase(index);
+}
+
+void String::remove(unsigned int index, unsigned int count) {
+    if (index < s_.length()) s_.erase(index, count);
+}
+
+void String::toLowerCase() {
+    for (size_t i = 0; i < s_.length(); i++) s_[i] = (char)tolower((unsigned char)s_[i]);
+}
+
+void String::toUpperCase() {
+    for (size_t i = 0; i < s_.length(); i++) s_[i] = (char)toupper((unsigned char)s_[i]);
+}
+
+void String::trim() {
+    size_t start = s_.find_first_not_of(" \t\r\n");
+    if (start == std::string::npos) {
+        s_.clear();
+        return;
+    }
+    size_t end = s_.find_last_not_of(" \t\r\n");
+    s_ = s_.substr(start, end - start + 1);
+}
+
+// ----- Serial -----
+
+void HardwareSerial::begin(unsigned long baud, int config) {
+    (void)config;
+    if (baud) baud_ = baud;
+}
+
+int HardwareSerial::available() {
+    advance(4);
+    return (int)(serial_in.length() - serial_pos);
+}
+
+int HardwareSerial::read() {
+    advance(4);
+    return serial_pos < serial_in.length() ? (unsigned char)serial_in[serial_pos++] : -1;
+}
+
+int HardwareSerial::peek() {
+    return serial_pos < serial_in.length() ? (unsigned char)serial_in[serial_pos] : -1;
+}
+
+size_t HardwareSerial::write(uint8_t c) {
+    fputc(c, stdout);
+    // 10 bits per byte at the configured baud rate
+    advance(10000000ULL / baud_);
+    return 1;
+}
+
+size_t HardwareSerial::write(const char *s) {
+    return write((const uint8_t *)s, strlen(s));
+}
+
+size_t HardwareSerial::write(const uint8_t *buffer, size_t size) {
+    for (size_t i = 0; i < size; i++) write(buffer[i]);
+    return size;
+}
+
+size_t HardwareSerial::print(long n, int base) { return write(format_signed(n, base).c_str()); }
+size_t HardwareSerial::print(unsigned long n, int base) { return write(format_unsigned(n, base).c_str()); }
+size_t HardwareSerial::print(double n, int digits) { return write(format_double(n, digits).c_str()); }
+
+long HardwareSerial::parseInt() {
+    while (peek() != -1 && peek() != '-' && !isdigit(peek())) read();
+    bool negative = false;
+    if (peek() == '-') {
+        negative = true;
+        read();
+    }
+    long value = 0;
+    while (peek() != -1 && isdigit(peek())) value = value * 10 + (read() - '0');
+    return negative ? -value : value;
+}
+
+float HardwareSerial::parseFloat() {
+    while (peek() != -1 && peek() != '-' && peek() != '.' && !isdigit(peek())) read();
+    std::string number;
+    while (peek() != -1 && (isdigit(peek()) || peek() == '.' || (number.empty() && peek() == '-'))) {
+        number += (char)read();
+    }
+    return (float)atof(number.c_str());
+}
+
+String HardwareSerial::readString() {
+    std::string rest = serial_in.substr(serial_pos);
+    serial_pos = serial_in.length();
+    advance(4);
+    return String(rest);
+}
+
+String HardwareSerial::readStringUntil(char terminator) {
+    std::string result;
+    while (serial_pos < serial_in.length()) {
+        char c = serial_in[serial_pos++];
+        if (c == terminator) break;
+        result += c;
+    }
+    advance(4);
+    return String(result);
+}
+
+size_t HardwareSerial::readBytes(char *buffer, size_t length) {
+    size_t n = 0;
+    while (n < length && serial_pos < serial_in.length()) buffer[n++] = serial_in[serial_pos++];
+    return n;
+}
+
+size_t HardwareSerial::readBytesUntil(char terminator, char *buffer, size_t length) {
+    size_t n = 0;
+    while (n < length && serial_pos < serial_in.length()) {
+        char c = serial_in[serial_pos++];
+        if (c == terminator) break;
+        buffer[n++] = c;
+    }
+    return n;
+}
+
+// ----- entry point -----
+
+static bool parse_assignment(const char *arg, int *key, int *value) {
+    return sscanf(arg, "%d=%d", key, value) == 2;
+}
+
+int main(int argc, char **argv) {
+    for (int pin = 0; pin < PIN_COUNT; pin++) input_levels[pin] = -1;
+
+    for (int i = 1; i + 1 < argc; i += 2) {
+        int key = 0, value = 0;
+        if (strcmp(argv[i], "--duration-ms") == 0) {
+            limit_us = strtoull(argv[i + 1], NULL, 10) * 1000;
+        } else if (strcmp(argv[i], "--digital") == 0 && parse_assignment(argv[i + 1], &key, &value)) {
+            if (key >= 0 && key < PIN_COUNT) input_levels[key] = value ? HIGH : LOW;
+        } else if (strcmp(argv[i], "--analog") == 0 && parse_assignment(argv[i + 1], &key, &value)) {
+            if (key >= 0 && key < 8) analog_inputs[key] = constrain(value, 0, 1023);
+        }
+    }
+
+    int c;
+    while ((c = fgetc(stdin)) != EOF) serial_in += (char)c;
+
+    setup();
+    for (;;) {
+        loop();
+        advance(4);
+    }
+}
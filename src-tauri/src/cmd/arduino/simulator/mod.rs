@@ -0,0 +1,349 @@
+// simulator.rs - Run sketches natively against a mock Arduino core
+//
+// A lighter alternative to the emulator: the generated sketch is compiled with
+// the host C++ compiler together with `core/`, which implements the Arduino
+// API on a virtual clock. The process runs for a fixed amount of simulated
+// time and usually finishes within a second. Serial output is streamed as
+// `serial-data`, pin activity as `arduino-pin-change`.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use serde::Deserialize;
+use tauri::{Emitter, Manager, Window};
+
+use super::emulator::{take_complete_utf8, PinChange, PinMode};
+use super::sketch::{self, prepare_sketch};
+
+const ARDUINO_H: &str = include_str!("core/Arduino.h");
+const CORE_CPP: &str = include_str!("core/hanx_sim.cpp");
+
+const PROCESS_NAME: &str = "arduino-sim";
+const DEFAULT_DURATION_MS: u64 = 10_000;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct SimulationOptions {
+    /// Simulated (not wall clock) time to run for
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+    /// Fed to `Serial.read()`
+    #[serde(default)]
+    pub serial_input: Option<String>,
+    #[serde(default)]
+    pub digital_inputs: HashMap<u8, bool>,
+    /// Readings (0-1023) per analog channel
+    #[serde(default)]
+    pub analog_inputs: HashMap<u8, u16>,
+}
+
+#[tauri::command]
+pub async fn simulate_arduino(window: Window, code: String, options: Option<SimulationOptions>) -> Result<(), String> {
+    let options = options.unwrap_or_default();
+    let sketch_dir = prepare_sketch(window.app_handle(), &code)?;
+
+    window.emit("arduino-output", "Building simulation...").unwrap_or(());
+    let binary = build(&window, &sketch_dir, &code)?;
+
+    let mut cmd = Command::new(&binary);
+    cmd.arg("--duration-ms")
+        .arg(options.duration_ms.unwrap_or(DEFAULT_DURATION_MS).to_string());
+    for (pin, high) in &options.digital_inputs {
+        cmd.arg("--digital").arg(format!("{}={}", pin, *high as u8));
+    }
+    for (channel, value) in &options.analog_inputs {
+        cmd.arg("--analog").arg(format!("{}={}", channel, value));
+    }
+
+    let mut child = cmd
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to start simulation: {}", e))?;
+
+    // The mock core reads all serial input up front, closing stdin ends it
+    if let Some(mut stdin) = child.stdin.take() {
+        let input = options.serial_input.unwrap_or_default();
+        let _ = stdin.write_all(input.as_bytes());
+    }
+
+    let mut stdout = child.stdout.take().ok_or("Failed to open stdout")?;
+    let stderr = child.stderr.take().ok_or("Failed to open stderr")?;
+
+    crate::cmd::sys::execution::register_process(PROCESS_NAME, child.id());
+    window.emit("arduino-output", "Simulation running...").unwrap_or(());
+
+    let window_clone_out = window.clone();
+    let window_clone_err = window.clone();
+    let window_clone_exit = window.clone();
+
+    // Stream Serial output
+    thread::spawn(move || {
+        let mut buf = [0u8; 4096];
+        let mut pending: Vec<u8> = Vec::new();
+        while let Ok(n) = stdout.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            pending.extend_from_slice(&buf[..n]);
+            if let Some(text) = take_complete_utf8(&mut pending) {
+                window_clone_out.emit("serial-data", text).unwrap_or(());
+            }
+        }
+    });
+
+    // Pin activity and runtime errors; returns the simulated end time
+    let stderr_thread = thread::spawn(move || {
+        let mut end_ms = None;
+        let reader = BufReader::new(stderr);
+        for line in reader.lines().map_while(Result::ok) {
+            if let Some(change) = parse_pin_change(&line) {
+                window_clone_err.emit("arduino-pin-change", change).unwrap_or(());
+            } else if let Some(ms) = line.strip_prefix("@hanx:end ") {
+                end_ms = ms.trim().parse::<u64>().ok();
+            } else {
+                window_clone_err.emit("arduino-stderr", line).unwrap_or(());
+            }
+        }
+        end_ms
+    });
+
+    // Wait for exit
+    thread::spawn(move || {
+        let status = child.wait();
+        crate::cmd::sys::execution::unregister_process(PROCESS_NAME);
+        let end_ms = stderr_thread.join().ok().flatten();
+        let msg = match (status, end_ms) {
+            (Ok(s), Some(ms)) if s.success() => format!("Simulation finished after {} ms of simulated time.", ms),
+            (Ok(s), _) => format!("Simulation exited with {}", s),
+            (Err(e), _) => format!("Process error: {}", e),
+        };
+        window_clone_exit.emit("arduino-finished", msg).unwrap_or(());
+    });
+
+    Ok(())
+}
+
+/// Compiles the sketch and the mock core into a native executable.
+fn build(window: &Window, sketch_dir: &Path, code: &str) -> Result<PathBuf, String> {
+    let compiler = find_compiler()
+        .ok_or("No C++ compiler found (c++, g++ or clang++). Install one or set CXX.")?;
+
+    let sim_dir = std::env::temp_dir().join(format!("{}_sim", sketch::SKETCH_NAME));
+    fs::create_dir_all(&sim_dir).map_err(|e| e.to_string())?;
+    fs::write(sim_dir.join("Arduino.h"), ARDUINO_H).map_err(|e| e.to_string())?;
+    fs::write(sim_dir.join("hanx_sim.cpp"), CORE_CPP).map_err(|e| e.to_string())?;
+    let ino = format!("{}.ino", sketch::SKETCH_NAME);
+    fs::write(sim_dir.join("sketch.cpp"), ino_to_cpp(code, Some(&ino))).map_err(|e| e.to_string())?;
+
+    let binary = sim_dir.join(if cfg!(windows) { "hanx_sim.exe" } else { "hanx_sim" });
+
+    let mut cmd = Command::new(&compiler);
+    cmd.args(["-std=gnu++11", "-w", "-O1"])
+        .arg("-I").arg(&sim_dir)
+        // Extension helpers are included as "src/<id>/..." relative to the sketch
+        .arg("-I").arg(sketch_dir)
+        .arg("-o").arg(&binary)
+        .args(["-x", "c++"])
+        .arg(sim_dir.join("sketch.cpp"))
+        .arg(sim_dir.join("hanx_sim.cpp"));
+    for source in extension_sources(&sketch_dir.join("src")) {
+        cmd.arg(source);
+    }
+
+    let output = cmd.output().map_err(|e| format!("Failed to run {}: {}", compiler, e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        window.emit("arduino-stderr", stderr.to_string()).unwrap_or(());
+        return Err("Compilation failed".to_string());
+    }
+    Ok(binary)
+}
+
+fn find_compiler() -> Option<String> {
+    let mut candidates: Vec<String> = std::env::var("CXX").ok().into_iter().collect();
+    candidates.extend(["c++", "g++", "clang++"].iter().map(|c| c.to_string()));
+    candidates.into_iter().find(|c| {
+        Command::new(c)
+            .arg("--version")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map(|s| s.success())
+            .unwrap_or(false)
+    })
+}
+
+fn extension_sources(dir: &Path) -> Vec<PathBuf> {
+    let mut sources = Vec::new();
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                sources.extend(extension_sources(&path));
+            } else if matches!(path.extension().and_then(|e| e.to_str()), Some("c" | "cc" | "cpp")) {
+                sources.push(path);
+            }
+        }
+    }
+    sources
+}
+
+/// Parses "@hanx:pin <ms> <pin> <mode> <value>" written by the mock core.
+fn parse_pin_change(line: &str) -> Option<PinChange> {
+    let mut parts = line.strip_prefix("@hanx:pin ")?.split_whitespace();
+    let time_ms = parts.next()?.parse().ok()?;
+    let pin = parts.next()?.parse().ok()?;
+    let mode = match parts.next()? {
+        "input" => PinMode::Input,
+        "output" => PinMode::Output,
+        "pwm" => PinMode::Pwm,
+        _ => return None,
+    };
+    let value = parts.next()?.parse().ok()?;
+    Some(PinChange { pin, mode, value, time_ms })
+}
+
+/// Turns the `.ino` into C++ the way the Arduino builder does: includes
+/// Arduino.h and declares every function before the first definition.
+/// With `ino_name`, `#line` directives point diagnostics back to the sketch.
+pub(super) fn ino_to_cpp(code: &str, ino_name: Option<&str>) -> String {
+    let (offset, prototypes) = find_prototypes(code);
+    let line = code[..offset].matches('\n').count() + 1;
+
+    let mut out = String::from("#include <Arduino.h>\n");
+    if let Some(ino) = ino_name {
+        out.push_str(&format!("#line 1 \"{}\"\n", ino));
+    }
+    out.push_str(&code[..offset]);
+    for prototype in prototypes {
+        out.push_str(&prototype);
+        out.push('\n');
+    }
+    if let Some(ino) = ino_name {
+        out.push_str(&format!("#line {} \"{}\"\n", line, ino));
+    }
+    out.push_str(&code[offset..]);
+    out
+}
+
+/// Returns the offset of the first top-level function definition and the
+/// prototypes of all of them.
+fn find_prototypes(code: &str) -> (usize, Vec<String>) {
+    let clean = strip_comments_and_literals(code.as_bytes());
+    let mut prototypes = Vec::new();
+    let mut first = None;
+    let mut depth = 0usize;
+    let mut segment_start = 0;
+    let mut line_start = true;
+    let mut i = 0;
+
+    while i < clean.len() {
+        let c = clean[i];
+        if line_start && depth == 0 && c == b'#' {
+            // Skip preprocessor lines, including continuations
+            while i < clean.len() && !(clean[i] == b'\n' && clean[i - 1] != b'\\') {
+                i += 1;
+            }
+            segment_start = i;
+            continue;
+        }
+        if c == b'\n' {
+            line_start = true;
+        } else if !c.is_ascii_whitespace() {
+            line_start = false;
+        }
+
+        match c {
+            b'{' => {
+                if depth == 0 {
+                    let header = String::from_utf8_lossy(&clean[segment_start..i]);
+                    if let Some(prototype) = prototype_for(&header) {
+                        let skipped = header.len() - header.trim_start().len();
+                        first.get_or_insert(segment_start + skipped);
+                        prototypes.push(prototype);
+                    }
+                }
+                depth += 1;
+            }
+            b'}' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    segment_start = i + 1;
+                }
+            }
+            b';' if depth == 0 => segment_start = i + 1,
+            _ => {}
+        }
+        i += 1;
+    }
+
+    // Prototypes go at the start of the line holding the first definition
+    let offset = first.map(|o| code[..o].rfind('\n').map_or(0, |n| n + 1)).unwrap_or(0);
+    (offset, prototypes)
+}
+
+fn prototype_for(header: &str) -> Option<String> {
+    let header = header.split_whitespace().collect::<Vec<_>>().join(" ");
+    let open = header.find('(')?;
+    let before = header[..open].trim();
+    let is_definition = header.ends_with(')') || header.ends_with(") const");
+    let first_word = before.split([' ', '*', '&']).next().unwrap_or("");
+    // Default arguments may only be given once, such functions must already be declared in order
+    if !is_definition
+        || !before.contains(' ')
+        || header.contains('=')
+        || matches!(first_word, "struct" | "class" | "enum" | "union" | "namespace" | "template" | "typedef")
+    {
+        return None;
+    }
+    Some(format!("{};", header))
+}
+
+/// Blanks out comments and string/char literals, keeping offsets and newlines.
+fn strip_comments_and_literals(code: &[u8]) -> Vec<u8> {
+    let mut out = code.to_vec();
+    let mut i = 0;
+    while i < code.len() {
+        match (code[i], code.get(i + 1)) {
+            (b'/', Some(b'/')) => {
+                while i < code.len() && code[i] != b'\n' {
+                    out[i] = b' ';
+                    i += 1;
+                }
+            }
+            (b'/', Some(b'*')) => {
+                let start = i;
+                i += 2;
+                while i < code.len() && !(code[i - 1] == b'*' && code[i] == b'/' && i - 1 > start + 1) {
+                    i += 1;
+                }
+                for b in out.iter_mut().take((i + 1).min(code.len())).skip(start) {
+                    if *b != b'\n' {
+                        *b = b' ';
+                    }
+                }
+                i += 1;
+            }
+            (quote @ (b'"' | b'\''), _) => {
+                i += 1;
+                while i < code.len() && code[i] != quote && code[i] != b'\n' {
+                    if code[i] == b'\\' {
+                        out[i] = b' ';
+                        i += 1;
+                    }
+                    if i < code.len() {
+                        out[i] = b' ';
+                    }
+                    i += 1;
+                }
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+    out
+}
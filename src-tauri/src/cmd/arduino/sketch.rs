@@ -0,0 +1,109 @@
+// sketch.rs - Prepare the temporary sketch folder compiled by arduino-cli
+//
+// Besides the generated `.ino`, extensions may ship C/C++ helpers in
+// `arduino/src/`. They are copied to `src/<extension-id>/` in the sketch when
+// the generated code includes one of them, i.e. when a generator emitted
+// `#include "src/<extension-id>/Helper.h"`.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use crate::cmd::sys::extensions::get_platform_extensions_dir;
+
+pub const SKETCH_NAME: &str = "hanx_sketch";
+
+pub fn prepare_sketch(app_handle: &tauri::AppHandle, code: &str) -> Result<PathBuf, String> {
+    let mut temp_dir = std::env::temp_dir();
+    temp_dir.push(SKETCH_NAME);
+    if !temp_dir.exists() {
+        fs::create_dir(&temp_dir).map_err(|e| e.to_string())?;
+    }
+    let sketch_dir = temp_dir.clone();
+    temp_dir.push(format!("{}.ino", SKETCH_NAME));
+
+    let mut file = File::create(&temp_dir).map_err(|e| e.to_string())?;
+    file.write_all(code.as_bytes()).map_err(|e| e.to_string())?;
+
+    // The sketch folder is reused, drop helpers copied for a previous program
+    let src_dir = sketch_dir.join("src");
+    if src_dir.exists() {
+        fs::remove_dir_all(&src_dir).map_err(|e| e.to_string())?;
+    }
+    copy_extension_sources(app_handle, code, &src_dir)?;
+
+    Ok(sketch_dir)
+}
+
+/// Where binaries compiled for `fqbn` are exported, outside the sketch folder.
+pub fn output_dir(fqbn: &str) -> PathBuf {
+    let board = fqbn.replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+    std::env::temp_dir().join(format!("{}_build", SKETCH_NAME)).join(board)
+}
+
+/// Headers included by the sketch, e.g. "Servo.h".
+pub fn included_headers(code: &str) -> Vec<String> {
+    code.lines()
+        .filter_map(|line| line.trim().strip_prefix("#include"))
+        .filter_map(|rest| {
+            let rest = rest.trim();
+            let end = match rest.chars().next()? {
+                '<' => rest.find('>')?,
+                '"' => rest[1..].find('"')? + 1,
+                _ => return None,
+            };
+            Some(rest[1..end].to_string())
+        })
+        .collect()
+}
+
+/// Returns `(extension id, arduino/src dir)` for every Arduino extension shipping sources.
+pub fn extension_source_dirs(app_handle: &tauri::AppHandle) -> Vec<(String, PathBuf)> {
+    let mut dirs = Vec::new();
+    let extensions_dir = get_platform_extensions_dir(app_handle, "arduino");
+    if let Ok(entries) = fs::read_dir(&extensions_dir) {
+        for entry in entries.flatten() {
+            let src = entry.path().join("arduino").join("src");
+            if src.is_dir() {
+                dirs.push((entry.file_name().to_string_lossy().to_string(), src));
+            }
+        }
+    }
+    dirs
+}
+
+/// Whether `code` includes helpers of the extension `id`.
+pub fn uses_extension(code: &str, id: &str) -> bool {
+    code.contains(&format!("src/{}/", id))
+}
+
+/// Copies the sources of every extension whose helpers are included by `code`
+/// into `dest/<extension-id>/`. Returns the ids of the extensions copied.
+pub fn copy_extension_sources(app_handle: &tauri::AppHandle, code: &str, dest: &Path) -> Result<Vec<String>, String> {
+    let mut used = Vec::new();
+    for (id, src) in extension_source_dirs(app_handle) {
+        if !uses_extension(code, &id) {
+            continue;
+        }
+        println!("[Arduino] 📎 Adding sources from extension {}", id);
+        copy_dir(&src, &dest.join(&id))?;
+        used.push(id);
+    }
+    Ok(used)
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<(), String> {
+    fs::create_dir_all(to).map_err(|e| e.to_string())?;
+    for entry in fs::read_dir(from).map_err(|e| e.to_string())?.flatten() {
+        let name = entry.file_name();
+        if name.to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            copy_dir(&path, &to.join(&name))?;
+        } else {
+            fs::copy(&path, to.join(&name)).map_err(|e| format!("Failed to copy {:?}: {}", path, e))?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,498 @@
+// cli.rs - arduino-cli toolchain
+//
+// Uses the arduino-cli daemon when that backend is enabled and running,
+// otherwise spawns `arduino-cli` with the app-owned config file. Profile
+// builds always spawn the CLI, the daemon would need an instance initialized
+// with the profile.
+
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread::{self, JoinHandle};
+use async_trait::async_trait;
+use serde::Deserialize;
+use tauri::async_runtime::{channel, Receiver};
+use crate::cmd::sys::env_manager::arduino::{command_for_config, ensure_config_file};
+use super::super::daemon::{self, DaemonClient};
+use super::super::usb_ids::BoardCandidate;
+use super::super::{sketch, ArduinoProgrammer, BoardEvent, DetectedBoard};
+use super::{ArduinoToolchain, BoardDetails, Output, CompileRequest, IndexEntry, InstalledCore, InstalledLibrary, UploadRequest};
+
+pub const NAME: &str = "arduino-cli";
+
+#[derive(Debug, Deserialize)]
+struct CliBoard {
+    matching_boards: Option<Vec<CliMatchingBoard>>,
+    port: CliPort,
+}
+
+#[derive(Debug, Deserialize)]
+struct CliMatchingBoard {
+    name: String,
+    fqbn: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CliPort {
+    address: String,
+    label: String,
+    #[serde(default)]
+    properties: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CliBoardList {
+    detected_ports: Option<Vec<CliBoard>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CliProgrammer {
+    #[serde(default)]
+    platform: String,
+    id: String,
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CliBoardDetails {
+    #[serde(default)]
+    name: String,
+    programmers: Option<Vec<CliProgrammer>>,
+    default_programmer_id: Option<String>,
+    #[serde(default)]
+    build_properties: Vec<String>,
+}
+
+pub struct CliToolchain {
+    config_file: PathBuf,
+}
+
+impl CliToolchain {
+    pub fn new(config_file: PathBuf) -> Self {
+        CliToolchain { config_file }
+    }
+
+    fn command(&self) -> Command {
+        command_for_config(&self.config_file)
+    }
+
+    async fn daemon(&self) -> Option<DaemonClient> {
+        daemon::connect(self.config_file.clone()).await
+    }
+
+    /// Runs `arduino-cli <args> --format json` and parses its output.
+    fn json(&self, args: &[&str]) -> Result<serde_json::Value, String> {
+        let output = self.command()
+            .args(args)
+            .args(&["--format", "json"])
+            .output()
+            .map_err(|e| format!("Failed to execute arduino-cli: {}", e))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("arduino-cli {} failed: {}", args.join(" "), stderr.trim()));
+        }
+        serde_json::from_slice(&output.stdout).map_err(|e| format!("Failed to parse arduino-cli output: {}", e))
+    }
+
+    /// Runs a command that prints nothing of interest, its stderr is the error.
+    fn run(&self, mut cmd: Command) -> Result<(), String> {
+        let out = cmd.output().map_err(|e| format!("Failed to run arduino-cli: {}", e))?;
+        if out.status.success() {
+            Ok(())
+        } else {
+            Err(String::from_utf8_lossy(&out.stderr).trim().to_string())
+        }
+    }
+}
+
+fn forward(out: &Output, pipe: impl Read + Send + 'static, stderr: bool) -> JoinHandle<()> {
+    let out = out.clone();
+    thread::spawn(move || {
+        for line in BufReader::new(pipe).lines().map_while(Result::ok) {
+            if stderr {
+                out.stderr(line);
+            } else {
+                out.output(line);
+            }
+        }
+    })
+}
+
+/// Runs an arduino-cli process to completion, streaming stdout and stderr to `out`.
+async fn stream(out: &Output, mut cmd: Command) -> Result<(), String> {
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to execute arduino-cli: {}. Is it installed?", e))?;
+    let stdout = child.stdout.take().ok_or("Failed to open stdout")?;
+    let stderr = child.stderr.take().ok_or("Failed to open stderr")?;
+    let readers = [forward(out, stdout, false), forward(out, stderr, true)];
+
+    let status = tauri::async_runtime::spawn_blocking(move || child.wait())
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("Process error: {}", e))?;
+    // All output is out before the caller reports the result
+    for reader in readers {
+        let _ = reader.join();
+    }
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("arduino-cli exited with {}", status))
+    }
+}
+
+fn strings(value: &serde_json::Value) -> Vec<String> {
+    value.as_array().into_iter().flatten()
+        .filter_map(|v| v.as_str().map(|s| s.to_string()))
+        .collect()
+}
+
+impl From<daemon::proto::DetectedPort> for DetectedBoard {
+    fn from(p: daemon::proto::DetectedPort) -> Self {
+        let port = p.port.unwrap_or_default();
+        let matches = p.matching_boards.into_iter()
+            .map(|b| BoardCandidate { fqbn: b.fqbn, name: b.name })
+            .collect();
+        DetectedBoard::new(port.address, port.label, &port.properties, matches)
+    }
+}
+
+#[async_trait]
+impl ArduinoToolchain for CliToolchain {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    async fn version(&self) -> Result<String, String> {
+        let output = Command::new("arduino-cli")
+            .arg("version")
+            .output()
+            .map_err(|e| format!("Arduino CLI not found: {}", e))?;
+        if !output.status.success() {
+            return Err("Arduino CLI returned error status".to_string());
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    async fn setup(&self) -> Result<(), String> {
+        ensure_config_file(&self.config_file)
+    }
+
+    async fn list_boards(&self) -> Result<Vec<DetectedBoard>, String> {
+        if let Some(client) = self.daemon().await {
+            let ports = client.board_list().await?;
+            return Ok(ports.into_iter().map(DetectedBoard::from).collect());
+        }
+
+        let output = self.command()
+            .args(&["board", "list", "--format", "json"])
+            .output()
+            .map_err(|e| format!("Failed to execute arduino-cli: {}", e))?;
+
+        if !output.status.success() {
+            return Err("Failed to list boards via arduino-cli".to_string());
+        }
+
+        let board_list: CliBoardList = serde_json::from_slice(&output.stdout)
+            .map_err(|e| format!("Failed to parse arduino-cli output: {}", e))?;
+
+        Ok(board_list.detected_ports
+            .unwrap_or_default()
+            .into_iter()
+            .map(|p| {
+                let matches = p.matching_boards
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|b| BoardCandidate { fqbn: b.fqbn, name: b.name })
+                    .collect();
+                DetectedBoard::new(p.port.address, p.port.label, &p.port.properties, matches)
+            })
+            .collect())
+    }
+
+    async fn watch_boards(&self) -> Result<Receiver<BoardEvent>, String> {
+        let client = self.daemon().await
+            .ok_or("Board watching requires the arduino-cli daemon backend")?;
+        let mut stream = client.board_list_watch().await?;
+
+        let (tx, rx) = channel(16);
+        tauri::async_runtime::spawn(async move {
+            loop {
+                match stream.message().await {
+                    Ok(Some(msg)) => {
+                        let event = BoardEvent {
+                            event_type: msg.event_type,
+                            board: msg.port.map(DetectedBoard::from),
+                            error: if msg.error.is_empty() { None } else { Some(msg.error) },
+                        };
+                        if tx.send(event).await.is_err() {
+                            break;
+                        }
+                    }
+                    Ok(None) => break,
+                    Err(status) => {
+                        println!("[Arduino] ⚠️ Board watch ended: {}", status.message());
+                        break;
+                    }
+                }
+            }
+        });
+        Ok(rx)
+    }
+
+    async fn board_details(&self, fqbn: &str) -> Result<BoardDetails, String> {
+        let output = self.command()
+            .args(&["board", "details", "-b", fqbn, "--show-properties=expanded", "--format", "json"])
+            .output()
+            .map_err(|e| format!("Failed to execute arduino-cli: {}", e))?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(format!("Failed to get board details for {}: {}", fqbn, stderr.trim()));
+        }
+
+        let details: CliBoardDetails = serde_json::from_slice(&output.stdout)
+            .map_err(|e| format!("Failed to parse arduino-cli output: {}", e))?;
+
+        let default_id = details.default_programmer_id.unwrap_or_default();
+        Ok(BoardDetails {
+            name: details.name,
+            programmers: details.programmers
+                .unwrap_or_default()
+                .into_iter()
+                .map(|p| ArduinoProgrammer {
+                    is_default: p.id == default_id,
+                    id: p.id,
+                    name: p.name,
+                    platform: p.platform,
+                })
+                .collect(),
+            build_properties: details.build_properties.iter()
+                .filter_map(|p| p.split_once('='))
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        })
+    }
+
+    async fn compile(&self, out: &Output, request: &CompileRequest) -> Result<(), String> {
+        let flags = &request.flags;
+        if request.profile.is_none() {
+            if let Some(client) = self.daemon().await {
+                return client.compile(out.as_ref(), &request.fqbn, &request.sketch_dir, &request.libs, &request.output_dir, flags).await;
+            }
+        }
+
+        let mut compile_cmd = self.command();
+        compile_cmd.arg("compile");
+        match &request.profile {
+            Some(name) => compile_cmd.arg("--profile").arg(name),
+            None => compile_cmd.arg("--fqbn").arg(&request.fqbn),
+        };
+        compile_cmd.arg("--output-dir")
+            .arg(&request.output_dir);
+
+        for lib in &request.libs {
+            compile_cmd.arg("--libraries").arg(lib);
+        }
+        for property in &flags.build_properties {
+            compile_cmd.arg("--build-property").arg(property);
+        }
+        if let Some(level) = &flags.warnings {
+            compile_cmd.arg("--warnings").arg(level);
+        }
+        if flags.verbose {
+            compile_cmd.arg("--verbose");
+        }
+
+        let compile_output = compile_cmd.arg(request.sketch_dir.to_str().ok_or("Invalid path")?)
+            .output()
+            .map_err(|e| format!("Failed to execute arduino-cli: {}. Is it installed?", e))?;
+
+        if !compile_output.status.success() {
+            let stderr = String::from_utf8_lossy(&compile_output.stderr);
+            out.stderr(stderr.to_string());
+            return Err("Compilation failed".to_string());
+        }
+        if flags.verbose {
+            for line in String::from_utf8_lossy(&compile_output.stdout).lines() {
+                out.output(line.to_string());
+            }
+        }
+        // Compiler warnings end up on stderr even when the build succeeds
+        for line in String::from_utf8_lossy(&compile_output.stderr).lines() {
+            out.output(line.to_string());
+        }
+        Ok(())
+    }
+
+    async fn upload(&self, out: &Output, request: &UploadRequest) -> Result<(), String> {
+        if request.profile.is_none() {
+            if let Some(client) = self.daemon().await {
+                return client.upload(
+                    out.as_ref(),
+                    &request.fqbn,
+                    &request.sketch_dir,
+                    &request.port,
+                    request.programmer.as_deref(),
+                    request.verbose,
+                ).await;
+            }
+        }
+
+        let mut upload_cmd = self.command();
+        upload_cmd.arg("upload");
+
+        // ISP programmers such as USBasp have no serial port of their own
+        if !request.port.is_empty() {
+            upload_cmd.arg("-p").arg(&request.port);
+        }
+        if let Some(p) = &request.programmer {
+            upload_cmd.arg("--programmer").arg(p);
+        }
+        if request.verbose {
+            upload_cmd.arg("--verbose");
+        }
+        match &request.profile {
+            Some(name) => upload_cmd.arg("--profile").arg(name),
+            None => upload_cmd.arg("--fqbn").arg(&request.fqbn),
+        };
+
+        let sketch_file = request.sketch_dir.join(format!("{}.ino", sketch::SKETCH_NAME));
+        upload_cmd.arg(sketch_file.to_str().ok_or("Invalid path")?);
+        stream(out, upload_cmd).await
+    }
+
+    async fn burn_bootloader(&self, out: &Output, fqbn: &str, port: &str, programmer: &str) -> Result<(), String> {
+        let mut burn_cmd = self.command();
+        burn_cmd.arg("burn-bootloader")
+            .arg("--fqbn")
+            .arg(fqbn)
+            .arg("--programmer")
+            .arg(programmer);
+
+        if !port.is_empty() {
+            burn_cmd.arg("-p").arg(port);
+        }
+        stream(out, burn_cmd).await
+    }
+
+    async fn list_libraries(&self, all: bool, fqbn: Option<&str>) -> Result<Vec<InstalledLibrary>, String> {
+        // The daemon only knows the unfiltered list
+        if all && fqbn.is_none() {
+            if let Some(client) = self.daemon().await {
+                return Ok(client.lib_list().await?
+                    .into_iter()
+                    .map(|l| InstalledLibrary { name: l.name, version: l.version, provides_includes: l.provides_includes })
+                    .collect());
+            }
+        }
+
+        let mut args = vec!["lib", "list"];
+        if all {
+            args.push("--all");
+        }
+        if let Some(fqbn) = fqbn {
+            args.extend(["--fqbn", fqbn]);
+        }
+        let json = self.json(&args)?;
+        let list = json.get("installed_libraries").unwrap_or(&json);
+
+        Ok(list.as_array().into_iter().flatten()
+            .map(|entry| &entry["library"])
+            .filter_map(|lib| {
+                Some(InstalledLibrary {
+                    name: lib["name"].as_str()?.to_string(),
+                    version: lib["version"].as_str().unwrap_or_default().to_string(),
+                    provides_includes: strings(&lib["provides_includes"]),
+                })
+            })
+            .collect())
+    }
+
+    async fn search_libraries(&self, query: &str) -> Result<Vec<IndexEntry>, String> {
+        let json = self.json(&["lib", "search", query])?;
+        Ok(json["libraries"].as_array().into_iter().flatten()
+            .filter_map(|lib| {
+                let latest = lib["latest"]["version"].as_str().unwrap_or_default().to_string();
+                let versions = match &lib["releases"] {
+                    serde_json::Value::Object(releases) => releases.keys().cloned().collect(),
+                    _ if !latest.is_empty() => vec![latest.clone()],
+                    _ => vec![],
+                };
+                Some(IndexEntry {
+                    name: lib["name"].as_str()?.to_string(),
+                    provides_includes: strings(&lib["latest"]["provides_includes"]),
+                    latest,
+                    versions,
+                })
+            })
+            .collect())
+    }
+
+    async fn install_library(&self, name: &str, version: Option<&str>) -> Result<(), String> {
+        if let Some(client) = self.daemon().await {
+            return client.lib_install(name, version.unwrap_or_default()).await;
+        }
+
+        let spec = match version {
+            Some(v) => format!("{}@{}", name, v),
+            None => name.to_string(),
+        };
+        let mut cmd = self.command();
+        cmd.args(&["lib", "install", &spec]);
+        self.run(cmd)
+    }
+
+    async fn install_zip_library(&self, zip_path: &Path) -> Result<(), String> {
+        if let Some(client) = self.daemon().await {
+            return client.zip_lib_install(zip_path).await;
+        }
+
+        let mut cmd = self.command();
+        cmd.args(&["lib", "install", "--zip-path"]).arg(zip_path);
+        self.run(cmd)
+    }
+
+    async fn list_cores(&self) -> Result<Vec<InstalledCore>, String> {
+        let json = self.json(&["core", "list"])?;
+        // Older arduino-cli versions print a bare array with "installed" instead of "installed_version"
+        let list = json.get("platforms").unwrap_or(&json);
+
+        Ok(list.as_array().into_iter().flatten()
+            .filter_map(|p| {
+                let id = p["id"].as_str().filter(|s| !s.is_empty())?;
+                let version = p["installed_version"].as_str().or_else(|| p["installed"].as_str()).filter(|s| !s.is_empty())?;
+                Some(InstalledCore { id: id.to_string(), version: version.to_string() })
+            })
+            .collect())
+    }
+
+    async fn install_core(&self, out: &Output, platform: &str, version: Option<&str>) -> Result<(), String> {
+        if let Some(client) = self.daemon().await {
+            return client.core_install(out.as_ref(), platform, version.unwrap_or_default()).await;
+        }
+
+        let target = match version {
+            Some(v) => format!("{}@{}", platform, v),
+            None => platform.to_string(),
+        };
+        let mut cmd = self.command();
+        cmd.args(&["core", "install", &target]);
+        stream(out, cmd).await
+    }
+
+    async fn update_index(&self, out: &Output) -> Result<(), String> {
+        if let Some(client) = self.daemon().await {
+            return client.update_index(out.as_ref()).await;
+        }
+
+        let mut cmd = self.command();
+        cmd.arg("update");
+        stream(out, cmd).await
+    }
+}
@@ -0,0 +1,310 @@
+// fake.rs - In-memory toolchain
+//
+// Answers every call from `FakeState` and records it in `calls`, so the IDE's
+// Arduino flows can be driven without arduino-cli or hardware. Compiling
+// writes an empty `.hex` to the output dir so later steps find an artifact.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
+use async_trait::async_trait;
+use tauri::async_runtime::{channel, Receiver};
+use super::super::usb_ids::BoardCandidate;
+use super::super::{sketch, BoardEvent, DetectedBoard};
+use super::{ArduinoToolchain, BoardDetails, Output, CompileRequest, IndexEntry, InstalledCore, InstalledLibrary, UploadRequest};
+
+pub const NAME: &str = "fake";
+
+#[derive(Debug, Clone, Default)]
+pub struct FakeState {
+    pub boards: Vec<DetectedBoard>,
+    pub libraries: Vec<InstalledLibrary>,
+    pub index: Vec<IndexEntry>,
+    pub cores: Vec<InstalledCore>,
+    /// By fqbn, boards of installed cores without an entry get empty details
+    pub details: HashMap<String, BoardDetails>,
+    /// Returned by compile, upload and bootloader burning when set
+    pub fail_with: Option<String>,
+    /// Every call made, e.g. `compile arduino:avr:uno`
+    pub calls: Vec<String>,
+}
+
+pub struct FakeToolchain {
+    state: Mutex<FakeState>,
+}
+
+impl FakeToolchain {
+    /// An Uno on port `fake0` with `arduino:avr` and Servo installed.
+    pub fn new() -> Self {
+        let uno = BoardCandidate { fqbn: "arduino:avr:uno".to_string(), name: "Arduino Uno".to_string() };
+        Self::with_state(FakeState {
+            boards: vec![DetectedBoard::new("fake0".to_string(), "fake0".to_string(), &HashMap::new(), vec![uno])],
+            libraries: vec![InstalledLibrary {
+                name: "Servo".to_string(),
+                version: "1.2.1".to_string(),
+                provides_includes: vec!["Servo.h".to_string()],
+            }],
+            index: vec![IndexEntry {
+                name: "Servo".to_string(),
+                latest: "1.2.2".to_string(),
+                versions: vec!["1.1.8".to_string(), "1.2.1".to_string(), "1.2.2".to_string()],
+                provides_includes: vec!["Servo.h".to_string()],
+            }],
+            cores: vec![InstalledCore { id: "arduino:avr".to_string(), version: "1.8.6".to_string() }],
+            ..Default::default()
+        })
+    }
+
+    pub fn with_state(state: FakeState) -> Self {
+        FakeToolchain { state: Mutex::new(state) }
+    }
+
+    pub fn state(&self) -> MutexGuard<'_, FakeState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Locks the state after adding `call` to the log.
+    fn record(&self, call: String) -> MutexGuard<'_, FakeState> {
+        let mut state = self.state();
+        state.calls.push(call);
+        state
+    }
+
+    fn outcome(&self, out: &Output, call: String) -> Result<(), String> {
+        let fail = self.record(call.clone()).fail_with.clone();
+        match fail {
+            Some(e) => {
+                out.stderr(e.clone());
+                Err(e)
+            }
+            None => {
+                out.output(format!("[fake] {}", call));
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Default for FakeToolchain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn core_of(fqbn: &str) -> String {
+    fqbn.split(':').take(2).collect::<Vec<_>>().join(":")
+}
+
+#[async_trait]
+impl ArduinoToolchain for FakeToolchain {
+    fn name(&self) -> &'static str {
+        NAME
+    }
+
+    async fn version(&self) -> Result<String, String> {
+        drop(self.record("version".to_string()));
+        Ok("fake".to_string())
+    }
+
+    async fn list_boards(&self) -> Result<Vec<DetectedBoard>, String> {
+        Ok(self.record("board list".to_string()).boards.clone())
+    }
+
+    async fn watch_boards(&self) -> Result<Receiver<BoardEvent>, String> {
+        let boards = self.record("board watch".to_string()).boards.clone();
+        // The current boards show up as attached, nothing changes afterwards
+        let (tx, rx) = channel(boards.len().max(1));
+        for board in boards {
+            let _ = tx.try_send(BoardEvent { event_type: "add".to_string(), board: Some(board), error: None });
+        }
+        Ok(rx)
+    }
+
+    async fn board_details(&self, fqbn: &str) -> Result<BoardDetails, String> {
+        let state = self.record(format!("board details {}", fqbn));
+        if let Some(details) = state.details.get(fqbn) {
+            return Ok(details.clone());
+        }
+        if state.cores.iter().any(|c| c.id == core_of(fqbn)) {
+            return Ok(BoardDetails { name: fqbn.to_string(), ..Default::default() });
+        }
+        Err(format!("Failed to get board details for {}: platform not installed", fqbn))
+    }
+
+    async fn compile(&self, out: &Output, request: &CompileRequest) -> Result<(), String> {
+        let target = request.profile.as_deref().unwrap_or(&request.fqbn);
+        self.outcome(out, format!("compile {}", target)).map_err(|_| "Compilation failed".to_string())?;
+        std::fs::create_dir_all(&request.output_dir).map_err(|e| e.to_string())?;
+        std::fs::write(request.output_dir.join(format!("{}.ino.hex", sketch::SKETCH_NAME)), "")
+            .map_err(|e| e.to_string())
+    }
+
+    async fn upload(&self, out: &Output, request: &UploadRequest) -> Result<(), String> {
+        self.outcome(out, format!("upload {} {}", request.fqbn, request.port))
+    }
+
+    async fn burn_bootloader(&self, out: &Output, fqbn: &str, port: &str, programmer: &str) -> Result<(), String> {
+        self.outcome(out, format!("burn-bootloader {} {} {}", fqbn, port, programmer))
+    }
+
+    async fn list_libraries(&self, _all: bool, _fqbn: Option<&str>) -> Result<Vec<InstalledLibrary>, String> {
+        Ok(self.record("lib list".to_string()).libraries.clone())
+    }
+
+    async fn search_libraries(&self, query: &str) -> Result<Vec<IndexEntry>, String> {
+        let state = self.record(format!("lib search {}", query));
+        let hits = match query.strip_prefix("provides:") {
+            Some(header) => state.index.iter().filter(|e| e.provides_includes.iter().any(|h| h == header)).cloned().collect(),
+            None => state.index.iter().filter(|e| e.name.to_lowercase().contains(&query.to_lowercase())).cloned().collect(),
+        };
+        Ok(hits)
+    }
+
+    async fn install_library(&self, name: &str, version: Option<&str>) -> Result<(), String> {
+        let mut state = self.record(format!("lib install {}@{}", name, version.unwrap_or("latest")));
+        let entry = state.index.iter().find(|e| e.name == name).cloned()
+            .ok_or_else(|| format!("Library {} not found", name))?;
+        let version = version.unwrap_or(&entry.latest).to_string();
+        if !entry.versions.contains(&version) {
+            return Err(format!("Library {}@{} not found", name, version));
+        }
+        state.libraries.retain(|l| l.name != name);
+        state.libraries.push(InstalledLibrary { name: entry.name, version, provides_includes: entry.provides_includes });
+        Ok(())
+    }
+
+    async fn install_zip_library(&self, zip_path: &Path) -> Result<(), String> {
+        drop(self.record(format!("lib install --zip-path {}", zip_path.display())));
+        Ok(())
+    }
+
+    async fn list_cores(&self) -> Result<Vec<InstalledCore>, String> {
+        Ok(self.record("core list".to_string()).cores.clone())
+    }
+
+    async fn install_core(&self, out: &Output, platform: &str, version: Option<&str>) -> Result<(), String> {
+        let version = version.unwrap_or("1.0.0");
+        self.outcome(out, format!("core install {}@{}", platform, version))?;
+        let mut state = self.state();
+        state.cores.retain(|c| c.id != platform);
+        state.cores.push(InstalledCore { id: platform.to_string(), version: version.to_string() });
+        Ok(())
+    }
+
+    async fn update_index(&self, out: &Output) -> Result<(), String> {
+        self.outcome(out, "update".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use std::sync::Arc;
+    use tauri::async_runtime::block_on;
+    use super::super::super::build_config::CompileFlags;
+    use super::super::OutputSink;
+    use super::*;
+
+    /// Collects output lines, stderr ones prefixed with `!`.
+    #[derive(Default)]
+    struct Lines(Mutex<Vec<String>>);
+
+    impl OutputSink for Lines {
+        fn output(&self, line: String) {
+            self.0.lock().unwrap().push(line);
+        }
+
+        fn stderr(&self, line: String) {
+            self.0.lock().unwrap().push(format!("!{}", line));
+        }
+    }
+
+    fn sink() -> (Arc<Lines>, Output) {
+        let lines = Arc::new(Lines::default());
+        (lines.clone(), lines)
+    }
+
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hanx_fake_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn compile_request(output_dir: PathBuf) -> CompileRequest {
+        CompileRequest {
+            fqbn: "arduino:avr:uno".to_string(),
+            sketch_dir: output_dir.join("sketch"),
+            libs: Vec::new(),
+            output_dir,
+            profile: None,
+            flags: CompileFlags { build_properties: Vec::new(), warnings: None, verbose: false },
+        }
+    }
+
+    #[test]
+    fn compile_writes_an_artifact_and_records_the_call() {
+        let fake = FakeToolchain::new();
+        let (lines, out) = sink();
+        let dir = scratch_dir("compile");
+
+        block_on(fake.compile(&out, &compile_request(dir.clone()))).unwrap();
+
+        assert!(dir.join(format!("{}.ino.hex", sketch::SKETCH_NAME)).is_file());
+        assert_eq!(fake.state().calls, ["compile arduino:avr:uno"]);
+        assert_eq!(*lines.0.lock().unwrap(), ["[fake] compile arduino:avr:uno"]);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn upload_and_burn_report_the_configured_failure() {
+        let fake = FakeToolchain::new();
+        fake.state().fail_with = Some("programmer not responding".to_string());
+        let (lines, out) = sink();
+        let request = UploadRequest {
+            fqbn: "arduino:avr:uno".to_string(),
+            sketch_dir: scratch_dir("upload"),
+            port: "fake0".to_string(),
+            programmer: None,
+            profile: None,
+            verbose: false,
+        };
+
+        assert_eq!(block_on(fake.upload(&out, &request)), Err("programmer not responding".to_string()));
+        assert!(block_on(fake.burn_bootloader(&out, "arduino:avr:uno", "", "usbasp")).is_err());
+        assert_eq!(fake.state().calls, ["upload arduino:avr:uno fake0", "burn-bootloader arduino:avr:uno  usbasp"]);
+        assert_eq!(*lines.0.lock().unwrap(), ["!programmer not responding", "!programmer not responding"]);
+    }
+
+    #[test]
+    fn library_calls_update_the_installed_libraries() {
+        let fake = FakeToolchain::new();
+
+        let hits = block_on(fake.search_libraries("provides:Servo.h")).unwrap();
+        assert_eq!(hits.iter().map(|e| e.name.as_str()).collect::<Vec<_>>(), ["Servo"]);
+
+        block_on(fake.install_library("Servo", Some("1.2.2"))).unwrap();
+        assert!(block_on(fake.install_library("Servo", Some("9.9.9"))).is_err());
+        assert!(block_on(fake.install_library("NoSuchLib", None)).is_err());
+
+        let installed = block_on(fake.list_libraries(false, None)).unwrap();
+        assert_eq!(installed.len(), 1);
+        assert_eq!(installed[0].version, "1.2.2");
+        assert_eq!(fake.state().calls, [
+            "lib search provides:Servo.h",
+            "lib install Servo@1.2.2",
+            "lib install Servo@9.9.9",
+            "lib install NoSuchLib@latest",
+            "lib list",
+        ]);
+    }
+
+    #[test]
+    fn board_details_need_an_installed_core() {
+        let fake = FakeToolchain::new();
+        let (_, out) = sink();
+
+        assert!(block_on(fake.board_details("rp2040:rp2040:rpipico")).is_err());
+        block_on(fake.install_core(&out, "rp2040:rp2040", Some("3.9.0"))).unwrap();
+        assert_eq!(block_on(fake.board_details("rp2040:rp2040:rpipico")).unwrap().name, "rp2040:rp2040:rpipico");
+    }
+}
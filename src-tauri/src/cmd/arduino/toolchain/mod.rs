@@ -0,0 +1,221 @@
+// toolchain/mod.rs - Backend-neutral interface to the Arduino build tools
+//
+// Board discovery, compiling, uploading and library/core management all go
+// through `ArduinoToolchain`. `CliToolchain` drives arduino-cli (through its
+// daemon when that backend is enabled), `FakeToolchain` answers from memory so
+// the IDE can be exercised without arduino-cli or a board. Another backend,
+// e.g. PlatformIO, only has to implement the trait and be returned by
+// `current`.
+
+pub mod cli;
+#[cfg(any(test, debug_assertions))]
+pub mod fake;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use async_trait::async_trait;
+use serde::Serialize;
+use tauri::async_runtime::Receiver;
+use tauri::{AppHandle, Emitter, Runtime, Window};
+use crate::cmd::sys::env_manager::arduino::get_config_file;
+use super::build_config::CompileFlags;
+use super::daemon::ArduinoProgress;
+use super::{ArduinoProgrammer, BoardEvent, DetectedBoard};
+
+pub use self::cli::CliToolchain;
+#[cfg(any(test, debug_assertions))]
+pub use self::fake::FakeToolchain;
+
+/// Set to `fake` to start debug builds with the fake toolchain instead of arduino-cli
+#[cfg(debug_assertions)]
+pub const TOOLCHAIN_ENV: &str = "HANX_ARDUINO_TOOLCHAIN";
+
+lazy_static::lazy_static! {
+    /// Replaces arduino-cli for every caller when set
+    static ref OVERRIDE: RwLock<Option<Arc<dyn ArduinoToolchain>>> = RwLock::new(from_env());
+}
+
+#[derive(Debug)]
+pub struct CompileRequest {
+    pub fqbn: String,
+    pub sketch_dir: PathBuf,
+    /// Extra library folders
+    pub libs: Vec<String>,
+    /// Where the binaries are exported, see `sketch::output_dir`
+    pub output_dir: PathBuf,
+    /// sketch.yaml profile to build with instead of `fqbn`
+    pub profile: Option<String>,
+    pub flags: CompileFlags,
+}
+
+#[derive(Debug)]
+pub struct UploadRequest {
+    pub fqbn: String,
+    pub sketch_dir: PathBuf,
+    /// Empty for programmers without a serial port
+    pub port: String,
+    pub programmer: Option<String>,
+    pub profile: Option<String>,
+    pub verbose: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InstalledLibrary {
+    pub name: String,
+    pub version: String,
+    pub provides_includes: Vec<String>,
+}
+
+/// A library of the library index.
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexEntry {
+    pub name: String,
+    pub latest: String,
+    /// Every released version, in no particular order
+    pub versions: Vec<String>,
+    /// Headers of the latest release
+    pub provides_includes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InstalledCore {
+    /// `<package>:<arch>`
+    pub id: String,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BoardDetails {
+    pub name: String,
+    pub programmers: Vec<ArduinoProgrammer>,
+    /// Expanded build properties, e.g. `build.variant.path`
+    pub build_properties: HashMap<String, String>,
+}
+
+/// Receives the output of long-running operations. The app passes its window,
+/// which emits the lines as `arduino-output` / `arduino-stderr` events.
+pub trait OutputSink: Send + Sync {
+    fn output(&self, line: String);
+    fn stderr(&self, line: String);
+    /// Download and task progress, `arduino-progress`
+    fn progress(&self, _progress: ArduinoProgress) {}
+}
+
+/// Shared so reader threads can hold on to it.
+pub type Output = Arc<dyn OutputSink>;
+
+impl<R: Runtime> OutputSink for Window<R> {
+    fn output(&self, line: String) {
+        self.emit("arduino-output", line).unwrap_or(());
+    }
+
+    fn stderr(&self, line: String) {
+        self.emit("arduino-stderr", line).unwrap_or(());
+    }
+
+    fn progress(&self, progress: ArduinoProgress) {
+        self.emit("arduino-progress", progress).unwrap_or(());
+    }
+}
+
+/// The `Output` of a window.
+pub fn window_output<R: Runtime>(window: &Window<R>) -> Output {
+    Arc::new(window.clone())
+}
+
+/// Everything the IDE asks of an Arduino toolchain. Long-running operations
+/// stream their output to `out` and return once they are done; reporting
+/// `arduino-finished` is up to the caller.
+#[async_trait]
+pub trait ArduinoToolchain: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Version of the underlying tool, fails when it isn't available.
+    async fn version(&self) -> Result<String, String>;
+
+    /// Creates whatever configuration the tool needs before first use.
+    async fn setup(&self) -> Result<(), String> {
+        Ok(())
+    }
+
+    /// Connected boards, not yet matched against `usb_ids`.
+    async fn list_boards(&self) -> Result<Vec<DetectedBoard>, String>;
+
+    /// Streams board attach/detach events until the receiver is dropped.
+    async fn watch_boards(&self) -> Result<Receiver<BoardEvent>, String>;
+
+    async fn board_details(&self, fqbn: &str) -> Result<BoardDetails, String>;
+
+    /// Compiler errors go to `stderr`, warnings of a successful build to `output`.
+    async fn compile(&self, out: &Output, request: &CompileRequest) -> Result<(), String>;
+
+    async fn upload(&self, out: &Output, request: &UploadRequest) -> Result<(), String>;
+
+    async fn burn_bootloader(&self, out: &Output, fqbn: &str, port: &str, programmer: &str) -> Result<(), String>;
+
+    /// Installed libraries; `all` includes the ones bundled with platforms,
+    /// `fqbn` limits those to the board's platform.
+    async fn list_libraries(&self, all: bool, fqbn: Option<&str>) -> Result<Vec<InstalledLibrary>, String>;
+
+    /// Searches the library index, `provides:<header>` finds libraries by header.
+    async fn search_libraries(&self, query: &str) -> Result<Vec<IndexEntry>, String>;
+
+    /// Installs `name` at `version` (replacing another installed version), the latest when `None`.
+    async fn install_library(&self, name: &str, version: Option<&str>) -> Result<(), String>;
+
+    async fn install_zip_library(&self, zip_path: &std::path::Path) -> Result<(), String>;
+
+    async fn list_cores(&self) -> Result<Vec<InstalledCore>, String>;
+
+    async fn install_core(&self, out: &Output, platform: &str, version: Option<&str>) -> Result<(), String>;
+
+    /// Refreshes the core and library indexes.
+    async fn update_index(&self, out: &Output) -> Result<(), String>;
+}
+
+#[cfg(debug_assertions)]
+fn from_env() -> Option<Arc<dyn ArduinoToolchain>> {
+    match std::env::var(TOOLCHAIN_ENV).ok()?.as_str() {
+        fake::NAME => {
+            println!("[Arduino] 🧪 Using the fake toolchain ({}={})", TOOLCHAIN_ENV, fake::NAME);
+            Some(Arc::new(FakeToolchain::new()))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(not(debug_assertions))]
+fn from_env() -> Option<Arc<dyn ArduinoToolchain>> {
+    None
+}
+
+/// The toolchain every Arduino operation goes through.
+pub fn current<R: Runtime>(app_handle: &AppHandle<R>) -> Arc<dyn ArduinoToolchain> {
+    if let Some(toolchain) = OVERRIDE.read().unwrap().clone() {
+        return toolchain;
+    }
+    Arc::new(CliToolchain::new(get_config_file(app_handle)))
+}
+
+/// Replaces the toolchain for all later calls, `None` goes back to arduino-cli.
+pub fn set_toolchain(toolchain: Option<Arc<dyn ArduinoToolchain>>) {
+    *OVERRIDE.write().unwrap() = toolchain;
+}
+
+#[tauri::command]
+pub async fn get_arduino_toolchain(app_handle: AppHandle) -> Result<String, String> {
+    Ok(current(&app_handle).name().to_string())
+}
+
+#[tauri::command]
+pub async fn set_arduino_toolchain(name: String) -> Result<String, String> {
+    match name.as_str() {
+        cli::NAME => set_toolchain(None),
+        #[cfg(debug_assertions)]
+        fake::NAME => set_toolchain(Some(Arc::new(FakeToolchain::new()))),
+        _ => return Err(format!("Unknown Arduino toolchain: {}", name)),
+    }
+    println!("[Arduino] 🔧 Toolchain: {}", name);
+    Ok(format!("Arduino toolchain: {}", name))
+}
@@ -0,0 +1,319 @@
+// uf2.rs - Drag-and-drop flashing for UF2 bootloaders (RP2040, SAMD21/51, nRF52, ...)
+//
+// A board in UF2 bootloader mode shows up as a USB drive holding
+// `INFO_UF2.TXT`. Flashing is copying the `.uf2` onto it; the board then
+// reboots into the sketch and comes back as a serial port. When no volume is
+// mounted, the board is asked to enter the bootloader with a 1200 baud touch
+// on its serial port, as the Arduino cores do.
+//
+// Mount roots can be overridden, so a plain directory with an INFO_UF2.TXT
+// in it works as a fake volume.
+
+use std::collections::HashSet;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager, Window};
+use crate::cmd::serial::OPEN_PORT;
+use super::sketch::{self, prepare_sketch};
+
+const INFO_FILE: &str = "INFO_UF2.TXT";
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+const DEFAULT_VOLUME_TIMEOUT_MS: u64 = 10_000;
+const DEFAULT_PORT_TIMEOUT_MS: u64 = 10_000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Uf2Volume {
+    pub path: String,
+    pub model: Option<String>,
+    pub board_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Uf2Options {
+    /// Directories whose entries (or themselves) may be UF2 volumes, instead of the OS defaults
+    pub mount_roots: Option<Vec<String>>,
+    pub volume_timeout_ms: Option<u64>,
+    /// 0 skips waiting for the serial port
+    pub port_timeout_ms: Option<u64>,
+}
+
+/// Where removable drives get mounted.
+fn default_mount_roots() -> Vec<PathBuf> {
+    if cfg!(target_os = "windows") {
+        return (b'D'..=b'Z').map(|letter| PathBuf::from(format!("{}:\\", letter as char))).collect();
+    }
+    if cfg!(target_os = "macos") {
+        return vec![PathBuf::from("/Volumes")];
+    }
+    let mut roots = Vec::new();
+    // Without a user these would be /media and /run/media/ again
+    if let Some(user) = std::env::var("USER").ok().filter(|u| !u.is_empty()) {
+        roots.push(PathBuf::from("/media").join(&user));
+        roots.push(PathBuf::from("/run/media").join(&user));
+    }
+    roots.extend([PathBuf::from("/media"), PathBuf::from("/mnt")]);
+    roots
+}
+
+fn mount_roots(options: &Uf2Options) -> Vec<PathBuf> {
+    match &options.mount_roots {
+        Some(roots) => roots.iter().map(PathBuf::from).collect(),
+        None => default_mount_roots(),
+    }
+}
+
+fn read_volume(path: &Path) -> Option<Uf2Volume> {
+    let info = fs::read_to_string(path.join(INFO_FILE)).ok()?;
+    let field = |key: &str| {
+        info.lines()
+            .find_map(|line| line.strip_prefix(key)?.trim_start().strip_prefix(':'))
+            .map(|v| v.trim().to_string())
+    };
+    Some(Uf2Volume {
+        path: path.to_string_lossy().to_string(),
+        model: field("Model"),
+        board_id: field("Board-ID"),
+    })
+}
+
+/// Mounted UF2 bootloader volumes: each root itself, or any directory directly in it.
+/// Overlapping roots may reach a volume twice, it is listed once.
+pub fn find_volumes(roots: &[PathBuf]) -> Vec<Uf2Volume> {
+    let mut seen = HashSet::new();
+    let mut volumes = Vec::new();
+    let mut add = |path: &Path| {
+        let Some(volume) = read_volume(path) else {
+            return false;
+        };
+        if seen.insert(path.canonicalize().unwrap_or_else(|_| path.to_path_buf())) {
+            volumes.push(volume);
+        }
+        true
+    };
+    for root in roots {
+        if add(root) {
+            continue;
+        }
+        for entry in fs::read_dir(root).into_iter().flatten().flatten() {
+            add(&entry.path());
+        }
+    }
+    volumes
+}
+
+/// Copies `uf2` onto the volume. The board reboots as soon as the last block
+/// arrives, so errors once everything was written are not failures.
+pub fn copy_to_volume(uf2: &Path, volume: &Path) -> Result<(), String> {
+    let data = fs::read(uf2).map_err(|e| format!("Failed to read {:?}: {}", uf2, e))?;
+    let name = uf2.file_name().ok_or("Invalid UF2 path")?;
+    let mut file = File::create(volume.join(name)).map_err(|e| format!("Failed to write to {:?}: {}", volume, e))?;
+    file.write_all(&data).map_err(|e| format!("Failed to write to {:?}: {}", volume, e))?;
+    if let Err(e) = file.sync_all() {
+        println!("[UF2] ℹ️ Sync after copy failed (board probably rebooted): {}", e);
+    }
+    Ok(())
+}
+
+fn serial_ports() -> HashSet<String> {
+    serialport::available_ports()
+        .map(|ports| ports.into_iter().map(|p| p.port_name).collect())
+        .unwrap_or_default()
+}
+
+/// Opening the port at 1200 baud and closing it makes Arduino cores reboot into the bootloader.
+fn touch_1200(port: &str) -> Result<(), String> {
+    let p = serialport::new(port, 1200)
+        .timeout(Duration::from_millis(100))
+        .open()
+        .map_err(|e| format!("Failed to open {}: {}", port, e))?;
+    drop(p);
+    Ok(())
+}
+
+fn wait_for<T>(timeout: Duration, mut probe: impl FnMut() -> Option<T>) -> Option<T> {
+    let start = Instant::now();
+    loop {
+        if let Some(found) = probe() {
+            return Some(found);
+        }
+        if start.elapsed() >= timeout {
+            return None;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// The `.uf2` last exported for `fqbn` by `compile_arduino`.
+fn compiled_uf2(fqbn: &str) -> Result<PathBuf, String> {
+    fs::read_dir(sketch::output_dir(fqbn))
+        .map_err(|_| "No compiled sketch found, compile it for this board first".to_string())?
+        .flatten()
+        .map(|e| e.path())
+        .find(|p| p.extension().map_or(false, |ext| ext == "uf2"))
+        .ok_or_else(|| format!("The build for {} produced no .uf2 file", fqbn))
+}
+
+/// Flashes `uf2`, returning the serial port the board came back on, if any.
+fn flash(window: &Window, uf2: &Path, port: Option<&str>, options: &Uf2Options) -> Result<Option<String>, String> {
+    let roots = mount_roots(options);
+    let ports_before = serial_ports();
+
+    let mut volumes = find_volumes(&roots);
+    if volumes.is_empty() {
+        match port {
+            Some(port) => {
+                window.emit("arduino-output", format!("Resetting {} into the UF2 bootloader...", port)).unwrap_or(());
+                touch_1200(port)?;
+            }
+            None => {
+                window.emit("arduino-output", "Waiting for a UF2 drive (hold BOOTSEL while plugging the board in)...").unwrap_or(());
+            }
+        }
+        let timeout = Duration::from_millis(options.volume_timeout_ms.unwrap_or(DEFAULT_VOLUME_TIMEOUT_MS));
+        volumes = wait_for(timeout, || Some(find_volumes(&roots)).filter(|v| !v.is_empty()))
+            .ok_or("No UF2 bootloader drive found")?;
+    }
+    if volumes.len() > 1 {
+        let paths: Vec<&str> = volumes.iter().map(|v| v.path.as_str()).collect();
+        return Err(format!("Several UF2 drives found, keep only one board connected: {}", paths.join(", ")));
+    }
+
+    let volume = &volumes[0];
+    window.emit("arduino-output", format!(
+        "Copying {} to {} ({})...",
+        uf2.file_name().unwrap_or_default().to_string_lossy(),
+        volume.path,
+        volume.board_id.as_deref().or(volume.model.as_deref()).unwrap_or("unknown board"),
+    )).unwrap_or(());
+    copy_to_volume(uf2, Path::new(&volume.path))?;
+    println!("[UF2] ✅ Copied {:?} to {}", uf2, volume.path);
+
+    let port_timeout = options.port_timeout_ms.unwrap_or(DEFAULT_PORT_TIMEOUT_MS);
+    if port_timeout == 0 {
+        return Ok(None);
+    }
+    window.emit("arduino-output", "Waiting for the board to restart...").unwrap_or(());
+    // The board's port disappears in bootloader mode, so it counts as new when it returns
+    let known: HashSet<String> = ports_before.into_iter().filter(|p| Some(p.as_str()) != port).collect();
+    Ok(wait_for(Duration::from_millis(port_timeout), || {
+        serial_ports().into_iter().find(|p| !known.contains(p))
+    }))
+}
+
+#[tauri::command]
+pub fn list_uf2_volumes(mount_roots: Option<Vec<String>>) -> Result<Vec<Uf2Volume>, String> {
+    let options = Uf2Options { mount_roots, ..Default::default() };
+    Ok(find_volumes(&self::mount_roots(&options)))
+}
+
+/// Compiles the sketch and flashes it through the board's UF2 bootloader drive.
+#[tauri::command]
+pub async fn upload_uf2(
+    window: Window,
+    code: String,
+    fqbn: String,
+    libs: Vec<String>,
+    port: Option<String>,
+    project_path: Option<String>,
+    options: Option<Uf2Options>,
+) -> Result<(), String> {
+    let port = port.filter(|p| !p.is_empty());
+    if port.is_some() {
+        *OPEN_PORT.lock().unwrap() = None;
+        window.emit("serial-data", "\n[System] Auto-closed serial for upload.\n").unwrap_or(());
+    }
+
+    let sketch_dir = prepare_sketch(window.app_handle(), &code)?;
+    window.emit("arduino-output", format!("Compiling for {}...", fqbn)).unwrap_or(());
+    super::compile_sketch(&window, &sketch_dir, &fqbn, &libs, project_path.as_deref()).await?;
+    let uf2 = compiled_uf2(&fqbn)?;
+
+    let options = options.unwrap_or_default();
+    let flash_window = window.clone();
+    let result = tauri::async_runtime::spawn_blocking(move || flash(&flash_window, &uf2, port.as_deref(), &options))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let msg = match &result {
+        Ok(Some(port)) => format!("Upload successful! The board is back on {}.", port),
+        Ok(None) => "Upload successful!".to_string(),
+        Err(e) => e.clone(),
+    };
+    window.emit("arduino-finished", msg).unwrap_or(());
+    result.map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INFO: &str = "UF2 Bootloader v3.0\nModel: Raspberry Pi RP2\nBoard-ID: RPI-RP2\n";
+
+    /// An empty scratch directory, unique per test.
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hanx_uf2_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn finds_a_root_that_is_a_volume() {
+        let root = scratch_dir("root");
+        fs::write(root.join(INFO_FILE), INFO).unwrap();
+
+        let volumes = find_volumes(std::slice::from_ref(&root));
+        assert_eq!(volumes.len(), 1);
+        assert_eq!(volumes[0].path, root.to_string_lossy());
+        assert_eq!(volumes[0].model.as_deref(), Some("Raspberry Pi RP2"));
+        assert_eq!(volumes[0].board_id.as_deref(), Some("RPI-RP2"));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn finds_volumes_inside_a_root() {
+        let root = scratch_dir("child");
+        let volume = root.join("RPI-RP2");
+        fs::create_dir_all(&volume).unwrap();
+        fs::create_dir_all(root.join("USB_STICK")).unwrap();
+        fs::write(volume.join(INFO_FILE), INFO).unwrap();
+
+        let volumes = find_volumes(std::slice::from_ref(&root));
+        assert_eq!(volumes.len(), 1);
+        assert_eq!(volumes[0].path, volume.to_string_lossy());
+        assert_eq!(volumes[0].board_id.as_deref(), Some("RPI-RP2"));
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn lists_a_volume_reached_through_several_roots_once() {
+        let root = scratch_dir("overlap");
+        let volume = root.join("RPI-RP2");
+        fs::create_dir_all(&volume).unwrap();
+        fs::write(volume.join(INFO_FILE), INFO).unwrap();
+
+        // As with /media/ and /media when USER is empty
+        let roots = [root.join(""), root.clone(), volume.clone(), root.join("RPI-RP2").join("..").join("RPI-RP2")];
+        assert_eq!(find_volumes(&roots).len(), 1);
+        let _ = fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn copies_the_uf2_unchanged() {
+        let dir = scratch_dir("copy");
+        let volume = dir.join("volume");
+        fs::create_dir_all(&volume).unwrap();
+        let uf2 = dir.join("sketch.ino.uf2");
+        let data: Vec<u8> = (0..2048u32).map(|i| (i % 251) as u8).collect();
+        fs::write(&uf2, &data).unwrap();
+
+        copy_to_volume(&uf2, &volume).unwrap();
+        assert_eq!(fs::read(volume.join("sketch.ino.uf2")).unwrap(), data);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,181 @@
+// usb_ids.rs - Fallback board identification from USB VID/PID
+//
+// arduino-cli only recognizes boards whose VID/PID is declared by an installed
+// core, so clones with generic USB-serial chips (CH340, CP2102, ...) come back
+// unidentified. This module guesses candidates from a bundled VID/PID table,
+// extended by `arduino/usb_ids.json` in any Arduino extension, and remembers the
+// board the user picked for a given device.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+use crate::cmd::sys::extensions::get_platform_extensions_dir;
+use super::DetectedBoard;
+
+const BUNDLED_USB_IDS: &str = include_str!("usb_ids.json");
+const CHOICES_FILE_NAME: &str = "board_choices.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BoardCandidate {
+    pub fqbn: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BoardConfidence {
+    /// Identified by arduino-cli from an installed core
+    Exact,
+    /// The user picked this board for the device before
+    Remembered,
+    /// The VID/PID maps to a single known board
+    High,
+    /// The VID/PID belongs to a generic chip used by several boards
+    Low,
+    #[default]
+    None,
+}
+
+#[derive(Debug, Deserialize)]
+struct UsbIdEntry {
+    vid: String,
+    pid: String,
+    #[serde(default)]
+    candidates: Vec<BoardCandidate>,
+}
+
+/// Normalizes "0x1A86" / "1a86" to "1a86".
+pub fn normalize_id(id: &str) -> String {
+    id.trim().trim_start_matches("0x").trim_start_matches("0X").to_lowercase()
+}
+
+/// Key under which a user's board choice is stored. Many clone chips (e.g. CH340)
+/// report no serial number, in which case the VID/PID and port stand in for it.
+pub fn choice_key(serial_number: Option<&str>, vid: Option<&str>, pid: Option<&str>, port: &str) -> Option<String> {
+    match (serial_number.filter(|s| !s.is_empty()), vid, pid) {
+        (Some(serial), _, _) => Some(serial.to_string()),
+        (None, Some(vid), Some(pid)) => Some(format!("{}:{}@{}", vid, pid, port)),
+        _ => None,
+    }
+}
+
+fn load_database(app_handle: &tauri::AppHandle) -> HashMap<(String, String), Vec<BoardCandidate>> {
+    let mut db: HashMap<(String, String), Vec<BoardCandidate>> = HashMap::new();
+
+    let mut merge = |entries: Vec<UsbIdEntry>, prepend: bool| {
+        for entry in entries {
+            let key = (normalize_id(&entry.vid), normalize_id(&entry.pid));
+            let list = db.entry(key).or_default();
+            let new: Vec<BoardCandidate> = entry.candidates.into_iter()
+                .filter(|c| !list.iter().any(|existing| existing.fqbn == c.fqbn))
+                .collect();
+            if prepend {
+                list.splice(0..0, new);
+            } else {
+                list.extend(new);
+            }
+        }
+    };
+
+    match serde_json::from_str::<Vec<UsbIdEntry>>(BUNDLED_USB_IDS) {
+        Ok(entries) => merge(entries, false),
+        Err(e) => println!("[Arduino] ❌ Failed to parse bundled USB id table: {}", e),
+    }
+
+    // Boards shipped by extensions are more specific than the generic table
+    let extensions_dir = get_platform_extensions_dir(app_handle, "arduino");
+    if let Ok(entries) = fs::read_dir(&extensions_dir) {
+        for entry in entries.flatten() {
+            let file = entry.path().join("arduino").join("usb_ids.json");
+            if !file.exists() {
+                continue;
+            }
+            match fs::read_to_string(&file).map_err(|e| e.to_string())
+                .and_then(|s| serde_json::from_str::<Vec<UsbIdEntry>>(&s).map_err(|e| e.to_string()))
+            {
+                Ok(entries) => merge(entries, true),
+                Err(e) => println!("[Arduino] ⚠️ Ignoring invalid {:?}: {}", file, e),
+            }
+        }
+    }
+
+    db
+}
+
+fn choices_path(app_handle: &tauri::AppHandle) -> PathBuf {
+    let mut path = app_handle.path().app_data_dir().unwrap_or(PathBuf::from("."));
+    path.push(CHOICES_FILE_NAME);
+    path
+}
+
+fn load_choices(app_handle: &tauri::AppHandle) -> HashMap<String, BoardCandidate> {
+    fs::read_to_string(choices_path(app_handle))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_choices(app_handle: &tauri::AppHandle, choices: &HashMap<String, BoardCandidate>) -> Result<(), String> {
+    let path = choices_path(app_handle);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(choices).map_err(|e| e.to_string())?;
+    fs::write(&path, json).map_err(|e| e.to_string())
+}
+
+/// Fills in candidates and confidence for boards arduino-cli could not identify.
+pub fn identify(app_handle: &tauri::AppHandle, boards: &mut [DetectedBoard]) {
+    for board in boards.iter_mut().filter(|b| b.fqbn.is_some()) {
+        board.confidence = BoardConfidence::Exact;
+    }
+    if boards.iter().all(|b| b.fqbn.is_some()) {
+        return;
+    }
+
+    let db = load_database(app_handle);
+    let choices = load_choices(app_handle);
+
+    for board in boards.iter_mut().filter(|b| b.fqbn.is_none()) {
+        let candidates = match (&board.vid, &board.pid) {
+            (Some(vid), Some(pid)) => db.get(&(vid.clone(), pid.clone())).cloned().unwrap_or_default(),
+            _ => Vec::new(),
+        };
+
+        let remembered = board.choice_key.as_ref().and_then(|k| choices.get(k));
+        let (best, confidence) = match remembered {
+            Some(choice) => (Some(choice.clone()), BoardConfidence::Remembered),
+            None => match candidates.len() {
+                0 => (None, BoardConfidence::None),
+                1 => (candidates.first().cloned(), BoardConfidence::High),
+                _ => (candidates.first().cloned(), BoardConfidence::Low),
+            },
+        };
+
+        if let Some(best) = best {
+            board.board_name = Some(best.name);
+            board.fqbn = Some(best.fqbn);
+        }
+        board.candidates = candidates;
+        board.confidence = confidence;
+    }
+}
+
+/// Remembers the board picked for a device (see `DetectedBoard::choice_key`).
+#[tauri::command]
+pub fn remember_board_choice(app_handle: tauri::AppHandle, choice_key: String, fqbn: String, name: String) -> Result<(), String> {
+    let mut choices = load_choices(&app_handle);
+    choices.insert(choice_key, BoardCandidate { fqbn, name });
+    save_choices(&app_handle, &choices)
+}
+
+#[tauri::command]
+pub fn forget_board_choice(app_handle: tauri::AppHandle, choice_key: String) -> Result<(), String> {
+    let mut choices = load_choices(&app_handle);
+    if choices.remove(&choice_key).is_some() {
+        save_choices(&app_handle, &choices)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,43 @@
+// blocks.rs - Block markers in generated Python code
+//
+// The Python generator puts `# hanx-block: <id>` on its own line before the
+// code of each block, or at the end of its first line. Every line belongs to
+// the closest marker above it, so nested blocks map to the innermost one.
+
+const MARKER: &str = "# hanx-block:";
+
+/// The block of every line, index 0 being line 1.
+pub fn line_blocks(code: &str) -> Vec<Option<String>> {
+    let mut current: Option<String> = None;
+    code.lines()
+        .map(|line| {
+            if let Some(pos) = line.find(MARKER) {
+                let id = line[pos + MARKER.len()..].trim();
+                current = Some(id.to_string()).filter(|id| !id.is_empty());
+            }
+            current.clone()
+        })
+        .collect()
+}
+
+/// The block of a 1-based line.
+pub fn block_at(code: &str, line: usize) -> Option<String> {
+    line.checked_sub(1).and_then(|i| line_blocks(code).into_iter().nth(i).flatten())
+}
+
+/// The first code line of each of `blocks`, 1-based; unknown blocks are skipped.
+pub fn block_lines(code: &str, blocks: &[String]) -> Vec<usize> {
+    let owners = line_blocks(code);
+    let mut lines: Vec<usize> = blocks.iter()
+        .filter_map(|block| {
+            code.lines().zip(&owners).position(|(line, owner)| {
+                let line = line.trim();
+                owner.as_deref() == Some(block.as_str()) && !line.is_empty() && !line.starts_with('#')
+            })
+        })
+        .map(|i| i + 1)
+        .collect();
+    lines.sort_unstable();
+    lines.dedup();
+    lines
+}
@@ -0,0 +1,130 @@
+# debugger.py - bdb harness for stepping through a user script
+#
+# Started as `python -u hanx_debugger.py <script>` with HANX_DEBUG_PORT set.
+# Talks to the IDE over a localhost socket in JSON lines: it waits for
+# {"cmd": "init", "lines": [...], "stop_on_entry": bool}, then reports every
+# stop as {"type": "paused", "line", "reason", "variables"} and resumes on
+# {"cmd": "continue" | "step" | "next" | "out"}. {"cmd": "pause"} and
+# {"cmd": "breakpoints", "lines": [...]} are handled while running too.
+# Only lines of the script itself are stopped at.
+
+import bdb
+import builtins
+import json
+import os
+import queue
+import socket
+import sys
+import threading
+
+import hanx_runtime
+
+hanx_runtime.HIDDEN_FILES.update({__file__, bdb.__file__})
+
+
+class Debugger(bdb.Bdb):
+    def __init__(self, script, conn):
+        super().__init__()
+        self.script = self.canonic(script)
+        self.conn = conn
+        self.send_lock = threading.Lock()
+        self.commands = queue.Queue()
+        self.paused = False
+        self.pause_requested = False
+        self.stop_on_entry = True
+        self.started = False
+
+    def send(self, message):
+        with self.send_lock:
+            self.conn.sendall((json.dumps(message) + "\n").encode("utf-8"))
+
+    def set_breakpoints(self, lines):
+        self.clear_all_breaks()
+        for line in lines:
+            self.set_break(self.script, line)
+
+    def listen(self, reader):
+        for raw in reader:
+            message = json.loads(raw)
+            cmd = message.get("cmd")
+            if cmd == "breakpoints":
+                self.set_breakpoints(message.get("lines", []))
+            elif cmd == "pause":
+                if not self.paused:
+                    self.pause_requested = True
+                    self.set_step()
+            elif self.paused:
+                self.commands.put(cmd)
+        # The IDE is gone, nobody can resume us
+        os._exit(1)
+
+    # Only the script is stepped through, library code runs freely
+    def stop_here(self, frame):
+        return self.canonic(frame.f_code.co_filename) == self.script and super().stop_here(frame)
+
+    # Keep tracing every script frame, so pause works anywhere in it
+    def break_anywhere(self, frame):
+        return self.canonic(frame.f_code.co_filename) == self.script or super().break_anywhere(frame)
+
+    # Unlike bdb, keep the trace function installed while running, for pause
+    def set_continue(self):
+        self._set_stopinfo(self.botframe, None, -1)
+
+    def user_line(self, frame):
+        if self.canonic(frame.f_code.co_filename) != self.script:
+            return
+        at_breakpoint = self.get_break(self.script, frame.f_lineno)
+        if not self.started:
+            self.started = True
+            if not self.stop_on_entry and not at_breakpoint:
+                self.set_continue()
+                return
+            reason = "entry"
+        elif self.pause_requested:
+            reason = "pause"
+        elif at_breakpoint:
+            reason = "breakpoint"
+        else:
+            reason = "step"
+        self.pause_requested = False
+
+        self.paused = True
+        self.send({"type": "paused", "line": frame.f_lineno, "reason": reason, "variables": hanx_runtime.variables(frame.f_locals)})
+        cmd = self.commands.get()
+        self.paused = False
+
+        if cmd == "step":
+            self.set_step()
+        elif cmd == "next":
+            self.set_next(frame)
+        elif cmd == "out":
+            self.set_return(frame)
+        else:
+            self.set_continue()
+
+
+def main():
+    script = hanx_runtime.prepare(sys.argv[1])
+    conn = socket.create_connection(("127.0.0.1", int(os.environ["HANX_DEBUG_PORT"])))
+    reader = conn.makefile("r", encoding="utf-8")
+    init = json.loads(reader.readline())
+
+    debugger = Debugger(script, conn)
+    debugger.stop_on_entry = init.get("stop_on_entry", True)
+    debugger.set_breakpoints(init.get("lines", []))
+    threading.Thread(target=debugger.listen, args=(reader,), daemon=True).start()
+
+    with open(script, encoding="utf-8") as f:
+        code = compile(f.read(), script, "exec")
+    globals_ = {"__name__": "__main__", "__file__": script, "__builtins__": builtins}
+    try:
+        debugger.run(code, globals_)
+    except SystemExit:
+        raise
+    except BaseException as e:
+        hanx_runtime.report_exception(e, script)
+        sys.exit(1)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,127 @@
+// debugger.rs - Step debugging of Python programs
+//
+// `debug_python_code` starts the program under `debugger.py`, a bdb harness
+// that connects back over a localhost socket. Stops are emitted as
+// `python-debug-paused` with the line mapped to its block (see `blocks`);
+// output, input and the end of the run use the usual `python-*` events.
+
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager, Window};
+use super::{blocks, runtime};
+use super::runtime::Variable;
+
+const DEBUGGER_SOURCE: &str = include_str!("debugger.py");
+const DEBUGGER_FILE: &str = "hanx_debugger.py";
+const COMMANDS: [&str; 5] = ["continue", "step", "next", "out", "pause"];
+
+static NEXT_SESSION: AtomicU64 = AtomicU64::new(1);
+
+lazy_static::lazy_static! {
+    static ref SESSION: Mutex<Option<DebugSession>> = Mutex::new(None);
+}
+
+struct DebugSession {
+    id: u64,
+    conn: TcpStream,
+    code: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum HarnessMessage {
+    Paused { line: usize, reason: String, variables: Vec<Variable> },
+}
+
+/// Payload of `python-debug-paused`.
+#[derive(Debug, Clone, Serialize)]
+pub struct DebugPaused {
+    pub line: usize,
+    pub block_id: Option<String>,
+    /// `entry`, `step`, `breakpoint` or `pause`
+    pub reason: String,
+    pub variables: Vec<Variable>,
+}
+
+fn write_debugger() -> Result<PathBuf, String> {
+    let path = std::env::temp_dir().join(DEBUGGER_FILE);
+    fs::write(&path, DEBUGGER_SOURCE).map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+    Ok(path)
+}
+
+fn listen(window: Window, conn: TcpStream, session_id: u64, code: String) {
+    for line in BufReader::new(conn).lines().map_while(Result::ok) {
+        match serde_json::from_str::<HarnessMessage>(&line) {
+            Ok(HarnessMessage::Paused { line, reason, variables }) => {
+                let paused = DebugPaused { line, block_id: blocks::block_at(&code, line), reason, variables };
+                window.emit("python-debug-paused", paused).unwrap_or(());
+            }
+            Err(e) => println!("[Python] ⚠️ Invalid debugger message: {}", e),
+        }
+    }
+    let mut session = SESSION.lock().unwrap();
+    if session.as_ref().map_or(false, |s| s.id == session_id) {
+        *session = None;
+    }
+}
+
+/// Runs the program under the debugger. `breakpoints` are block ids; with
+/// `stop_on_entry` (the default) it pauses before the first line.
+#[tauri::command]
+pub async fn debug_python_code(
+    window: Window,
+    code: String,
+    libs: Vec<String>,
+    breakpoints: Option<Vec<String>>,
+    stop_on_entry: Option<bool>,
+    project: Option<String>,
+) -> Result<(), String> {
+    let listener = TcpListener::bind("127.0.0.1:0").map_err(|e| format!("Failed to open the debugger port: {}", e))?;
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+
+    runtime::write_runtime()?;
+    let launcher = write_debugger()?;
+    let python = super::project_env::resolve_python_bin(window.app_handle(), project).await?;
+    let options = super::RunOptions { python, libs, ..Default::default() };
+    super::start_program(&window, &code, &launcher, &[("HANX_DEBUG_PORT", port.to_string())], options)?;
+
+    let conn = tauri::async_runtime::spawn_blocking(move || runtime::accept_harness(&listener))
+        .await
+        .map_err(|e| e.to_string())??;
+    let lines = blocks::block_lines(&code, &breakpoints.unwrap_or_default());
+    runtime::send_harness(&conn, serde_json::json!({ "cmd": "init", "lines": lines, "stop_on_entry": stop_on_entry.unwrap_or(true) }))?;
+
+    let id = NEXT_SESSION.fetch_add(1, Ordering::SeqCst);
+    let reader = conn.try_clone().map_err(|e| e.to_string())?;
+    *SESSION.lock().unwrap() = Some(DebugSession { id, conn, code: code.clone() });
+    thread::spawn(move || listen(window, reader, id, code));
+    println!("[Python] 🐞 Debugging on port {}", port);
+    Ok(())
+}
+
+/// `continue`, `step` (into calls), `next` (over calls), `out` (of the current function) or `pause`.
+/// Stopping is `stop_execution`.
+#[tauri::command]
+pub fn python_debug_command(command: String) -> Result<(), String> {
+    if !COMMANDS.contains(&command.as_str()) {
+        return Err(format!("Unknown debugger command {}, expected one of {}", command, COMMANDS.join(", ")));
+    }
+    let session = SESSION.lock().unwrap();
+    let session = session.as_ref().ok_or("No Python program is being debugged")?;
+    runtime::send_harness(&session.conn, serde_json::json!({ "cmd": command }))
+}
+
+/// Replaces the breakpoints of the running debug session, by block id.
+#[tauri::command]
+pub fn set_python_breakpoints(blocks: Vec<String>) -> Result<(), String> {
+    let session = SESSION.lock().unwrap();
+    let session = session.as_ref().ok_or("No Python program is being debugged")?;
+    let lines = blocks::block_lines(&session.code, &blocks);
+    runtime::send_harness(&session.conn, serde_json::json!({ "cmd": "breakpoints", "lines": lines }))
+}
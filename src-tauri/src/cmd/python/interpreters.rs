@@ -0,0 +1,160 @@
+// interpreters.rs - Python installations found on this machine
+//
+// Looks on PATH, in pyenv's versions, in conda's base and named environments
+// and, on Windows, asks the `py` launcher. Each candidate is run once for its
+// version, so stubs that are not a working Python (like the Microsoft Store
+// alias) drop out. Entries that resolve to the same file are listed once.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PythonInterpreter {
+    pub path: String,
+    /// e.g. `3.12.4`
+    pub version: String,
+    /// `path`, `pyenv`, `conda` or `py`
+    pub source: String,
+}
+
+const VERSION_SCRIPT: &str = "import sys; print('%d.%d.%d' % sys.version_info[:3])";
+
+/// Runs `path` to ask for its version; `None` if it is not a working Python.
+pub fn python_version(path: &Path) -> Option<String> {
+    let output = Command::new(path)
+        .args(["-c", VERSION_SCRIPT])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !version.is_empty()).then_some(version)
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" }).map(PathBuf::from)
+}
+
+/// The interpreter at the root of an installation or environment.
+fn install_python(root: &Path) -> PathBuf {
+    if cfg!(windows) {
+        root.join("python.exe")
+    } else {
+        root.join("bin").join("python3")
+    }
+}
+
+fn subdirs(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut dirs: Vec<PathBuf> = entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()).collect();
+    dirs.sort();
+    dirs
+}
+
+fn path_candidates() -> Vec<PathBuf> {
+    let names: &[&str] = if cfg!(windows) { &["python.exe", "python3.exe"] } else { &["python3", "python"] };
+    let Some(path) = std::env::var_os("PATH") else {
+        return Vec::new();
+    };
+    std::env::split_paths(&path)
+        .flat_map(|dir| names.iter().map(move |name| dir.join(name)))
+        .filter(|p| p.is_file())
+        .collect()
+}
+
+fn pyenv_candidates() -> Vec<PathBuf> {
+    let root = match std::env::var_os("PYENV_ROOT") {
+        Some(root) => PathBuf::from(root),
+        None => match home_dir() {
+            // pyenv-win keeps its versions one level down
+            Some(home) if cfg!(windows) => home.join(".pyenv").join("pyenv-win"),
+            Some(home) => home.join(".pyenv"),
+            None => return Vec::new(),
+        },
+    };
+    subdirs(&root.join("versions")).iter().map(|v| install_python(v)).collect()
+}
+
+fn conda_candidates() -> Vec<PathBuf> {
+    let mut roots = Vec::new();
+    if let Some(prefix) = std::env::var_os("CONDA_PREFIX") {
+        roots.push(PathBuf::from(prefix));
+    }
+    // CONDA_EXE is <root>/bin/conda or <root>\Scripts\conda.exe
+    if let Some(root) = std::env::var_os("CONDA_EXE").and_then(|exe| PathBuf::from(exe).parent()?.parent().map(Path::to_path_buf)) {
+        roots.push(root);
+    }
+    if let Some(home) = home_dir() {
+        for name in ["miniconda3", "anaconda3", "miniforge3", "mambaforge"] {
+            roots.push(home.join(name));
+        }
+    }
+
+    let mut candidates = Vec::new();
+    for root in roots {
+        candidates.push(install_python(&root));
+        candidates.extend(subdirs(&root.join("envs")).iter().map(|env| install_python(env)));
+    }
+    candidates.into_iter().filter(|p| p.is_file()).collect()
+}
+
+/// Parses `py -0p`, whose lines look like ` -V:3.12 *        C:\Python312\python.exe`
+/// (or ` -3.9-64 ...` on older launchers).
+fn py_launcher_candidates() -> Vec<PathBuf> {
+    if !cfg!(windows) {
+        return Vec::new();
+    }
+    let Ok(output) = Command::new("py").arg("-0p").stdin(Stdio::null()).output() else {
+        return Vec::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| {
+            // The path starts at its drive letter; it may contain spaces
+            let colon = line.find(":\\")?;
+            line.get(colon.checked_sub(1)?..).map(|p| PathBuf::from(p.trim()))
+        })
+        .collect()
+}
+
+/// Every working interpreter found, PATH first.
+pub fn discover() -> Vec<PythonInterpreter> {
+    let sources: [(&str, Vec<PathBuf>); 4] = [
+        ("path", path_candidates()),
+        ("py", py_launcher_candidates()),
+        ("pyenv", pyenv_candidates()),
+        ("conda", conda_candidates()),
+    ];
+
+    let mut seen = HashSet::new();
+    let mut found = Vec::new();
+    for (source, candidates) in sources {
+        for path in candidates {
+            let key = path.canonicalize().unwrap_or_else(|_| path.clone());
+            if !seen.insert(key) {
+                continue;
+            }
+            if let Some(version) = python_version(&path) {
+                found.push(PythonInterpreter {
+                    path: path.to_string_lossy().to_string(),
+                    version,
+                    source: source.to_string(),
+                });
+            }
+        }
+    }
+    found
+}
+
+#[tauri::command]
+pub async fn list_python_interpreters() -> Result<Vec<PythonInterpreter>, String> {
+    let found = tauri::async_runtime::spawn_blocking(discover)
+        .await
+        .map_err(|e| e.to_string())?;
+    println!("[Python] 🔍 Found {} interpreters", found.len());
+    Ok(found)
+}
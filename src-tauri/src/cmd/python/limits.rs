@@ -0,0 +1,246 @@
+// limits.rs - Time and memory limits for Python programs
+//
+// CPU time and memory are enforced by the OS: rlimits on Unix (the memory
+// limit only on Linux, macOS ignores RLIMIT_AS) and a job object on Windows.
+// The wall-clock timeout is enforced while waiting for the program. Running
+// out of memory surfaces as a `MemoryError`, which the runtime reports.
+
+use std::fmt;
+use std::io;
+use std::process::{Child, Command, ExitStatus};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct ResourceLimits {
+    /// Wall-clock time
+    pub timeout_secs: Option<u64>,
+    pub cpu_secs: Option<u64>,
+    /// Address space on Linux, committed memory on Windows
+    pub memory_mb: Option<u64>,
+}
+
+/// The limit that stopped a program.
+#[derive(Debug, Clone, Copy)]
+pub enum Limit {
+    Timeout(u64),
+    Cpu(u64),
+    Memory(u64),
+}
+
+impl fmt::Display for Limit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Limit::Timeout(secs) => write!(f, "time limit of {}s exceeded", secs),
+            Limit::Cpu(secs) => write!(f, "CPU time limit of {}s exceeded", secs),
+            Limit::Memory(mb) => write!(f, "memory limit of {} MB exceeded", mb),
+        }
+    }
+}
+
+/// Huge limits saturate rather than overflow; they mean no limit in practice.
+#[cfg(any(target_os = "linux", windows))]
+fn memory_bytes(mb: u64) -> u64 {
+    mb.saturating_mul(1024 * 1024)
+}
+
+// RLIM_INFINITY is u64::MAX on Linux only, macOS's is lower
+#[cfg(unix)]
+#[allow(clippy::unnecessary_min_or_max)]
+fn rlim(value: u64) -> libc::rlim_t {
+    (value as libc::rlim_t).min(libc::RLIM_INFINITY)
+}
+
+/// Makes the program start with the rlimits in place.
+#[cfg(unix)]
+pub fn apply(cmd: &mut Command, limits: &ResourceLimits) {
+    use std::os::unix::process::CommandExt;
+
+    let limits = *limits;
+    if limits.cpu_secs.is_none() && limits.memory_mb.is_none() {
+        return;
+    }
+    // Only async-signal-safe calls between fork and exec
+    unsafe {
+        cmd.pre_exec(move || {
+            if let Some(secs) = limits.cpu_secs {
+                // SIGXCPU at the soft limit, SIGKILL a second later if it is handled
+                let limit = libc::rlimit { rlim_cur: rlim(secs), rlim_max: rlim(secs.saturating_add(1)) };
+                if libc::setrlimit(libc::RLIMIT_CPU, &limit) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            #[cfg(target_os = "linux")]
+            if let Some(mb) = limits.memory_mb {
+                let bytes = rlim(memory_bytes(mb));
+                let limit = libc::rlimit { rlim_cur: bytes, rlim_max: bytes };
+                if libc::setrlimit(libc::RLIMIT_AS, &limit) != 0 {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+            Ok(())
+        });
+    }
+}
+
+/// Job objects are assigned after the start, see `Supervisor::new`.
+#[cfg(not(unix))]
+pub fn apply(_cmd: &mut Command, _limits: &ResourceLimits) {}
+
+#[cfg(windows)]
+mod job {
+    use std::os::windows::io::AsRawHandle;
+    use std::process::Child;
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::JobObjects::*;
+    use super::ResourceLimits;
+
+    pub struct Job(HANDLE);
+
+    // The handle is only used to close the job
+    unsafe impl Send for Job {}
+
+    impl Job {
+        pub fn attach(child: &Child, limits: &ResourceLimits) -> Result<Job, String> {
+            unsafe {
+                let handle = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+                if handle.is_null() {
+                    return Err(format!("CreateJobObject failed: {}", std::io::Error::last_os_error()));
+                }
+                let job = Job(handle);
+
+                let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+                info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+                if let Some(secs) = limits.cpu_secs {
+                    // In 100ns ticks
+                    info.BasicLimitInformation.PerProcessUserTimeLimit = secs.saturating_mul(10_000_000).min(i64::MAX as u64) as i64;
+                    info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_PROCESS_TIME;
+                }
+                if let Some(mb) = limits.memory_mb {
+                    info.ProcessMemoryLimit = usize::try_from(super::memory_bytes(mb)).unwrap_or(usize::MAX);
+                    info.BasicLimitInformation.LimitFlags |= JOB_OBJECT_LIMIT_PROCESS_MEMORY;
+                }
+                let size = std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32;
+                if SetInformationJobObject(handle, JobObjectExtendedLimitInformation, &info as *const _ as *const core::ffi::c_void, size) == 0 {
+                    return Err(format!("SetInformationJobObject failed: {}", std::io::Error::last_os_error()));
+                }
+                if AssignProcessToJobObject(handle, child.as_raw_handle() as HANDLE) == 0 {
+                    return Err(format!("AssignProcessToJobObject failed: {}", std::io::Error::last_os_error()));
+                }
+                Ok(job)
+            }
+        }
+    }
+
+    impl Drop for Job {
+        fn drop(&mut self) {
+            unsafe { CloseHandle(self.0) };
+        }
+    }
+
+    /// User CPU time of an exited process, in seconds.
+    pub fn user_time_secs(child: &Child) -> Option<u64> {
+        use windows_sys::Win32::Foundation::FILETIME;
+        use windows_sys::Win32::System::Threading::GetProcessTimes;
+
+        let mut times: [FILETIME; 4] = unsafe { std::mem::zeroed() };
+        let [created, exited, kernel, user] = &mut times;
+        let ok = unsafe { GetProcessTimes(child.as_raw_handle() as HANDLE, created, exited, kernel, user) };
+        if ok == 0 {
+            return None;
+        }
+        let ticks = ((user.dwHighDateTime as u64) << 32) | user.dwLowDateTime as u64;
+        Some(ticks / 10_000_000)
+    }
+}
+
+/// Watches a started program for its limits.
+pub struct Supervisor {
+    limits: ResourceLimits,
+    started: Instant,
+    timed_out: bool,
+    memory_error: Arc<AtomicBool>,
+    #[cfg(windows)]
+    _job: Option<job::Job>,
+}
+
+impl Supervisor {
+    #[cfg_attr(not(windows), allow(unused_variables))]
+    pub fn new(child: &Child, limits: ResourceLimits) -> Self {
+        #[cfg(windows)]
+        let _job = if limits.cpu_secs.is_some() || limits.memory_mb.is_some() {
+            job::Job::attach(child, &limits)
+                .map_err(|e| println!("[Python] ⚠️ Resource limits not applied: {}", e))
+                .ok()
+        } else {
+            None
+        };
+
+        Supervisor {
+            limits,
+            started: Instant::now(),
+            timed_out: false,
+            memory_error: Arc::new(AtomicBool::new(false)),
+            #[cfg(windows)]
+            _job,
+        }
+    }
+
+    /// Set when the program dies of a `MemoryError`.
+    pub fn memory_error_flag(&self) -> Arc<AtomicBool> {
+        self.memory_error.clone()
+    }
+
+    /// Waits for the program, killing it at the timeout.
+    pub fn wait(&mut self, child: &mut Child) -> io::Result<ExitStatus> {
+        let Some(secs) = self.limits.timeout_secs else {
+            return child.wait();
+        };
+        loop {
+            match child.try_wait()? {
+                Some(status) => return Ok(status),
+                None if self.started.elapsed() >= Duration::from_secs(secs) => {
+                    self.timed_out = true;
+                    let _ = child.kill();
+                    return child.wait();
+                }
+                None => thread::sleep(Duration::from_millis(100)),
+            }
+        }
+    }
+
+    /// The limit that stopped the program, once it exited and its output was read.
+    pub fn exceeded(&self, child: &Child, status: &ExitStatus) -> Option<Limit> {
+        if self.timed_out {
+            return self.limits.timeout_secs.map(Limit::Timeout);
+        }
+        if let Some(secs) = self.limits.cpu_secs {
+            if cpu_exceeded(child, status, secs) {
+                return Some(Limit::Cpu(secs));
+            }
+        }
+        match self.limits.memory_mb {
+            Some(mb) if self.memory_error.load(Ordering::SeqCst) => Some(Limit::Memory(mb)),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(unix)]
+fn cpu_exceeded(_child: &Child, status: &ExitStatus, _secs: u64) -> bool {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal() == Some(libc::SIGXCPU)
+}
+
+#[cfg(windows)]
+fn cpu_exceeded(child: &Child, status: &ExitStatus, secs: u64) -> bool {
+    !status.success() && job::user_time_secs(child).map_or(false, |used| used >= secs)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn cpu_exceeded(_child: &Child, _status: &ExitStatus, _secs: u64) -> bool {
+    false
+}
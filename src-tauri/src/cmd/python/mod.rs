@@ -1,43 +1,81 @@
 use tauri::{Window, Emitter, Manager};
-use std::process::{Command, Stdio};
+use std::process::{ChildStdin, Command, Stdio};
 use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::Ordering;
 use std::thread;
 use std::fs::File;
+use limits::ResourceLimits;
+use runtime::RuntimeMessage;
+use sandbox::Sandbox;
+
+pub mod runtime;
+pub mod blocks;
+pub mod debugger;
+pub mod session;
+pub mod limits;
+pub mod sandbox;
+pub mod stage;
+pub mod packages;
+pub mod interpreters;
+pub mod project_env;
+pub mod traceback;
+
+lazy_static::lazy_static! {
+    /// Stdin of the running program and its pid, dropped to signal EOF
+    static ref PYTHON_STDIN: Mutex<Option<(u32, ChildStdin)>> = Mutex::new(None);
+}
 
 #[tauri::command]
-pub async fn run_python_code(window: Window, code: String, libs: Vec<String>) -> Result<(), String> {
-    // 1. Write code to temp file
-    let mut temp_dir = std::env::temp_dir();
+pub async fn run_python_code(
+    window: Window,
+    code: String,
+    libs: Vec<String>,
+    limits: Option<ResourceLimits>,
+    sandbox: Option<bool>,
+    project: Option<String>,
+) -> Result<(), String> {
+    let options = RunOptions {
+        python: project_env::resolve_python_bin(window.app_handle(), project).await?,
+        libs,
+        limits: limits.unwrap_or_default(),
+        sandbox: sandbox.unwrap_or(false),
+    };
+    start_program(&window, &code, &runtime::write_runtime()?, &[], options)
+}
+
+/// How `start_program` runs a script.
+#[derive(Default)]
+struct RunOptions {
+    /// The interpreter, see `project_env`
+    python: PathBuf,
+    libs: Vec<String>,
+    limits: ResourceLimits,
+    /// Run under the restricted profile, see `sandbox`
+    sandbox: bool,
+}
+
+/// Writes `code` to the temp script and starts it through `launcher`, one of the
+/// bootstraps in this module, streaming its output as `python-*` events.
+fn start_program(window: &Window, code: &str, launcher: &Path, envs: &[(&str, String)], options: RunOptions) -> Result<(), String> {
+    // 1. Write code to temp file (in its own directory when sandboxed)
+    let sandbox = if options.sandbox { Some(Sandbox::create()?) } else { None };
+    let mut temp_dir = sandbox.as_ref().map_or_else(std::env::temp_dir, |s| s.dir().to_path_buf());
     temp_dir.push("hanx_script.py");
     let file_path = temp_dir.to_str().ok_or("Invalid path")?.to_string();
 
     let mut file = File::create(&file_path).map_err(|e| e.to_string())?;
     file.write_all(code.as_bytes()).map_err(|e| e.to_string())?;
 
-    // 2. Determine python binary (use isolated environment)
-    let app_handle = window.app_handle();
-    // Ensure environment is ready
-    crate::cmd::sys::env_manager::python::ensure_env(app_handle).map_err(|e| e.to_string())?;
-    // Get binary path
-    let python_bin = crate::cmd::sys::env_manager::python::get_python_bin(app_handle);
-
-    // 3. Prepare environment (PYTHONPATH)
-    let mut python_path = std::env::var("PYTHONPATH").unwrap_or_default();
-    for lib in libs {
-        if !python_path.is_empty() {
-            #[cfg(windows)]
-            python_path.push(';');
-            #[cfg(not(windows))]
-            python_path.push(':');
-        }
-        python_path.push_str(&lib);
-    }
-
-    // 4. Spawn process
-    let mut child = Command::new(python_bin)
-        .arg("-u") // Unbuffered output
+    // 2. Spawn process under the bootstrap
+    let mut cmd = python_command(&options.python, options.libs, sandbox.as_ref());
+    limits::apply(&mut cmd, &options.limits);
+    let mut child = cmd
+        .arg(launcher)
         .arg(&file_path)
-        .env("PYTHONPATH", python_path)
+        .envs(envs.iter().map(|(k, v)| (k, v)))
+        .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .spawn()
@@ -45,42 +83,75 @@ pub async fn run_python_code(window: Window, code: String, libs: Vec<String>) ->
 
     let stdout = child.stdout.take().ok_or("Failed to open stdout")?;
     let stderr = child.stderr.take().ok_or("Failed to open stderr")?;
-    
-    // Register process for stop functionality
     let pid = child.id();
+    *PYTHON_STDIN.lock().unwrap() = child.stdin.take().map(|stdin| (pid, stdin));
+
+    // Register process for stop functionality
     crate::cmd::sys::execution::register_process("python", pid);
+    let mut supervisor = limits::Supervisor::new(&child, options.limits);
+    let memory_error = supervisor.memory_error_flag();
 
     let window_clone_out = window.clone();
     let window_clone_err = window.clone();
     let window_clone_exit = window.clone();
 
     // Stream stdout
-    thread::spawn(move || {
+    let stdout_reader = thread::spawn(move || {
         let reader = BufReader::new(stdout);
         for line in reader.lines() {
             if let Ok(l) = line {
-                window_clone_out.emit("python-output", l).unwrap_or(());
+                match runtime::parse_line(&l) {
+                    (output, Some(message)) => {
+                        if !output.is_empty() {
+                            window_clone_out.emit("python-output", output).unwrap_or(());
+                        }
+                        if let RuntimeMessage::MemoryError = message {
+                            memory_error.store(true, Ordering::SeqCst);
+                        }
+                        runtime::dispatch(&window_clone_out, message);
+                    }
+                    (output, None) => window_clone_out.emit("python-output", output).unwrap_or(()),
+                }
             }
         }
     });
 
-    // Stream stderr
+    // Stream stderr, reporting tracebacks as they complete
+    let code = code.to_string();
     thread::spawn(move || {
         let reader = BufReader::new(stderr);
+        let mut parser = traceback::TracebackParser::default();
         for line in reader.lines() {
             if let Ok(l) = line {
+                let error = parser.feed(&l);
                 window_clone_err.emit("python-stderr", l).unwrap_or(());
+                if let Some(mut error) = error {
+                    error.locate(&file_path, &code);
+                    window_clone_err.emit("python-error", error).unwrap_or(());
+                }
             }
         }
     });
 
     // Wait for exit
     thread::spawn(move || {
-        let status = child.wait();
+        let status = supervisor.wait(&mut child);
+        drop(sandbox);
         // Unregister process after it exits
         crate::cmd::sys::execution::unregister_process("python");
+        // A newer run may have replaced the stdin already
+        let mut stdin = PYTHON_STDIN.lock().unwrap();
+        if stdin.as_ref().is_some_and(|(owner, _)| *owner == pid) {
+            *stdin = None;
+        }
+        drop(stdin);
+        // The runtime's last messages tell whether a limit was hit
+        let _ = stdout_reader.join();
         let msg = match status {
-            Ok(s) => format!("Process exited with {}", s),
+            Ok(s) => match supervisor.exceeded(&child, &s) {
+                Some(limit) => format!("Process stopped: {}", limit),
+                None => format!("Process exited with {}", s),
+            },
             Err(e) => format!("Process error: {}", e),
         };
         window_clone_exit.emit("python-finished", msg).unwrap_or(());
@@ -89,3 +160,41 @@ pub async fn run_python_code(window: Window, code: String, libs: Vec<String>) ->
     Ok(())
 }
 
+
+/// A `python -u` command on `python_bin`, with `libs` on PYTHONPATH.
+fn python_command(python_bin: &Path, libs: Vec<String>, sandbox: Option<&Sandbox>) -> Command {
+    let mut python_path = std::env::var("PYTHONPATH").unwrap_or_default();
+    for lib in libs {
+        if !python_path.is_empty() {
+            #[cfg(windows)]
+            python_path.push(';');
+            #[cfg(not(windows))]
+            python_path.push(':');
+        }
+        python_path.push_str(&lib);
+    }
+
+    let mut cmd = match sandbox {
+        Some(sandbox) => sandbox.command(python_bin),
+        None => Command::new(python_bin),
+    };
+    cmd.arg("-u") // Unbuffered output
+        .env("PYTHONPATH", python_path);
+    cmd
+}
+
+/// Sends `data` to the running program's stdin as is (include the newline `input()` waits for).
+/// `eof` closes stdin afterwards, so further reads get EOF.
+#[tauri::command]
+pub fn write_python_stdin(data: String, eof: Option<bool>) -> Result<(), String> {
+    let mut stdin = PYTHON_STDIN.lock().unwrap();
+    let (_, pipe) = stdin.as_mut().ok_or("No running Python program is reading input")?;
+    if !data.is_empty() {
+        pipe.write_all(data.as_bytes()).and_then(|_| pipe.flush())
+            .map_err(|e| format!("Failed to write to the program: {}", e))?;
+    }
+    if eof.unwrap_or(false) {
+        *stdin = None;
+    }
+    Ok(())
+}
@@ -0,0 +1,181 @@
+// packages.rs - pip packages of a project's Python environment
+//
+// Listing and lookups return their results; installs and uninstalls stream
+// pip's output as `python-pip-output` / `python-pip-stderr` and end with a
+// `python-pip-finished` message, like Arduino operations do. Only one pip
+// process runs at a time, as they would trip over each other in the venv.
+
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Command, Stdio};
+use std::thread::{self, JoinHandle};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Window};
+use crate::cmd::sys::env_manager::python::pip_command_for;
+use super::project_env;
+
+lazy_static::lazy_static! {
+    static ref PIP_LOCK: tauri::async_runtime::Mutex<()> = tauri::async_runtime::Mutex::new(());
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PythonPackage {
+    pub name: String,
+    pub version: String,
+}
+
+/// A package as found on the index.
+#[derive(Debug, Clone, Serialize)]
+pub struct PackageInfo {
+    pub name: String,
+    pub latest: Option<String>,
+    /// Newest first
+    pub versions: Vec<String>,
+    pub installed: Option<String>,
+}
+
+/// Package specs go to pip as arguments, they must not be read as options.
+fn check_specs(packages: &[String]) -> Result<(), String> {
+    if packages.is_empty() {
+        return Err("No packages given".to_string());
+    }
+    match packages.iter().find(|p| p.trim().is_empty() || p.trim_start().starts_with('-')) {
+        Some(bad) => Err(format!("Invalid package name: {:?}", bad)),
+        None => Ok(()),
+    }
+}
+
+async fn output(mut cmd: Command) -> Result<String, String> {
+    let output = tauri::async_runtime::spawn_blocking(move || cmd.output())
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| format!("Failed to run pip: {}", e))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("pip failed: {}", stderr.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+/// pip on the interpreter of `project`, the managed environment by default.
+async fn pip_command(app_handle: &AppHandle, project: Option<String>) -> Result<Command, String> {
+    Ok(pip_command_for(app_handle, &project_env::resolve_python_bin(app_handle, project).await?))
+}
+
+fn forward(window: &Window, pipe: impl Read + Send + 'static, event: &'static str) -> JoinHandle<()> {
+    let window = window.clone();
+    thread::spawn(move || {
+        for line in BufReader::new(pipe).lines().map_while(Result::ok) {
+            window.emit(event, line).unwrap_or(());
+        }
+    })
+}
+
+/// Runs pip to completion, streaming its output, and reports the result as
+/// `python-pip-finished`.
+async fn stream(window: &Window, action: &str, mut cmd: Command) -> Result<(), String> {
+    let _guard = PIP_LOCK.lock().await;
+    let result = async {
+        let mut child = cmd
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| format!("Failed to run pip: {}", e))?;
+        let stdout = child.stdout.take().ok_or("Failed to open stdout")?;
+        let stderr = child.stderr.take().ok_or("Failed to open stderr")?;
+        let readers = [forward(window, stdout, "python-pip-output"), forward(window, stderr, "python-pip-stderr")];
+
+        let status = tauri::async_runtime::spawn_blocking(move || child.wait())
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("Process error: {}", e))?;
+        for reader in readers {
+            let _ = reader.join();
+        }
+        if status.success() {
+            Ok(())
+        } else {
+            Err(format!("pip exited with {}", status))
+        }
+    }
+    .await;
+
+    let msg = match &result {
+        Ok(()) => format!("{} successful!", action),
+        Err(e) => format!("{} failed: {}", action, e),
+    };
+    println!("[Python] 📦 {}", msg);
+    window.emit("python-pip-finished", msg).unwrap_or(());
+    result
+}
+
+#[tauri::command]
+pub async fn list_python_packages(app_handle: AppHandle, project: Option<String>) -> Result<Vec<PythonPackage>, String> {
+    let mut cmd = pip_command(&app_handle, project).await?;
+    cmd.args(["list", "--format", "json"]);
+    let stdout = output(cmd).await?;
+    serde_json::from_str(&stdout).map_err(|e| format!("Failed to parse pip output: {}", e))
+}
+
+/// Looks a package up on the configured index. PyPI has no search API, so
+/// `name` must be the exact project name.
+#[tauri::command]
+pub async fn search_python_packages(app_handle: AppHandle, name: String, project: Option<String>) -> Result<PackageInfo, String> {
+    check_specs(std::slice::from_ref(&name))?;
+    let mut cmd = pip_command(&app_handle, project).await?;
+    cmd.args(["index", "versions"]).arg(name.trim());
+    let stdout = output(cmd).await.map_err(|e| {
+        if e.contains("No matching distribution") {
+            format!("Package {} not found", name.trim())
+        } else {
+            e
+        }
+    })?;
+    Ok(parse_index_versions(name.trim(), &stdout))
+}
+
+/// Parses `pip index versions`:
+/// ```text
+/// requests (2.32.3)
+/// Available versions: 2.32.3, 2.32.2, ...
+///   INSTALLED: 2.31.0
+///   LATEST:    2.32.3
+/// ```
+fn parse_index_versions(name: &str, stdout: &str) -> PackageInfo {
+    let mut info = PackageInfo { name: name.to_string(), latest: None, versions: Vec::new(), installed: None };
+    for (i, line) in stdout.lines().enumerate() {
+        let line = line.trim();
+        if i == 0 {
+            if let Some((project, rest)) = line.split_once(" (") {
+                info.name = project.to_string();
+                info.latest = Some(rest.trim_end_matches(')').to_string());
+            }
+        } else if let Some(versions) = line.strip_prefix("Available versions:") {
+            info.versions = versions.split(',').map(|v| v.trim().to_string()).filter(|v| !v.is_empty()).collect();
+        } else if let Some(version) = line.strip_prefix("INSTALLED:") {
+            info.installed = Some(version.trim().to_string());
+        }
+    }
+    info
+}
+
+/// Installs requirement specs such as `numpy` or `requests==2.31.0`;
+/// `upgrade` also upgrades packages that are already installed.
+#[tauri::command]
+pub async fn install_python_packages(window: Window, app_handle: AppHandle, packages: Vec<String>, upgrade: Option<bool>, project: Option<String>) -> Result<(), String> {
+    check_specs(&packages)?;
+    let mut cmd = pip_command(&app_handle, project).await?;
+    cmd.arg("install");
+    if upgrade.unwrap_or(false) {
+        cmd.arg("--upgrade");
+    }
+    cmd.args(packages.iter().map(|p| p.trim()));
+    stream(&window, "Install", cmd).await
+}
+
+#[tauri::command]
+pub async fn uninstall_python_packages(window: Window, app_handle: AppHandle, packages: Vec<String>, project: Option<String>) -> Result<(), String> {
+    check_specs(&packages)?;
+    let mut cmd = pip_command(&app_handle, project).await?;
+    cmd.args(["uninstall", "--yes"]).args(packages.iter().map(|p| p.trim()));
+    stream(&window, "Uninstall", cmd).await
+}
@@ -0,0 +1,237 @@
+// project_env.rs - The Python a project runs with
+//
+// Stored as `python.json` in the project's data dir. Projects run with the
+// managed environment unless they pick an interpreter. With `venv` a project
+// gets its own virtual environment in the data dir instead, created from that
+// interpreter (the system Python by default) on first use, with the
+// requirements of the Python extensions installed.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime};
+use crate::cmd::sys::env_manager::python::{create_venv, ensure_env, get_env_dir, get_python_bin, system_python, venv_python};
+use crate::cmd::sys::extension_manager::python::install_requirements;
+use crate::cmd::sys::extensions::get_platform_extensions_dir;
+use crate::cmd::sys::project::project_data_dir;
+use super::interpreters::python_version;
+
+const PYTHON_CONFIG_FILE: &str = "python.json";
+const VENV_DIR: &str = "venv";
+const REQUIREMENTS_HASH_FILE: &str = ".requirements.sha256";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PythonProjectConfig {
+    /// Path of an interpreter, e.g. one from `list_python_interpreters`
+    pub interpreter: Option<String>,
+    /// Run in a virtual environment of the project's own
+    pub venv: bool,
+}
+
+impl PythonProjectConfig {
+    pub fn load(project_path: &str) -> Result<Self, String> {
+        let file = project_data_dir(project_path)?.join(PYTHON_CONFIG_FILE);
+        if !file.exists() {
+            return Ok(Self::default());
+        }
+        let text = fs::read_to_string(&file).map_err(|e| e.to_string())?;
+        serde_json::from_str(&text).map_err(|e| format!("Invalid {}: {}", PYTHON_CONFIG_FILE, e))
+    }
+
+    fn base_python(&self) -> PathBuf {
+        self.interpreter.as_ref().map_or_else(|| PathBuf::from(system_python()), PathBuf::from)
+    }
+}
+
+/// A virtual environment `recreate_python_env` and `repair_python_env` can work on.
+struct VirtualEnv {
+    dir: PathBuf,
+    base_python: PathBuf,
+    /// The app's environment rather than a project's
+    managed: bool,
+}
+
+impl VirtualEnv {
+    fn of<R: Runtime>(app_handle: &AppHandle<R>, project_path: Option<&str>) -> Result<Self, String> {
+        let Some(project_path) = project_path else {
+            return Ok(Self::managed(app_handle));
+        };
+        let config = PythonProjectConfig::load(project_path)?;
+        match (&config.interpreter, config.venv) {
+            (_, true) => Ok(VirtualEnv { dir: venv_dir(project_path)?, base_python: config.base_python(), managed: false }),
+            (Some(interpreter), false) => Err(format!("{} runs with {} directly, it has no virtual environment", project_path, interpreter)),
+            (None, false) => Ok(Self::managed(app_handle)),
+        }
+    }
+
+    fn managed<R: Runtime>(app_handle: &AppHandle<R>) -> Self {
+        VirtualEnv { dir: get_env_dir(app_handle), base_python: PathBuf::from(system_python()), managed: true }
+    }
+
+    fn python(&self) -> PathBuf {
+        venv_python(&self.dir)
+    }
+
+    /// Creates the environment and installs the extensions' requirements into it.
+    fn create<R: Runtime>(&self, app_handle: &AppHandle<R>) -> Result<PathBuf, String> {
+        println!("[Python] 🔧 Creating virtual environment {:?} from {:?}", self.dir, self.base_python);
+        if let Some(parent) = self.dir.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Failed to create {:?}: {}", parent, e))?;
+        }
+        create_venv(&self.base_python, &self.dir)?;
+        let python = self.python();
+        install_extension_requirements(app_handle, &python, self.managed);
+        Ok(python)
+    }
+
+    fn recreate<R: Runtime>(&self, app_handle: &AppHandle<R>) -> Result<PathBuf, String> {
+        if self.dir.exists() {
+            fs::remove_dir_all(&self.dir).map_err(|e| format!("Failed to remove {:?}: {}", self.dir, e))?;
+        }
+        self.create(app_handle)
+    }
+}
+
+fn venv_dir(project_path: &str) -> Result<PathBuf, String> {
+    Ok(project_data_dir(project_path)?.join(VENV_DIR))
+}
+
+/// Installs every Python extension's requirements with `python`. Failures are
+/// logged only, the environment stays usable for programs that don't need them.
+/// The managed environment's install markers are dropped as well, so failed
+/// installs are retried when the extensions load.
+fn install_extension_requirements<R: Runtime>(app_handle: &AppHandle<R>, python: &Path, managed: bool) {
+    let Ok(entries) = fs::read_dir(get_platform_extensions_dir(app_handle, "python")) else {
+        return;
+    };
+    for extension in entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()) {
+        if managed {
+            let _ = fs::remove_file(extension.join("lib").join(REQUIREMENTS_HASH_FILE));
+        }
+        if let Err(e) = install_requirements(app_handle, python, &extension) {
+            println!("[Python] ⚠️ Failed to install the requirements of {:?}: {}", extension, e);
+        }
+    }
+}
+
+/// The interpreter programs of `project_path` run with, the managed
+/// environment's without a project. Missing environments are created.
+pub fn python_bin<R: Runtime>(app_handle: &AppHandle<R>, project_path: Option<&str>) -> Result<PathBuf, String> {
+    let config = match project_path {
+        Some(project_path) => PythonProjectConfig::load(project_path)?,
+        None => PythonProjectConfig::default(),
+    };
+    if let (Some(project_path), true) = (project_path, config.venv) {
+        let env = VirtualEnv { dir: venv_dir(project_path)?, base_python: config.base_python(), managed: false };
+        let python = env.python();
+        return if python.exists() { Ok(python) } else { env.create(app_handle) };
+    }
+    match config.interpreter {
+        Some(interpreter) => Ok(PathBuf::from(interpreter)),
+        None => {
+            ensure_env(app_handle)?;
+            Ok(get_python_bin(app_handle))
+        }
+    }
+}
+
+/// `python_bin` for async commands. Creating an environment installs the
+/// extensions' requirements and can take minutes, so it runs on a blocking thread.
+pub async fn resolve_python_bin(app_handle: &AppHandle, project_path: Option<String>) -> Result<PathBuf, String> {
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn_blocking(move || python_bin(&app_handle, project_path.as_deref()))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub fn get_python_project_config(project_path: String) -> Result<PythonProjectConfig, String> {
+    PythonProjectConfig::load(&project_path)
+}
+
+/// Saves the project's choice. A venv made from another interpreter is
+/// replaced, and an enabled venv is created right away so errors show here.
+#[tauri::command]
+pub async fn set_python_project_config(app_handle: AppHandle, project_path: String, config: PythonProjectConfig) -> Result<(), String> {
+    let config = PythonProjectConfig {
+        interpreter: config.interpreter.map(|s| s.trim().to_string()).filter(|s| !s.is_empty()),
+        venv: config.venv,
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        if let Some(interpreter) = &config.interpreter {
+            if python_version(Path::new(interpreter)).is_none() {
+                return Err(format!("{} is not a working Python interpreter", interpreter));
+            }
+        }
+
+        let previous = PythonProjectConfig::load(&project_path)?;
+        let dir = project_data_dir(&project_path)?;
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {:?}: {}", dir, e))?;
+        let json = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
+        fs::write(dir.join(PYTHON_CONFIG_FILE), json).map_err(|e| e.to_string())?;
+        println!("[Python] ⚙️ Saved Python config for {}", project_path);
+
+        let venv = venv_dir(&project_path)?;
+        if previous.interpreter != config.interpreter && venv.exists() {
+            fs::remove_dir_all(&venv).map_err(|e| format!("Failed to remove {:?}: {}", venv, e))?;
+        }
+        if config.venv {
+            python_bin(&app_handle, Some(&project_path))?;
+        }
+        Ok(())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Deletes and recreates the project's venv, or the managed environment
+/// without a project, then reinstalls the extensions' requirements.
+#[tauri::command]
+pub async fn recreate_python_env(app_handle: AppHandle, project_path: Option<String>) -> Result<(), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        VirtualEnv::of(&app_handle, project_path.as_deref())?.recreate(&app_handle)?;
+        println!("[Python] ✅ Recreated the Python environment");
+        Ok(())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Checks the environment `recreate_python_env` would replace and fixes what
+/// is broken: a Python that no longer starts (e.g. its base interpreter was
+/// removed) means a new environment, a missing pip is reinstalled. Returns
+/// what was done.
+#[tauri::command]
+pub async fn repair_python_env(app_handle: AppHandle, project_path: Option<String>) -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let env = VirtualEnv::of(&app_handle, project_path.as_deref())?;
+        let python = env.python();
+        if python_version(&python).is_none() {
+            env.recreate(&app_handle)?;
+            return Ok("The environment was broken and has been recreated".to_string());
+        }
+
+        let pip_works = Command::new(&python)
+            .args(["-m", "pip", "--version"])
+            .stdin(Stdio::null())
+            .output()
+            .map_or(false, |o| o.status.success());
+        if !pip_works {
+            let output = Command::new(&python)
+                .args(["-m", "ensurepip", "--upgrade"])
+                .stdin(Stdio::null())
+                .output()
+                .map_err(|e| format!("Failed to run ensurepip: {}", e))?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(format!("Failed to reinstall pip: {}", stderr.trim()));
+            }
+            return Ok("pip was missing and has been reinstalled".to_string());
+        }
+        Ok("The environment is healthy".to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
@@ -0,0 +1,118 @@
+# runtime.py - Runs a user script with the IDE's hooks installed
+#
+# Started as `python -u hanx_runtime.py <script> [args...]`. Messages for the
+# IDE are single stdout lines: MARKER followed by a JSON object with a "type".
+# Anything printed before a marker on the same line is regular output.
+
+import builtins
+import json
+import os
+import reprlib
+import runpy
+import sys
+import traceback
+import types
+
+MARKER = "\x1ehanx:"
+
+# Started as a script, helpers importing hanx_runtime must get this module and
+# not a second copy with its own hooks
+sys.modules.setdefault("hanx_runtime", sys.modules[__name__])
+
+
+def send(kind, **fields):
+    fields["type"] = kind
+    sys.__stdout__.write(MARKER + json.dumps(fields) + "\n")
+    sys.__stdout__.flush()
+
+
+_input = builtins.input
+
+
+def _input_hook(prompt=""):
+    # The prompt has no newline, so it travels with the request instead of as output
+    send("input", prompt=str(prompt))
+    return _input()
+
+
+builtins.input = _input_hook
+
+
+_repr = reprlib.Repr()
+_repr.maxstring = 80
+_repr.maxother = 80
+
+
+def short_repr(value):
+    try:
+        return _repr.repr(value)
+    except Exception as e:
+        return "<repr failed: %s>" % type(e).__name__
+
+
+def variables(namespace):
+    """The user's variables in a namespace, as {"name", "type", "repr"} objects."""
+    result = []
+    for name, value in namespace.items():
+        if name.startswith("__") or isinstance(value, types.ModuleType):
+            continue
+        result.append({"name": name, "type": type(value).__name__, "repr": short_repr(value)})
+    return result
+
+
+# Frames of these files are left out of tracebacks
+HIDDEN_FILES = {__file__}
+
+
+# Where the IDE writes this file and helpers such as hanx_stage
+HELPERS_DIR = os.path.dirname(os.path.abspath(__file__))
+
+
+def set_main_dir(directory):
+    """Puts `directory` first on sys.path, keeping the helpers importable."""
+    sys.path[0] = directory
+    if HELPERS_DIR not in sys.path:
+        sys.path.append(HELPERS_DIR)
+
+
+def prepare(script):
+    """Makes the script look like it was started directly, returns its absolute path."""
+    script = os.path.abspath(script)
+    sys.argv = sys.argv[1:]
+    set_main_dir(os.path.dirname(script))
+    return script
+
+
+def report_exception(e, script):
+    """Prints the traceback of an uncaught exception without the IDE's frames."""
+    if isinstance(e, MemoryError):
+        send("memory_error")
+    tb = e.__traceback__
+    while tb is not None and tb.tb_frame.f_code.co_filename != script:
+        tb = tb.tb_next
+    report = traceback.TracebackException(type(e), e, tb)
+    report.stack = traceback.StackSummary.from_list(
+        [frame for frame in report.stack if frame.filename not in HIDDEN_FILES]
+    )
+    sys.stderr.write("".join(report.format()))
+
+
+def main():
+    sandbox = os.environ.pop("HANX_SANDBOX", None)
+    if sandbox:
+        # Imported while the bootstrap's own directory is still on sys.path
+        import hanx_sandbox
+    script = prepare(sys.argv[1])
+    if sandbox:
+        hanx_sandbox.install(sandbox)
+    try:
+        runpy.run_path(script, run_name="__main__")
+    except SystemExit:
+        raise
+    except BaseException as e:
+        report_exception(e, script)
+        sys.exit(1)
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,121 @@
+// runtime.rs - The bootstrap user scripts run under, and talking to it
+//
+// `runtime.py` is written next to the script and started instead of it. It
+// hooks into the program (e.g. `input()`) and reports to the IDE through
+// marker lines on stdout, which the stdout reader turns into events.
+
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Window};
+use super::sandbox::SandboxBlocked;
+use super::stage::{self, StageCommand};
+
+const RUNTIME_SOURCE: &str = include_str!("runtime.py");
+const RUNTIME_FILE: &str = "hanx_runtime.py";
+const MARKER: &str = "\u{1e}hanx:";
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// A variable of the program, as listed by `variables()` in `runtime.py`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Variable {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_name: String,
+    pub repr: String,
+}
+
+/// Payload of `python-input-request`, sent when the program calls `input()`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InputRequest {
+    pub prompt: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RuntimeMessage {
+    Input(InputRequest),
+    /// The program died of a `MemoryError`, see `limits`
+    MemoryError,
+    SandboxBlocked(SandboxBlocked),
+    Stage { command: StageCommand },
+    /// A message that did not parse, e.g. a stage command with a wrong field
+    #[serde(skip)]
+    Invalid(String),
+}
+
+/// Writes the bootstrap and the helper modules programs can import to the
+/// temp dir, returning the bootstrap's path.
+pub fn write_runtime() -> Result<PathBuf, String> {
+    let dir = std::env::temp_dir();
+    let path = dir.join(RUNTIME_FILE);
+    fs::write(&path, RUNTIME_SOURCE).map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+    stage::write_helper(&dir)?;
+    Ok(path)
+}
+
+/// Splits a stdout line into the program's own output and a runtime message.
+/// Output the program printed without a newline right before the message comes first.
+/// A marker followed by something unreadable gives `RuntimeMessage::Invalid`.
+pub fn parse_line(line: &str) -> (&str, Option<RuntimeMessage>) {
+    match line.find(MARKER) {
+        Some(pos) => match serde_json::from_str(&line[pos + MARKER.len()..]) {
+            Ok(message) => (&line[..pos], Some(message)),
+            Err(e) => {
+                println!("[Python] ⚠️ Invalid runtime message: {}", e);
+                (&line[..pos], Some(RuntimeMessage::Invalid(format!("Invalid runtime message: {}", e))))
+            }
+        },
+        None => (line, None),
+    }
+}
+
+/// Emits the event for a runtime message.
+pub fn dispatch(window: &Window, message: RuntimeMessage) {
+    match message {
+        RuntimeMessage::Input(request) => {
+            window.emit("python-input-request", request).unwrap_or(());
+        }
+        RuntimeMessage::MemoryError => {}
+        RuntimeMessage::SandboxBlocked(blocked) => {
+            println!("[Python] 🚫 Sandbox blocked {} {}", blocked.action, blocked.target);
+            window.emit("python-sandbox-blocked", blocked).unwrap_or(());
+        }
+        RuntimeMessage::Stage { command } => {
+            window.emit("python-stage", command).unwrap_or(());
+        }
+        RuntimeMessage::Invalid(error) => {
+            window.emit("python-runtime-error", error).unwrap_or(());
+        }
+    }
+}
+
+/// Waits for a harness (debugger, session) to connect back to `listener`.
+/// It may never do so if the program fails to start.
+pub fn accept_harness(listener: &TcpListener) -> Result<TcpStream, String> {
+    listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+    let start = Instant::now();
+    loop {
+        match listener.accept() {
+            Ok((conn, _)) => {
+                conn.set_nonblocking(false).map_err(|e| e.to_string())?;
+                return Ok(conn);
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock && start.elapsed() < CONNECT_TIMEOUT => {
+                thread::sleep(Duration::from_millis(50));
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => return Err("The Python harness did not start in time".to_string()),
+            Err(e) => return Err(format!("Harness connection failed: {}", e)),
+        }
+    }
+}
+
+/// Sends one JSON line to a harness.
+pub fn send_harness(mut conn: &TcpStream, message: serde_json::Value) -> Result<(), String> {
+    let line = format!("{}\n", message);
+    conn.write_all(line.as_bytes()).map_err(|e| format!("Lost the connection to Python: {}", e))
+}
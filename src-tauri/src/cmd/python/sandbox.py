@@ -0,0 +1,90 @@
+# sandbox.py - Audit hook guard for the restricted profile
+#
+# Imported by the runtime when HANX_SANDBOX names the sandbox directory. The
+# hook denies network access, starting or signalling processes and writing
+# files outside that directory, raising PermissionError and telling the IDE
+# with a "sandbox_blocked" message. Audit hooks are not a security boundary
+# on their own; the IDE adds OS-level restrictions where it can.
+
+import os
+import sys
+
+import hanx_runtime
+
+hanx_runtime.HIDDEN_FILES.add(__file__)
+
+NETWORK_EVENTS = {
+    "socket.bind",
+    "socket.connect",
+    "socket.getaddrinfo",
+    "socket.gethostbyaddr",
+    "socket.gethostbyname",
+    "socket.sendmsg",
+    "socket.sendto",
+}
+
+PROCESS_EVENTS = {
+    "os.exec",
+    "os.fork",
+    "os.forkpty",
+    "os.kill",
+    "os.killpg",
+    "os.posix_spawn",
+    "os.spawn",
+    "os.startfile",
+    "os.system",
+    "subprocess.Popen",
+}
+
+# Events whose first argument is a path that gets modified
+PATH_EVENTS = {
+    "os.chmod",
+    "os.chown",
+    "os.link",
+    "os.mkdir",
+    "os.remove",
+    "os.rename",
+    "os.rmdir",
+    "os.symlink",
+    "os.truncate",
+    "os.utime",
+    "shutil.rmtree",
+}
+
+# Paths checked for each of PATH_EVENTS: both ends of a rename or hard link,
+# only the link itself for a symlink (its target is resolved when opened)
+PATH_ARGS = {"os.link": (0, 1), "os.rename": (0, 1), "os.symlink": (1,)}
+
+WRITE_FLAGS = os.O_WRONLY | os.O_RDWR | os.O_APPEND | os.O_CREAT | os.O_TRUNC
+
+
+def install(root):
+    root = os.path.realpath(root)
+
+    def inside(path):
+        if isinstance(path, int):
+            # Already open descriptor
+            return True
+        path = os.path.realpath(os.fsdecode(path))
+        return path == root or path.startswith(root + os.sep)
+
+    def block(action, target):
+        hanx_runtime.send("sandbox_blocked", action=action, target=str(target))
+        raise PermissionError("Blocked by the sandbox: %s %s" % (action, target))
+
+    def hook(event, args):
+        if event == "open":
+            path, mode, flags = args
+            writing = (mode is not None and any(c in mode for c in "wax+")) or (flags or 0) & WRITE_FLAGS
+            if path is not None and writing and not inside(path):
+                block("write", path)
+        elif event in NETWORK_EVENTS:
+            block("network", event)
+        elif event in PROCESS_EVENTS:
+            block("process", event)
+        elif event in PATH_EVENTS:
+            for target in (args[i] for i in PATH_ARGS.get(event, (0,))):
+                if target is not None and not inside(target):
+                    block("write", target)
+
+    sys.addaudithook(hook)
@@ -0,0 +1,107 @@
+// sandbox.rs - The restricted profile for Python programs
+//
+// A sandboxed run gets a fresh working directory holding the script, removed
+// when the run ends. `sandbox.py` guards the program from inside with an
+// audit hook; on top of that Linux takes its network away (user and network
+// namespaces, where the kernel allows them) and macOS runs it under
+// `sandbox-exec`. Windows relies on the audit hook alone.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicU64, Ordering};
+use serde::{Deserialize, Serialize};
+
+const GUARD_SOURCE: &str = include_str!("sandbox.py");
+const GUARD_FILE: &str = "hanx_sandbox.py";
+
+static NEXT_SANDBOX: AtomicU64 = AtomicU64::new(1);
+
+/// Payload of `python-sandbox-blocked`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SandboxBlocked {
+    /// `write`, `network` or `process`
+    pub action: String,
+    /// The path, or the audit event for network and process actions
+    pub target: String,
+}
+
+pub struct Sandbox {
+    dir: PathBuf,
+}
+
+impl Sandbox {
+    /// Creates an empty working directory and writes the guard next to the runtime.
+    pub fn create() -> Result<Sandbox, String> {
+        let temp_dir = std::env::temp_dir();
+        let guard = temp_dir.join(GUARD_FILE);
+        fs::write(&guard, GUARD_SOURCE).map_err(|e| format!("Failed to write {:?}: {}", guard, e))?;
+
+        let id = NEXT_SANDBOX.fetch_add(1, Ordering::SeqCst);
+        let dir = temp_dir.join("hanx_sandbox").join(format!("{}-{}", std::process::id(), id));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create the sandbox directory: {}", e))?;
+        // The guard compares resolved paths
+        let dir = dir.canonicalize().map_err(|e| e.to_string())?;
+        Ok(Sandbox { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// A command running `python_bin` in the sandbox.
+    pub fn command(&self, python_bin: &Path) -> Command {
+        let mut cmd = self.os_command(python_bin);
+        cmd.current_dir(&self.dir)
+            .env("HANX_SANDBOX", &self.dir)
+            .env("PYTHONDONTWRITEBYTECODE", "1")
+            .env("TMPDIR", &self.dir)
+            .env("TEMP", &self.dir)
+            .env("TMP", &self.dir);
+        cmd
+    }
+
+    #[cfg(target_os = "linux")]
+    fn os_command(&self, python_bin: &Path) -> Command {
+        use std::os::unix::process::CommandExt;
+
+        let mut cmd = Command::new(python_bin);
+        // Best effort: without unprivileged user namespaces only the audit hook applies
+        unsafe {
+            cmd.pre_exec(|| {
+                libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET);
+                Ok(())
+            });
+        }
+        cmd
+    }
+
+    #[cfg(target_os = "macos")]
+    fn os_command(&self, python_bin: &Path) -> Command {
+        const SANDBOX_EXEC: &str = "/usr/bin/sandbox-exec";
+        if !Path::new(SANDBOX_EXEC).exists() {
+            return Command::new(python_bin);
+        }
+        let dir = self.dir.to_string_lossy().replace('\\', "\\\\").replace('"', "\\\"");
+        let profile = format!(
+            "(version 1)(allow default)(deny network*)(deny file-write*)\
+             (allow file-write* (subpath \"{}\") (literal \"/dev/null\") (literal \"/dev/tty\"))",
+            dir
+        );
+        let mut cmd = Command::new(SANDBOX_EXEC);
+        cmd.arg("-p").arg(profile).arg(python_bin);
+        cmd
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    fn os_command(&self, python_bin: &Path) -> Command {
+        Command::new(python_bin)
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
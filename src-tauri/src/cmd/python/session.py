@@ -0,0 +1,69 @@
+# session.py - Long-lived interpreter that runs snippets into one namespace
+#
+# Started as `python -u hanx_session.py` in the project directory, with
+# HANX_SESSION_PORT set. Requests come over a localhost socket as JSON lines
+# and are answered in order:
+#   {"cmd": "exec", "code", "filename"} -> {"type": "done", "ok", "error"}
+#   {"cmd": "variables"}                -> {"type": "variables", "variables": [...]}
+# Snippets print and read input() through stdio, like scripts do. A trailing
+# expression is echoed like in the interactive shell.
+
+import ast
+import builtins
+import json
+import linecache
+import os
+import socket
+import sys
+import traceback
+
+import hanx_runtime
+
+hanx_runtime.HIDDEN_FILES.add(__file__)
+
+
+def run_snippet(code, namespace, filename):
+    # Keep the source around for tracebacks
+    linecache.cache[filename] = (len(code), None, code.splitlines(True), filename)
+    tree = ast.parse(code, filename)
+    last = None
+    if tree.body and isinstance(tree.body[-1], ast.Expr):
+        last = ast.Expression(tree.body.pop().value)
+    exec(compile(tree, filename, "exec"), namespace)
+    if last is not None:
+        sys.displayhook(eval(compile(last, filename, "eval"), namespace))
+
+
+def main():
+    sys.argv = [""]
+    hanx_runtime.set_main_dir(os.getcwd())
+    conn = socket.create_connection(("127.0.0.1", int(os.environ["HANX_SESSION_PORT"])))
+    reader = conn.makefile("r", encoding="utf-8")
+
+    def reply(kind, **fields):
+        fields["type"] = kind
+        conn.sendall((json.dumps(fields) + "\n").encode("utf-8"))
+
+    namespace = {"__name__": "__main__", "__builtins__": builtins}
+    count = 0
+    for raw in reader:
+        request = json.loads(raw)
+        cmd = request.get("cmd")
+        if cmd == "exec":
+            count += 1
+            filename = request.get("filename") or "<snippet %d>" % count
+            try:
+                run_snippet(request.get("code", ""), namespace, filename)
+                reply("done", ok=True, error=None)
+            except SystemExit:
+                raise
+            except BaseException as e:
+                hanx_runtime.report_exception(e, filename)
+                error = traceback.format_exception_only(type(e), e)[-1].strip()
+                reply("done", ok=False, error=error)
+        elif cmd == "variables":
+            reply("variables", variables=hanx_runtime.variables(namespace))
+
+
+if __name__ == "__main__":
+    main()
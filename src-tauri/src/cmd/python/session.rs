@@ -0,0 +1,297 @@
+// session.rs - Long-lived Python sessions, one per project
+//
+// A session is an interpreter running `session.py` that keeps its globals
+// between snippets, for running blocks one at a time. Requests go over a
+// localhost socket; output and input use the stdio pipes like programs do and
+// are emitted as `python-session-*` events tagged with the project.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, ChildStdin, Stdio};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager, Window};
+use super::runtime::{self, RuntimeMessage, Variable};
+use super::traceback::{PythonError, TracebackParser};
+use super::stage::StageCommand;
+
+const SESSION_SOURCE: &str = include_str!("session.py");
+const SESSION_FILE: &str = "hanx_session.py";
+
+static NEXT_SESSION: AtomicU64 = AtomicU64::new(1);
+
+lazy_static::lazy_static! {
+    /// Running sessions by project path
+    static ref SESSIONS: Mutex<HashMap<String, Session>> = Mutex::new(HashMap::new());
+}
+
+struct Session {
+    id: u64,
+    child: Child,
+    stdin: ChildStdin,
+    conn: TcpStream,
+    /// Replies arrive in request order, holding the lock pairs them up
+    replies: Arc<Mutex<Receiver<SessionReply>>>,
+    /// The code of every snippet run, `<snippet 1>` first, for locating errors
+    snippets: Arc<Mutex<Vec<String>>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum SessionReply {
+    Done { ok: bool, error: Option<String> },
+    Variables { variables: Vec<Variable> },
+}
+
+/// Payload of `python-session-output` and `python-session-stderr`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionOutput {
+    pub project: String,
+    pub text: String,
+}
+
+/// Payload of `python-session-input-request`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionInputRequest {
+    pub project: String,
+    pub prompt: String,
+}
+
+/// Payload of `python-session-stage`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionStage {
+    pub project: String,
+    pub command: StageCommand,
+}
+
+/// Payload of `python-session-error`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionError {
+    pub project: String,
+    pub error: PythonError,
+}
+
+fn write_session() -> Result<PathBuf, String> {
+    let path = std::env::temp_dir().join(SESSION_FILE);
+    fs::write(&path, SESSION_SOURCE).map_err(|e| format!("Failed to write {:?}: {}", path, e))?;
+    Ok(path)
+}
+
+fn stream_output(window: Window, project: String, stdout: impl std::io::Read) {
+    for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+        let (output, message) = runtime::parse_line(&line);
+        if !output.is_empty() || message.is_none() {
+            let payload = SessionOutput { project: project.clone(), text: output.to_string() };
+            window.emit("python-session-output", payload).unwrap_or(());
+        }
+        match message {
+            Some(RuntimeMessage::Input(request)) => {
+                let payload = SessionInputRequest { project: project.clone(), prompt: request.prompt };
+                window.emit("python-session-input-request", payload).unwrap_or(());
+            }
+            Some(RuntimeMessage::Stage { command }) => {
+                let payload = SessionStage { project: project.clone(), command };
+                window.emit("python-session-stage", payload).unwrap_or(());
+            }
+            Some(RuntimeMessage::Invalid(error)) => {
+                let payload = SessionOutput { project: project.clone(), text: error };
+                window.emit("python-session-runtime-error", payload).unwrap_or(());
+            }
+            _ => {}
+        }
+    }
+}
+
+fn stream_stderr(window: Window, project: String, snippets: Arc<Mutex<Vec<String>>>, stderr: impl std::io::Read) {
+    let mut parser = TracebackParser::default();
+    for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+        let error = parser.feed(&line);
+        let payload = SessionOutput { project: project.clone(), text: line };
+        window.emit("python-session-stderr", payload).unwrap_or(());
+        if let Some(mut error) = error {
+            let snippets = snippets.lock().unwrap();
+            let snippet = error.frames.iter().rev().find_map(|frame| {
+                let n: usize = frame.file.strip_prefix("<snippet ")?.strip_suffix('>')?.parse().ok()?;
+                Some((frame.file.clone(), snippets.get(n.checked_sub(1)?)?))
+            });
+            if let Some((file, code)) = snippet {
+                error.locate(&file, code);
+            }
+            window.emit("python-session-error", SessionError { project: project.clone(), error }).unwrap_or(());
+        }
+    }
+}
+
+/// Forwards replies until the interpreter goes away, then cleans up its session.
+fn listen(window: Window, project: String, session_id: u64, conn: TcpStream, replies: Sender<SessionReply>) {
+    for line in BufReader::new(conn).lines().map_while(Result::ok) {
+        match serde_json::from_str::<SessionReply>(&line) {
+            Ok(reply) => replies.send(reply).unwrap_or(()),
+            Err(e) => println!("[Python] ⚠️ Invalid session message: {}", e),
+        }
+    }
+    drop(replies);
+
+    let session = {
+        let mut sessions = SESSIONS.lock().unwrap();
+        match sessions.get(&project) {
+            Some(s) if s.id == session_id => sessions.remove(&project),
+            _ => None,
+        }
+    };
+    if let Some(mut session) = session {
+        let _ = session.child.kill();
+        let _ = session.child.wait();
+    }
+    println!("[Python] 🛑 Session ended: {}", project);
+    window.emit("python-session-ended", &project).unwrap_or(());
+}
+
+async fn request(project: &str, message: serde_json::Value) -> Result<SessionReply, String> {
+    let (conn, replies) = {
+        let sessions = SESSIONS.lock().unwrap();
+        let session = sessions.get(project).ok_or_else(|| format!("No Python session for {}", project))?;
+        (session.conn.try_clone().map_err(|e| e.to_string())?, session.replies.clone())
+    };
+    tauri::async_runtime::spawn_blocking(move || {
+        let replies = replies.lock().unwrap();
+        runtime::send_harness(&conn, message)?;
+        replies.recv().map_err(|_| "The Python session ended".to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Starts the session of `project`, in that directory, unless it is already running.
+#[tauri::command]
+pub async fn start_python_session(window: Window, project: String, libs: Option<Vec<String>>) -> Result<(), String> {
+    if SESSIONS.lock().unwrap().contains_key(&project) {
+        return Ok(());
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0").map_err(|e| format!("Failed to open the session port: {}", e))?;
+    let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+
+    runtime::write_runtime()?;
+    let launcher = write_session()?;
+    let python = super::project_env::resolve_python_bin(window.app_handle(), Some(project.clone())).await?;
+    let mut cmd = super::python_command(&python, libs.unwrap_or_default(), None);
+    if Path::new(&project).is_dir() {
+        cmd.current_dir(&project);
+    } else {
+        cmd.current_dir(std::env::temp_dir());
+    }
+    let mut child = cmd
+        .arg(&launcher)
+        .env("HANX_SESSION_PORT", port.to_string())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Failed to start python: {}", e))?;
+
+    let stdout = child.stdout.take().ok_or("Failed to open stdout")?;
+    let stderr = child.stderr.take().ok_or("Failed to open stderr")?;
+    let stdin = child.stdin.take().ok_or("Failed to open stdin")?;
+
+    let accepted = tauri::async_runtime::spawn_blocking(move || runtime::accept_harness(&listener))
+        .await
+        .map_err(|e| e.to_string())
+        .and_then(|r| r);
+    let conn = match accepted {
+        Ok(conn) => conn,
+        Err(e) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(e);
+        }
+    };
+    let reader = conn.try_clone().map_err(|e| e.to_string())?;
+    let (tx, rx) = mpsc::channel();
+
+    let id = NEXT_SESSION.fetch_add(1, Ordering::SeqCst);
+    let snippets = Arc::new(Mutex::new(Vec::new()));
+    let mut session = Session { id, child, stdin, conn, replies: Arc::new(Mutex::new(rx)), snippets: snippets.clone() };
+    {
+        let mut sessions = SESSIONS.lock().unwrap();
+        if sessions.contains_key(&project) {
+            // Another start of the same project got there first, keep its session
+            drop(sessions);
+            let _ = session.child.kill();
+            let _ = session.child.wait();
+            return Ok(());
+        }
+        sessions.insert(project.clone(), session);
+    }
+
+    let (w, p) = (window.clone(), project.clone());
+    thread::spawn(move || stream_output(w, p, stdout));
+    let (w, p) = (window.clone(), project.clone());
+    thread::spawn(move || stream_stderr(w, p, snippets, stderr));
+    println!("[Python] 🐍 Session started: {}", project);
+    thread::spawn(move || listen(window, project, id, reader, tx));
+    Ok(())
+}
+
+/// Runs `code` in the session, keeping what it defines. Fails with the last
+/// line of the traceback if it raises; the full traceback goes to stderr and
+/// is reported as `python-session-error`.
+#[tauri::command]
+pub async fn run_python_session(project: String, code: String) -> Result<(), String> {
+    let filename = {
+        let sessions = SESSIONS.lock().unwrap();
+        let session = sessions.get(&project).ok_or_else(|| format!("No Python session for {}", project))?;
+        let mut snippets = session.snippets.lock().unwrap();
+        snippets.push(code.clone());
+        format!("<snippet {}>", snippets.len())
+    };
+    match request(&project, serde_json::json!({ "cmd": "exec", "code": code, "filename": filename })).await? {
+        SessionReply::Done { ok: true, .. } => Ok(()),
+        SessionReply::Done { error, .. } => Err(error.unwrap_or_else(|| "Python error".to_string())),
+        reply => Err(format!("Unexpected session reply: {:?}", reply)),
+    }
+}
+
+/// The session's global variables, with their types and short reprs.
+#[tauri::command]
+pub async fn get_python_session_variables(project: String) -> Result<Vec<Variable>, String> {
+    match request(&project, serde_json::json!({ "cmd": "variables" })).await? {
+        SessionReply::Variables { variables } => Ok(variables),
+        reply => Err(format!("Unexpected session reply: {:?}", reply)),
+    }
+}
+
+/// Answers an `input()` of the session, `data` is written as is.
+#[tauri::command]
+pub fn write_python_session_stdin(project: String, data: String) -> Result<(), String> {
+    let mut sessions = SESSIONS.lock().unwrap();
+    let session = sessions.get_mut(&project).ok_or_else(|| format!("No Python session for {}", project))?;
+    session.stdin.write_all(data.as_bytes()).and_then(|_| session.stdin.flush())
+        .map_err(|e| format!("Failed to write to the session: {}", e))
+}
+
+/// Ends the session of `project`, dropping its variables.
+#[tauri::command]
+pub fn stop_python_session(project: String) -> Result<(), String> {
+    let session = SESSIONS.lock().unwrap().remove(&project);
+    if let Some(mut session) = session {
+        session.child.kill().map_err(|e| format!("Failed to stop the session: {}", e))?;
+        let _ = session.child.wait();
+    }
+    Ok(())
+}
+
+/// Ends every session, when the app exits.
+pub fn stop_all() {
+    let sessions: Vec<Session> = SESSIONS.lock().unwrap().drain().map(|(_, s)| s).collect();
+    for mut session in sessions {
+        let _ = session.child.kill();
+        let _ = session.child.wait();
+    }
+}
@@ -0,0 +1,102 @@
+# stage.py - Drawing on the IDE's stage instead of a native window
+#
+# Programs run from the IDE can `import hanx_stage as stage`. Every call
+# becomes a "stage" runtime message, relayed by the IDE as a `python-stage`
+# event. Coordinates are Scratch's: a 480x360 stage by default, the origin
+# in the middle and y pointing up. Images are PNG, given as bytes or a path.
+# Arguments are checked here, so a wrong one raises at the caller's line.
+
+import base64
+import io
+import numbers
+
+import hanx_runtime
+
+hanx_runtime.HIDDEN_FILES.add(__file__)
+
+
+def _send(kind, **fields):
+    fields["type"] = kind
+    hanx_runtime.send("stage", command=fields)
+
+
+def _number(name, value):
+    if isinstance(value, bool) or not isinstance(value, numbers.Real):
+        raise TypeError("%s must be a number, not %s" % (name, type(value).__name__))
+    return float(value)
+
+
+def _color(name, value, optional=False):
+    """Colors are CSS colors such as "red" or "#ff0000"."""
+    if value is None and optional:
+        return None
+    if not isinstance(value, str):
+        raise TypeError("%s must be a color name or \"#rrggbb\", not %s" % (name, type(value).__name__))
+    return value
+
+
+def _png(data):
+    if isinstance(data, (bytes, bytearray)):
+        raw = bytes(data)
+    else:
+        with open(data, "rb") as f:
+            raw = f.read()
+    return base64.b64encode(raw).decode("ascii")
+
+
+def size(width, height):
+    width, height = int(_number("width", width)), int(_number("height", height))
+    if width < 1 or height < 1:
+        raise ValueError("the stage must be at least 1x1, not %dx%d" % (width, height))
+    _send("size", width=width, height=height)
+
+
+def clear(color=None):
+    _send("clear", color=_color("color", color, optional=True))
+
+
+def line(x1, y1, x2, y2, color="black", width=1):
+    _send("line", x1=_number("x1", x1), y1=_number("y1", y1), x2=_number("x2", x2), y2=_number("y2", y2),
+          color=_color("color", color), width=_number("width", width))
+
+
+def rect(x, y, width, height, color="black", fill=None):
+    _send("rect", x=_number("x", x), y=_number("y", y), width=_number("width", width), height=_number("height", height),
+          color=_color("color", color), fill=_color("fill", fill, optional=True))
+
+
+def circle(x, y, radius, color="black", fill=None):
+    _send("circle", x=_number("x", x), y=_number("y", y), radius=_number("radius", radius),
+          color=_color("color", color), fill=_color("fill", fill, optional=True))
+
+
+def text(x, y, text, color="black", size=16):
+    _send("text", x=_number("x", x), y=_number("y", y), text=str(text), color=_color("color", color), size=_number("size", size))
+
+
+def image(data, x=0, y=0, name=None):
+    """Draws a PNG centred on (x, y); a named image replaces the previous one of that name."""
+    name = None if name is None else str(name)
+    _send("image", name=name, x=_number("x", x), y=_number("y", y), data=_png(data))
+
+
+def costume(name, data):
+    """Registers a PNG that sprites can wear."""
+    _send("costume", name=str(name), data=_png(data))
+
+
+def sprite(name, x, y, costume=None, direction=90, size=100, visible=True):
+    """Creates or moves a sprite; direction and size (in percent) as in Scratch."""
+    costume = None if costume is None else str(costume)
+    _send("sprite", name=str(name), x=_number("x", x), y=_number("y", y), costume=costume,
+          direction=_number("direction", direction), size=_number("size", size), visible=bool(visible))
+
+
+def show(figure=None, x=0, y=0, name="figure"):
+    """Draws a matplotlib figure, the current one by default, instead of plt.show()."""
+    if figure is None:
+        import matplotlib.pyplot as plt
+        figure = plt.gcf()
+    buffer = io.BytesIO()
+    figure.savefig(buffer, format="png")
+    image(buffer.getvalue(), x, y, name)
@@ -0,0 +1,46 @@
+// stage.rs - Drawing commands from Python programs
+//
+// `stage.py` is importable as `hanx_stage` by every program the IDE runs. It
+// sends its commands as "stage" runtime messages, so they travel with the
+// program's output and work in debug runs, sessions and the sandbox alike.
+// Each is checked against `StageCommand` and relayed as a `python-stage` event.
+
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+const STAGE_SOURCE: &str = include_str!("stage.py");
+const STAGE_FILE: &str = "hanx_stage.py";
+
+/// Payload of `python-stage`. Coordinates are Scratch's (origin in the middle,
+/// y up), colors are CSS colors and images are base64 PNG.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StageCommand {
+    Size { width: u32, height: u32 },
+    Clear { color: Option<String> },
+    Line { x1: f64, y1: f64, x2: f64, y2: f64, color: String, width: f64 },
+    Rect { x: f64, y: f64, width: f64, height: f64, color: String, fill: Option<String> },
+    Circle { x: f64, y: f64, radius: f64, color: String, fill: Option<String> },
+    Text { x: f64, y: f64, text: String, color: String, size: f64 },
+    /// A named image replaces the previous one of that name
+    Image { name: Option<String>, x: f64, y: f64, data: String },
+    /// An image sprites can wear
+    Costume { name: String, data: String },
+    Sprite {
+        name: String,
+        x: f64,
+        y: f64,
+        costume: Option<String>,
+        direction: f64,
+        /// Percent
+        size: f64,
+        visible: bool,
+    },
+}
+
+/// Writes the helper module into `dir`, next to the runtime.
+pub fn write_helper(dir: &Path) -> Result<(), String> {
+    let path = dir.join(STAGE_FILE);
+    fs::write(&path, STAGE_SOURCE).map_err(|e| format!("Failed to write {:?}: {}", path, e))
+}
@@ -0,0 +1,180 @@
+// traceback.rs - Python errors as structured data, explained for beginners
+//
+// The stderr readers pass every line through a `TracebackParser`, which picks
+// tracebacks out of the stream, including the headerless ones of syntax
+// errors, and turns each into a `PythonError`. The error gets its
+// exception, the frames and, for common exceptions, an explanation in plain
+// words. `locate` maps the failing line of the user's code to its block
+// through the generator's markers, see `blocks`.
+
+use serde::Serialize;
+use super::blocks;
+
+const TRACEBACK_HEADER: &str = "Traceback (most recent call last):";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TracebackFrame {
+    pub file: String,
+    pub line: usize,
+    /// Missing for syntax errors, which are not raised inside a function
+    pub function: Option<String>,
+    /// The source line, when Python could show it
+    pub code: Option<String>,
+}
+
+/// Payload of `python-error`.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PythonError {
+    /// e.g. `NameError`
+    pub exception: String,
+    pub message: String,
+    /// Outermost first, as in the traceback
+    pub frames: Vec<TracebackFrame>,
+    /// For common exceptions, what went wrong in plain words
+    pub explanation: Option<String>,
+    /// The failing line of the user's code, 1-based
+    pub line: Option<usize>,
+    pub block_id: Option<String>,
+}
+
+impl PythonError {
+    /// Points the error at the innermost frame in `file`, whose source is
+    /// `code`, and at that line's block. Returns whether such a frame exists.
+    pub fn locate(&mut self, file: &str, code: &str) -> bool {
+        let Some(frame) = self.frames.iter().rev().find(|f| f.file == file) else {
+            return false;
+        };
+        self.line = Some(frame.line);
+        self.block_id = blocks::block_at(code, frame.line);
+        true
+    }
+}
+
+/// Collects traceback lines until the exception line ends them.
+#[derive(Default)]
+pub struct TracebackParser {
+    current: Option<PythonError>,
+}
+
+impl TracebackParser {
+    /// Feeds one stderr line, returning the error it completes.
+    pub fn feed(&mut self, line: &str) -> Option<PythonError> {
+        if line == TRACEBACK_HEADER {
+            self.current = Some(PythonError::default());
+            return None;
+        }
+        let frame = parse_frame(line);
+        let Some(error) = self.current.as_mut() else {
+            // A syntax error in the script itself comes without a header
+            if let Some(frame) = frame {
+                self.current = Some(PythonError { frames: vec![frame], ..Default::default() });
+            }
+            return None;
+        };
+        if let Some(frame) = frame {
+            error.frames.push(frame);
+            return None;
+        }
+        if line.is_empty() || line.starts_with(' ') {
+            // The source line of the last frame, then markers like `^^^^`
+            let code = line.trim();
+            if let Some(frame) = error.frames.last_mut() {
+                if frame.code.is_none() && !code.is_empty() && !code.chars().all(|c| matches!(c, '^' | '~' | ' ')) {
+                    frame.code = Some(code.to_string());
+                }
+            }
+            return None;
+        }
+
+        // The first unindented line names the exception
+        let mut error = self.current.take()?;
+        let (exception, message) = match line.split_once(": ") {
+            Some((exception, message)) if is_exception_name(exception) => (exception, message),
+            _ if is_exception_name(line) => (line, ""),
+            _ => return None,
+        };
+        error.exception = exception.to_string();
+        error.message = message.to_string();
+        error.explanation = explain(&error.exception, &error.message);
+        Some(error)
+    }
+}
+
+/// Parses `  File "<path>", line <n>, in <function>`, the function being
+/// left out for syntax errors.
+fn parse_frame(line: &str) -> Option<TracebackFrame> {
+    let rest = line.strip_prefix("  File \"")?;
+    let (file, rest) = rest.split_once("\", line ")?;
+    let (number, function) = match rest.split_once(", in ") {
+        Some((number, function)) => (number, Some(function.to_string())),
+        None => (rest, None),
+    };
+    Some(TracebackFrame {
+        file: file.to_string(),
+        line: number.trim().parse().ok()?,
+        function,
+        code: None,
+    })
+}
+
+/// `ValueError` or `json.decoder.JSONDecodeError`.
+fn is_exception_name(name: &str) -> bool {
+    name.split('.').all(|part| {
+        part.chars().next().map_or(false, |c| c.is_alphabetic() || c == '_')
+            && part.chars().all(|c| c.is_alphanumeric() || c == '_')
+    })
+}
+
+/// The `n`th 'quoted' part of an exception message.
+fn quoted(message: &str, n: usize) -> Option<&str> {
+    message.split('\'').skip(1).step_by(2).nth(n)
+}
+
+/// What a common exception means, for someone new to programming.
+fn explain(exception: &str, message: &str) -> Option<String> {
+    let name = exception.rsplit('.').next().unwrap_or(exception);
+    let first = quoted(message, 0).unwrap_or("");
+    let text = match name {
+        "NameError" => format!("找不到名字 “{}”：这个变量或函数还没有定义。检查名字是否拼写正确（区分大小写），以及是否在使用它之前先给它赋了值。", first),
+        "UnboundLocalError" => format!("在函数里给变量 “{}” 赋值之前就使用了它。如果想修改函数外面的变量，需要先在函数里写 global {}。", first, first),
+        "TypeError" if message.contains("can only concatenate str") || message.contains("must be str, not") => {
+            "文字和数字不能直接相加。可以用 str() 把数字变成文字，或用 int() / float() 把文字变成数字。".to_string()
+        }
+        "TypeError" if message.contains("unsupported operand type") => {
+            "这两种类型的数据不能进行这个运算，例如把文字和数字相减。先检查参与运算的值是什么类型。".to_string()
+        }
+        "TypeError" if message.contains("object is not callable") => {
+            format!("“{}” 类型的值不是函数，不能在后面加括号调用。可能是变量名和函数名重复了。", first)
+        }
+        "TypeError" if message.contains("positional argument") => {
+            "调用函数时给的参数个数不对，检查函数需要几个参数。".to_string()
+        }
+        "TypeError" => "数据的类型不对，例如把文字当成数字使用。检查参与运算或传给函数的值是什么类型。".to_string(),
+        "ValueError" if message.starts_with("invalid literal for int()") || message.starts_with("could not convert string to float") => {
+            let value = message.rsplit_once(": ").map_or("", |(_, v)| v);
+            format!("{} 不是一个数字，不能转换成数字。检查输入的内容是否只包含数字。", value)
+        }
+        "ValueError" => "值的类型对，但内容不合适，例如让 int() 转换一段不是数字的文字。".to_string(),
+        "ZeroDivisionError" => "不能除以 0。在做除法或取余之前，先判断除数是否为 0。".to_string(),
+        "IndexError" => "下标超出了范围：列表或文字里没有这个位置。第一个元素的下标是 0，最后一个是长度减 1。".to_string(),
+        "KeyError" => format!("字典里没有键 {}。检查键的拼写，或先用 in 判断键是否存在。", message),
+        "AttributeError" => match quoted(message, 1) {
+            Some(attribute) => format!("“{}” 类型的值没有 “{}” 这个属性或方法。检查名字是否拼写正确。", first, attribute),
+            None => "这个值没有要用的属性或方法，检查名字是否拼写正确。".to_string(),
+        },
+        "IndentationError" | "TabError" => "缩进（行首的空格）不正确。同一层的代码要对齐，冒号后面的代码要多缩进一层。".to_string(),
+        "SyntaxError" => "代码的写法不符合 Python 的语法。常见原因：括号或引号没有成对、if / for / def 后面漏了冒号。".to_string(),
+        "ModuleNotFoundError" => format!("找不到模块 “{}”，它可能还没有安装。可以在包管理里安装它。", first),
+        "ImportError" => "导入失败：模块里没有要导入的名字，或模块本身出错了。".to_string(),
+        "FileNotFoundError" => "找不到这个文件或文件夹，检查路径和文件名是否正确。".to_string(),
+        "PermissionError" => "没有权限进行这个操作，例如在受限模式下写文件或联网。".to_string(),
+        "RecursionError" => "函数调用自己的次数太多了。检查递归函数有没有能够结束的条件。".to_string(),
+        "OverflowError" => "数字太大了，超出了能表示的范围。".to_string(),
+        "MemoryError" => "程序用光了内存，可能是列表太大或者循环不停地添加数据。".to_string(),
+        "EOFError" => "程序在等待输入，但输入已经结束了。".to_string(),
+        "AssertionError" => "assert 检查的条件不成立。".to_string(),
+        "KeyboardInterrupt" => "程序被手动停止了。".to_string(),
+        _ => return None,
+    };
+    Some(text)
+}
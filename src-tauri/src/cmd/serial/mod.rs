@@ -79,6 +79,9 @@ pub fn write_serial(data: String) -> Result<(), String> {
     if let Some(ref mut p) = *open_port {
         p.write_all(data.as_bytes()).map_err(|e| e.to_string())?;
         Ok(())
+    } else if crate::cmd::arduino::emulator::write_uart(data.as_bytes()) {
+        // No real port, the monitor is attached to the emulator
+        Ok(())
     } else {
         Err("Port not open".to_string())
     }
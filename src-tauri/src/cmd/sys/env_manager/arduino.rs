@@ -1,40 +1,207 @@
-use tauri::{Runtime, AppHandle};
+use tauri::{Runtime, AppHandle, Manager};
 use std::process::{Command};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use serde::Serialize;
+use crate::cmd::arduino::{daemon, toolchain};
 use super::EnvironmentImplementation;
 
 pub struct ArduinoEnvironment;
 
+const CONFIG_FILE_NAME: &str = "arduino-cli.yaml";
+
+#[derive(Debug, Serialize)]
+pub struct ArduinoCliSettings {
+    pub config_file: String,
+    pub data_dir: Option<String>,
+    pub user_dir: Option<String>,
+    pub additional_urls: Vec<String>,
+    pub proxy: Option<String>,
+}
+
 impl<R: Runtime> EnvironmentImplementation<R> for ArduinoEnvironment {
     fn platform_name(&self) -> &str {
         "arduino"
     }
 
-    fn ensure_environment(&self, _app_handle: &AppHandle<R>) -> Result<(), String> {
-        // Check if arduino-cli is available
-        let status = Command::new("arduino-cli")
-            .arg("version")
-            .status()
-            .map_err(|e| format!("Arduino CLI not found: {}", e))?;
-
-        if !status.success() {
-            return Err("Arduino CLI returned error status".to_string());
-        }
-
-        Ok(())
+    fn ensure_environment(&self, app_handle: &AppHandle<R>) -> Result<(), String> {
+        let toolchain = toolchain::current(app_handle);
+        daemon::blocking(async move {
+            let version = toolchain.version().await?;
+            println!("[Arduino] 🔧 Toolchain {}: {}", toolchain.name(), version);
+            toolchain.setup().await
+        })
     }
 
     fn get_binary_path(&self, _app_handle: &AppHandle<R>) -> PathBuf {
         PathBuf::from("arduino-cli")
     }
 
-    fn install_dependencies(&self, _app_handle: &AppHandle<R>, deps: &[String]) -> Result<(), String> {
+    fn install_dependencies(&self, app_handle: &AppHandle<R>, deps: &[String]) -> Result<(), String> {
+        let toolchain = toolchain::current(app_handle);
         for dep in deps {
             println!("Installing arduino library: {}", dep);
-            let _ = Command::new("arduino-cli")
-                .args(&["lib", "install", dep])
-                .status();
+            let toolchain = toolchain.clone();
+            let name = dep.clone();
+            let _ = daemon::blocking(async move { toolchain.install_library(&name, None).await });
         }
         Ok(())
     }
 }
+
+// Private helper
+fn get_env_dir<R: Runtime>(app_handle: &AppHandle<R>) -> PathBuf {
+    #[cfg(target_os = "windows")]
+    {
+        // On Windows, try to use "workspace" folder next to the executable for portability
+        if let Ok(mut exe_path) = std::env::current_exe() {
+            exe_path.pop(); // Get directory
+            let workspace = exe_path.join("workspace");
+            return workspace.join("envs").join("arduino");
+        }
+    }
+
+    // Path: ~/Library/Application Support/<app-id>/workspace/envs/arduino
+    let mut path = app_handle.path().app_data_dir().unwrap_or(PathBuf::from("."));
+    path.push("workspace");
+    path.push("envs");
+    path.push("arduino");
+    path
+}
+
+pub fn get_config_file<R: Runtime>(app_handle: &AppHandle<R>) -> PathBuf {
+    get_env_dir(app_handle).join(CONFIG_FILE_NAME)
+}
+
+/// Creates an `arduino-cli` command bound to the app-owned configuration,
+/// so the user's global arduino-cli setup is never read or modified.
+pub fn cli_command<R: Runtime>(app_handle: &AppHandle<R>) -> Command {
+    command_for_config(&get_config_file(app_handle))
+}
+
+/// `cli_command` for callers that only hold the config file path.
+pub fn command_for_config(config_file: &Path) -> Command {
+    let mut cmd = Command::new("arduino-cli");
+    cmd.arg("--config-file").arg(config_file);
+    cmd
+}
+
+fn ensure_config<R: Runtime>(app_handle: &AppHandle<R>) -> Result<(), String> {
+    ensure_config_file(&get_config_file(app_handle))
+}
+
+/// Creates the app-owned config with data/user/download directories next to it.
+/// A fresh config starts with empty indexes, `update_arduino_index` and
+/// `install_arduino_core` fill them with progress reported to the window.
+pub fn ensure_config_file(config_file: &Path) -> Result<(), String> {
+    if config_file.exists() {
+        return Ok(());
+    }
+
+    let env_dir = config_file.parent().ok_or("Invalid arduino-cli config path")?;
+    for sub in ["data", "user", "downloads"] {
+        std::fs::create_dir_all(env_dir.join(sub)).map_err(|e| e.to_string())?;
+    }
+
+    println!("[Arduino] 🔧 Creating isolated arduino-cli config: {:?}", config_file);
+    let output = Command::new("arduino-cli")
+        .args(&["config", "init", "--dest-file"])
+        .arg(config_file)
+        .output()
+        .map_err(|e| format!("Failed to create arduino-cli config: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to create arduino-cli config: {}", stderr.trim()));
+    }
+
+    let dirs = [
+        ("directories.data", env_dir.join("data")),
+        ("directories.user", env_dir.join("user")),
+        ("directories.downloads", env_dir.join("downloads")),
+    ];
+    for (key, dir) in dirs {
+        let dir = dir.to_string_lossy().to_string();
+        if let Err(e) = config_set(config_file, key, &[dir]) {
+            // Don't leave a half-configured file behind, it would point at the global dirs
+            let _ = std::fs::remove_file(config_file);
+            return Err(e);
+        }
+    }
+
+    Ok(())
+}
+
+fn config_set(config_file: &Path, key: &str, values: &[String]) -> Result<(), String> {
+    let output = if values.is_empty() {
+        command_for_config(config_file).args(&["config", "delete", key]).output()
+    } else {
+        command_for_config(config_file).args(&["config", "set", key]).args(values).output()
+    }
+    .map_err(|e| format!("Failed to execute arduino-cli: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // Deleting a key that was never set is not an error for us
+        if values.is_empty() {
+            return Ok(());
+        }
+        return Err(format!("Failed to set {}: {}", key, stderr.trim()));
+    }
+
+    // The daemon only reads its config at startup
+    daemon::shutdown();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_arduino_cli_config(app_handle: tauri::AppHandle) -> Result<ArduinoCliSettings, String> {
+    ensure_config(&app_handle)?;
+
+    let output = cli_command(&app_handle)
+        .args(&["config", "dump", "--format", "json"])
+        .output()
+        .map_err(|e| format!("Failed to execute arduino-cli: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to read arduino-cli config: {}", stderr.trim()));
+    }
+
+    let dump: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Failed to parse arduino-cli output: {}", e))?;
+    // Newer arduino-cli versions wrap the settings in a "config" object
+    let config = dump.get("config").unwrap_or(&dump);
+
+    let as_string = |v: &serde_json::Value| v.as_str().filter(|s| !s.is_empty()).map(|s| s.to_string());
+
+    Ok(ArduinoCliSettings {
+        config_file: get_config_file(&app_handle).to_string_lossy().to_string(),
+        data_dir: as_string(&config["directories"]["data"]),
+        user_dir: as_string(&config["directories"]["user"]),
+        additional_urls: config["board_manager"]["additional_urls"]
+            .as_array()
+            .map(|urls| urls.iter().filter_map(as_string).collect())
+            .unwrap_or_default(),
+        proxy: as_string(&config["network"]["proxy"]),
+    })
+}
+
+#[tauri::command]
+pub fn set_arduino_board_manager_urls(app_handle: tauri::AppHandle, urls: Vec<String>) -> Result<(), String> {
+    ensure_config(&app_handle)?;
+    let urls: Vec<String> = urls.into_iter()
+        .map(|u| u.trim().to_string())
+        .filter(|u| !u.is_empty())
+        .collect();
+    config_set(&get_config_file(&app_handle), "board_manager.additional_urls", &urls)
+}
+
+#[tauri::command]
+pub fn set_arduino_proxy(app_handle: tauri::AppHandle, proxy: Option<String>) -> Result<(), String> {
+    ensure_config(&app_handle)?;
+    let values: Vec<String> = proxy.into_iter()
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect();
+    config_set(&get_config_file(&app_handle), "network.proxy", &values)
+}
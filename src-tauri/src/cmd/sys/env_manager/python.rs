@@ -1,10 +1,29 @@
 use tauri::{Runtime, AppHandle, Manager};
 use std::process::{Command, Stdio};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::cmd::sys::extensions::get_platform_extensions_dir;
 use super::EnvironmentImplementation;
 
 pub struct PythonEnvironment;
 
+const PIP_SETTINGS_FILE_NAME: &str = "pip_settings.json";
+
+/// Where pip gets packages from. Applied to every pip run through `PIP_*`
+/// environment variables, so each pip command picks the options it knows.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PipSettings {
+    /// Replaces PyPI, e.g. a mirror
+    pub index_url: Option<String>,
+    pub extra_index_urls: Vec<String>,
+    /// Hosts whose HTTPS certificate is not checked, or that are served over plain HTTP
+    pub trusted_hosts: Vec<String>,
+    pub proxy: Option<String>,
+    /// Never contact an index, install only from the wheels Python extensions ship in `lib/wheels`
+    pub offline: bool,
+}
+
 impl<R: Runtime> EnvironmentImplementation<R> for PythonEnvironment {
     fn platform_name(&self) -> &str {
         "python"
@@ -16,36 +35,11 @@ impl<R: Runtime> EnvironmentImplementation<R> for PythonEnvironment {
             return Ok(());
         }
 
-        // Determine system python
-        let system_python = if Command::new("python3").arg("--version").output().is_ok() {
-            "python3"
-        } else {
-            "python"
-        };
-
-        // Create venv
-        let status = Command::new(system_python)
-            .args(&["-m", "venv"])
-            .arg(&env_dir)
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .map_err(|e| format!("Failed to create python venv: {}", e))?;
-
-        if !status.success() {
-            return Err("Failed to create python virtual environment".to_string());
-        }
-
-        Ok(())
+        create_venv(Path::new(system_python()), &env_dir)
     }
 
     fn get_binary_path(&self, app_handle: &AppHandle<R>) -> PathBuf {
-        let env_dir = get_env_dir(app_handle);
-        if cfg!(windows) {
-            env_dir.join("Scripts").join("python.exe")
-        } else {
-            env_dir.join("bin").join("python3")
-        }
+        venv_python(&get_env_dir(app_handle))
     }
 
     fn install_dependencies(&self, app_handle: &AppHandle<R>, deps: &[String]) -> Result<(), String> {
@@ -54,21 +48,9 @@ impl<R: Runtime> EnvironmentImplementation<R> for PythonEnvironment {
         }
 
         self.ensure_environment(app_handle)?;
-        let python_bin = self.get_binary_path(app_handle);
-
-        let mut args = vec![
-            "-m".to_string(), 
-            "pip".to_string(), 
-            "install".to_string(),
-            "--trusted-host".to_string(), "pypi.org".to_string(),
-            "--trusted-host".to_string(), "files.pythonhosted.org".to_string(),
-            "--trusted-host".to_string(), "pypi.python.org".to_string(),
-            "-i https://pypi.tuna.tsinghua.edu.cn/simple".to_string()
-        ];
-        args.extend(deps.iter().cloned());
-
-        let output = Command::new(python_bin)
-            .args(&args)
+        let output = pip_command_for(app_handle, &self.get_binary_path(app_handle))
+            .arg("install")
+            .args(deps)
             .output()
             .map_err(|e| format!("Pip install failed: {}", e))?;
 
@@ -81,8 +63,8 @@ impl<R: Runtime> EnvironmentImplementation<R> for PythonEnvironment {
     }
 }
 
-// Private helper
-fn get_env_dir<R: Runtime>(app_handle: &AppHandle<R>) -> PathBuf {
+/// Where the managed environment lives.
+pub fn get_env_dir<R: Runtime>(app_handle: &AppHandle<R>) -> PathBuf {
     #[cfg(target_os = "windows")]
     {
         // On Windows, try to use "workspace" folder next to the executable for portability
@@ -110,3 +92,120 @@ pub fn ensure_env<R: Runtime>(app_handle: &AppHandle<R>) -> Result<(), String> {
 pub fn get_python_bin<R: Runtime>(app_handle: &AppHandle<R>) -> PathBuf {
     PythonEnvironment.get_binary_path(app_handle)
 }
+
+/// The Python found on PATH, which environments are created from by default.
+pub fn system_python() -> &'static str {
+    if Command::new("python3").arg("--version").output().is_ok() {
+        "python3"
+    } else {
+        "python"
+    }
+}
+
+/// The interpreter inside the virtual environment at `env_dir`.
+pub fn venv_python(env_dir: &Path) -> PathBuf {
+    if cfg!(windows) {
+        env_dir.join("Scripts").join("python.exe")
+    } else {
+        env_dir.join("bin").join("python3")
+    }
+}
+
+/// Creates a virtual environment at `env_dir` with `base_python`.
+pub fn create_venv(base_python: &Path, env_dir: &Path) -> Result<(), String> {
+    let output = Command::new(base_python)
+        .args(["-m", "venv"])
+        .arg(env_dir)
+        .stdout(Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to create python venv: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("Failed to create python virtual environment: {}", stderr.trim()));
+    }
+
+    Ok(())
+}
+
+fn pip_settings_path<R: Runtime>(app_handle: &AppHandle<R>) -> PathBuf {
+    let mut path = app_handle.path().app_data_dir().unwrap_or(PathBuf::from("."));
+    path.push(PIP_SETTINGS_FILE_NAME);
+    path
+}
+
+pub fn load_pip_settings<R: Runtime>(app_handle: &AppHandle<R>) -> PipSettings {
+    std::fs::read_to_string(pip_settings_path(app_handle))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+/// The `lib/wheels` directories of installed Python extensions.
+pub fn extension_wheel_dirs<R: Runtime>(app_handle: &AppHandle<R>) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(get_platform_extensions_dir(app_handle, "python")) else {
+        return Vec::new();
+    };
+    entries.flatten()
+        .map(|entry| entry.path().join("lib").join("wheels"))
+        .filter(|dir| dir.is_dir())
+        .collect()
+}
+
+/// `python -m pip` on the environment of `python`, with the pip settings applied.
+pub fn pip_command_for<R: Runtime>(app_handle: &AppHandle<R>, python: &Path) -> Command {
+    let settings = load_pip_settings(app_handle);
+
+    let mut cmd = Command::new(python);
+    cmd.args(["-m", "pip", "--disable-pip-version-check"]);
+    if let Some(url) = &settings.index_url {
+        cmd.env("PIP_INDEX_URL", url);
+    }
+    // pip splits list variables on whitespace
+    if !settings.extra_index_urls.is_empty() {
+        cmd.env("PIP_EXTRA_INDEX_URL", settings.extra_index_urls.join(" "));
+    }
+    if !settings.trusted_hosts.is_empty() {
+        cmd.env("PIP_TRUSTED_HOST", settings.trusted_hosts.join(" "));
+    }
+    if let Some(proxy) = &settings.proxy {
+        cmd.env("PIP_PROXY", proxy);
+    }
+    if settings.offline {
+        // As file URLs, paths may contain spaces
+        let links: Vec<String> = extension_wheel_dirs(app_handle).iter()
+            .filter_map(|dir| reqwest::Url::from_directory_path(dir).ok())
+            .map(|url| url.to_string())
+            .collect();
+        cmd.env("PIP_NO_INDEX", "1").env("PIP_FIND_LINKS", links.join(" "));
+    }
+    cmd
+}
+
+#[tauri::command]
+pub fn get_python_pip_settings(app_handle: tauri::AppHandle) -> PipSettings {
+    load_pip_settings(&app_handle)
+}
+
+#[tauri::command]
+pub fn set_python_pip_settings(app_handle: tauri::AppHandle, settings: PipSettings) -> Result<(), String> {
+    let clean = |v: Option<String>| v.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    let clean_all = |v: Vec<String>| -> Vec<String> { v.into_iter().filter_map(|s| clean(Some(s))).collect() };
+    let settings = PipSettings {
+        index_url: clean(settings.index_url),
+        extra_index_urls: clean_all(settings.extra_index_urls),
+        trusted_hosts: clean_all(settings.trusted_hosts),
+        proxy: clean(settings.proxy),
+        offline: settings.offline,
+    };
+    if let Some(bad) = settings.extra_index_urls.iter().chain(&settings.trusted_hosts).find(|s| s.contains(char::is_whitespace)) {
+        return Err(format!("Invalid entry: {:?}", bad));
+    }
+
+    let path = pip_settings_path(&app_handle);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| e.to_string())
+}
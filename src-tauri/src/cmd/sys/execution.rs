@@ -45,6 +45,8 @@ pub fn stop_execution() -> Result<(), String> {
         }
     }
     
+    crate::cmd::arduino::emulator::stop();
+
     // Clear all registered processes
     if let Ok(mut processes) = RUNNING_PROCESSES.lock() {
         processes.clear();
@@ -1,29 +1,13 @@
 use tauri::{Runtime, AppHandle, Manager};
+use std::collections::HashMap;
 use std::path::PathBuf;
-use std::process::{Command};
 use std::fs;
 use super::lifecycle::ExtensionLifecycle;
+use super::library_requirements::{collect_requirements, parse_requirement, resolve_library, LibraryRequirement, LibraryResolution};
+use crate::cmd::arduino::{daemon, toolchain};
 
 pub struct ArduinoExtensionLifecycle;
 
-use serde::Deserialize;
-
-#[derive(Deserialize, Debug)]
-struct ArduinoLib {
-    name: String,
-}
-
-#[derive(Deserialize, Debug)]
-struct ArduinoLibWrapper {
-    library: ArduinoLib,
-}
-
-#[derive(Deserialize, Debug)]
-struct ArduinoLibList {
-    #[serde(rename = "installed_libraries")]
-    libraries: Option<Vec<ArduinoLibWrapper>>,
-}
-
 impl<R: Runtime> ExtensionLifecycle<R> for ArduinoExtensionLifecycle {
     fn on_load(&self, _app_handle: &AppHandle<R>, extension_id: &str, path: &PathBuf) -> Result<(), String> {
         println!("[Arduino] 📂 Loading extension: {} (Path: {:?})", extension_id, path);
@@ -42,73 +26,64 @@ impl<R: Runtime> ExtensionLifecycle<R> for ArduinoExtensionLifecycle {
             let session = _app_handle.state::<crate::SessionState>();
             let mut cache = session.arduino_libraries_cache.lock().unwrap();
             
-            let installed_names: Vec<String> = if let Some(libs) = &*cache {
-                println!("[Arduino] ⏩ Using cached library list ({} libs)", libs.len() as usize);
-                libs.clone()
-            } else {
+            let installed = cache.get_or_insert_with(|| {
                 // Get installed libraries (including built-ins)
-                // Runs: arduino-cli lib list --all --format json
                 println!("[Arduino] 🔍 Fetching installed libraries list...");
-                let installed_libs_output = Command::new("arduino-cli")
-                    .args(&["lib", "list", "--all", "--format", "json"])
-                    .output()
-                    .map_err(|e| format!("Failed to list installed libs: {}", e))?;
-                
-                let names: Vec<String> = if installed_libs_output.status.success() {
-                     let json_output = String::from_utf8_lossy(&installed_libs_output.stdout);
-                     match serde_json::from_str::<ArduinoLibList>(&json_output) {
-                         Ok(list) => {
-                             let n: Vec<String> = list.libraries
-                                 .unwrap_or_default()
-                                 .into_iter()
-                                 .map(|l| l.library.name)
-                                 .collect();
-                             println!("[Arduino] 📚 Parsed {} libraries from index", n.len());
-                             n
-                         }
-                         Err(e) => {
-                             println!("[Arduino] ❌ Failed to parse library list JSON: {}", e);
-                             vec![]
-                         }
-                     }
-                } else {
-                     let stderr = String::from_utf8_lossy(&installed_libs_output.stderr);
-                     println!("[Arduino] ❌ arduino-cli lib list --all failed: {}", stderr.trim());
-                     vec![] // Failed to list
-                };
-                
-                *cache = Some(names.clone());
-                names
-            };
+                list_installed_libraries(_app_handle).unwrap_or_default()
+            });
+            println!("[Arduino] ⏩ Using library list ({} libs)", installed.len());
+
+            // Other extensions' requirements on the same libraries must keep holding
+            let platform_dir = path.parent().unwrap_or(path);
+            let all_requirements = collect_requirements(platform_dir);
 
             let content = fs::read_to_string(&libraries_txt).map_err(|e| e.to_string())?;
             
-            for line in content.lines() {
-                let lib_name = line.trim();
-                if !lib_name.is_empty() && !lib_name.starts_with('#') {
-                    if installed_names.contains(&lib_name.to_string()) {
-                         println!("[Arduino] ✅ Library '{}' already installed, skipping.", lib_name);
-                         continue;
+            for parsed in content.lines().filter_map(|line| parse_requirement(line, extension_id)) {
+                let requirement = match parsed {
+                    Ok(req) => req,
+                    Err(e) => {
+                        println!("[Arduino] ⚠️ {}", e);
+                        continue;
                     }
+                };
+                let mut requirements: Vec<&LibraryRequirement> = all_requirements.iter()
+                    .filter(|r| r.name == requirement.name)
+                    .collect();
+                if requirements.is_empty() {
+                    requirements.push(&requirement);
+                }
+                let lib_name = requirement.name.as_str();
 
-                    println!("[Arduino] 📦 Installing library: {}", lib_name);
-                    let output = Command::new("arduino-cli")
-                        .args(&["lib", "install", lib_name])
-                        .output();
-                    
-                    match output {
-                        Ok(out) => {
-                            if out.status.success() {
+                match resolve_library_blocking(_app_handle, lib_name, &requirements, installed.get(lib_name).map(|v| v.as_str())) {
+                    LibraryResolution::Satisfied { version, .. } => {
+                        println!("[Arduino] ✅ Library '{}' {} already installed, skipping.", lib_name, version);
+                    }
+                    LibraryResolution::Install { version, installed: previous, .. } => {
+                        let target = version.as_deref().unwrap_or("latest");
+                        match &previous {
+                            Some(old) => println!("[Arduino] 🔁 Replacing library {} {} with {}", lib_name, old, target),
+                            None => println!("[Arduino] 📦 Installing library: {}@{}", lib_name, target),
+                        }
+                        match install_library(_app_handle, lib_name, version.as_deref()) {
+                            Ok(_) => {
                                 println!("[Arduino] ✅ Library installed: {}", lib_name);
-                            } else {
-                                let stderr = String::from_utf8_lossy(&out.stderr);
-                                println!("[Arduino] ⚠️ Library install potential fail: {} - {}", lib_name, stderr.trim());
+                                // "latest" doesn't say which version that was, later requirements need to know
+                                let version = version.or_else(|| list_installed_libraries(_app_handle).ok()?.remove(lib_name));
+                                match version {
+                                    Some(version) => installed.insert(lib_name.to_string(), version),
+                                    None => installed.remove(lib_name),
+                                };
                             }
-                        }
-                        Err(e) => {
-                            println!("[Arduino] ❌ Failed to run arduino-cli: {}", e);
+                            Err(e) => println!("[Arduino] ⚠️ Library install potential fail: {} - {}", lib_name, e),
                         }
                     }
+                    LibraryResolution::Conflict { requirements, .. } => {
+                        let wanted: Vec<String> = requirements.iter()
+                            .map(|r| format!("{} needs {}", r.extension, r.spec))
+                            .collect();
+                        println!("[Arduino] ❌ Conflicting versions of library {}: {}", lib_name, wanted.join("; "));
+                    }
                 }
             }
         }
@@ -120,23 +95,9 @@ impl<R: Runtime> ExtensionLifecycle<R> for ArduinoExtensionLifecycle {
                     let p = entry.path();
                     if p.extension().map_or(false, |ext| ext == "zip") {
                         println!("[Arduino扩展] 📦 从 ZIP 安装库: {:?}", p);
-                        let output = Command::new("arduino-cli")
-                            .args(&["lib", "install", "--zip-path"])
-                            .arg(&p)
-                            .output();
-                        
-                        match output {
-                            Ok(out) => {
-                                if out.status.success() {
-                                    println!("[Arduino扩展] ✅ ZIP 库安装成功");
-                                } else {
-                                    let stderr = String::from_utf8_lossy(&out.stderr);
-                                    println!("[Arduino扩展] ⚠️ ZIP 库安装失败: {}", stderr.trim());
-                                }
-                            }
-                            Err(e) => {
-                                println!("[Arduino扩展] ❌ 执行 arduino-cli 失败: {}", e);
-                            }
+                        match install_zip_library(_app_handle, &p) {
+                            Ok(_) => println!("[Arduino扩展] ✅ ZIP 库安装成功"),
+                            Err(e) => println!("[Arduino扩展] ⚠️ ZIP 库安装失败: {}", e),
                         }
                     }
                 }
@@ -152,3 +113,50 @@ impl<R: Runtime> ExtensionLifecycle<R> for ArduinoExtensionLifecycle {
         Ok(())
     }
 }
+
+/// Returns the name and version of all installed libraries, including platform built-ins.
+pub(super) fn list_installed_libraries<R: Runtime>(app_handle: &AppHandle<R>) -> Result<HashMap<String, String>, String> {
+    let toolchain = toolchain::current(app_handle);
+    match daemon::blocking(async move { toolchain.list_libraries(true, None).await }) {
+        Ok(libs) => {
+            let n: HashMap<String, String> = libs.into_iter().map(|l| (l.name, l.version)).collect();
+            println!("[Arduino] 📚 Parsed {} installed libraries", n.len());
+            Ok(n)
+        }
+        Err(e) => {
+            println!("[Arduino] ❌ Library list failed: {}", e);
+            Ok(HashMap::new())
+        }
+    }
+}
+
+/// `resolve_library` for synchronous callers.
+fn resolve_library_blocking<R: Runtime>(
+    app_handle: &AppHandle<R>,
+    lib_name: &str,
+    requirements: &[&LibraryRequirement],
+    installed: Option<&str>,
+) -> LibraryResolution {
+    let toolchain = toolchain::current(app_handle);
+    let name = lib_name.to_string();
+    let requirements: Vec<LibraryRequirement> = requirements.iter().map(|r| (*r).clone()).collect();
+    let installed = installed.map(|v| v.to_string());
+    daemon::blocking(async move {
+        let requirements: Vec<&LibraryRequirement> = requirements.iter().collect();
+        resolve_library(toolchain.as_ref(), &name, &requirements, installed.as_deref()).await
+    })
+}
+
+/// Installs `lib_name`, at `version` if given (replacing another installed version), else the latest.
+pub(super) fn install_library<R: Runtime>(app_handle: &AppHandle<R>, lib_name: &str, version: Option<&str>) -> Result<(), String> {
+    let toolchain = toolchain::current(app_handle);
+    let name = lib_name.to_string();
+    let version = version.map(|v| v.to_string());
+    daemon::blocking(async move { toolchain.install_library(&name, version.as_deref()).await })
+}
+
+fn install_zip_library<R: Runtime>(app_handle: &AppHandle<R>, zip_path: &PathBuf) -> Result<(), String> {
+    let toolchain = toolchain::current(app_handle);
+    let path = zip_path.clone();
+    daemon::blocking(async move { toolchain.install_zip_library(&path).await })
+}
@@ -0,0 +1,293 @@
+// library_requirements.rs - Versioned Arduino library requirements of extensions
+//
+// Each line of an extension's `lib/libraries.txt` is `Name`, `Name@1.2.3`
+// (exact) or `Name@<range>` with a semver range such as `^1.2`, `~1.4.0` or
+// `>=1.0, <2.0`. The requirements of all Arduino extensions are combined per
+// library: the installed version is kept when it satisfies all of them,
+// otherwise the newest index release that does is installed (upgrading or
+// downgrading). Libraries no release can satisfy are reported as conflicts.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use semver::{Version, VersionReq};
+use serde::Serialize;
+use tauri::Manager;
+use crate::cmd::arduino::daemon;
+use crate::cmd::arduino::toolchain::{self, ArduinoToolchain};
+use crate::cmd::sys::extensions::get_platform_extensions_dir;
+use super::arduino::{install_library, list_installed_libraries};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LibraryRequirement {
+    pub extension: String,
+    pub name: String,
+    /// The range as written, `*` for a bare name
+    pub spec: String,
+    #[serde(skip)]
+    req: VersionReq,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum LibraryResolution {
+    /// The installed version satisfies every requirement
+    Satisfied { name: String, version: String },
+    /// `version` must be installed, replacing `installed` if any; `None` is the latest release
+    Install { name: String, version: Option<String>, installed: Option<String> },
+    /// No release satisfies every requirement
+    Conflict { name: String, installed: Option<String>, requirements: Vec<LibraryRequirement> },
+}
+
+/// Library versions aren't always full semver ("1.0", "2.1.0-beta"), pad the missing parts.
+pub fn parse_version(version: &str) -> Option<Version> {
+    let version = version.trim().trim_start_matches('v');
+    Version::parse(version).ok().or_else(|| {
+        let (core, rest) = version.split_at(version.find(['-', '+']).unwrap_or(version.len()));
+        let mut parts: Vec<&str> = core.split('.').collect();
+        if parts.is_empty() || parts.len() > 3 {
+            return None;
+        }
+        parts.resize(3, "0");
+        Version::parse(&format!("{}{}", parts.join("."), rest)).ok()
+    })
+}
+
+/// Parses a `libraries.txt` line; `None` for blank lines and comments.
+pub fn parse_requirement(line: &str, extension: &str) -> Option<Result<LibraryRequirement, String>> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let (name, spec) = match line.rsplit_once('@') {
+        Some((name, spec)) => (name.trim(), spec.trim()),
+        None => (line, "*"),
+    };
+    // A plain version means exactly that version, as with `arduino-cli lib install Name@1.2.3`
+    let range = match spec.chars().next() {
+        Some(c) if c.is_ascii_digit() => match parse_version(spec) {
+            Some(v) => format!("={}", v),
+            None => spec.to_string(),
+        },
+        _ => spec.to_string(),
+    };
+    Some(
+        VersionReq::parse(&range)
+            .map(|req| LibraryRequirement {
+                extension: extension.to_string(),
+                name: name.to_string(),
+                spec: spec.to_string(),
+                req,
+            })
+            .map_err(|e| format!("Invalid version range in '{}' ({}): {}", line, extension, e)),
+    )
+}
+
+/// Requirements declared by every installed Arduino extension, invalid lines are logged and skipped.
+pub fn collect_requirements(extensions_dir: &std::path::Path) -> Vec<LibraryRequirement> {
+    let mut requirements = Vec::new();
+    for entry in fs::read_dir(extensions_dir).into_iter().flatten().flatten() {
+        let id = entry.file_name().to_string_lossy().to_string();
+        let Ok(content) = fs::read_to_string(entry.path().join("lib").join("libraries.txt")) else {
+            continue;
+        };
+        for parsed in content.lines().filter_map(|line| parse_requirement(line, &id)) {
+            match parsed {
+                Ok(req) => requirements.push(req),
+                Err(e) => println!("[Arduino] ⚠️ {}", e),
+            }
+        }
+    }
+    requirements
+}
+
+fn satisfies(version: &str, requirements: &[&LibraryRequirement]) -> bool {
+    // Unparseable versions only satisfy "any version"
+    match parse_version(version) {
+        Some(v) => requirements.iter().all(|r| r.req.matches(&v)),
+        None => requirements.iter().all(|r| r.req == VersionReq::STAR),
+    }
+}
+
+/// Released versions of `name` in the library index, newest first.
+pub async fn index_versions(toolchain: &dyn ArduinoToolchain, name: &str) -> Result<Vec<String>, String> {
+    let mut versions = toolchain.search_libraries(name).await?
+        .into_iter()
+        .find(|lib| lib.name == name)
+        .ok_or_else(|| format!("Library {} not found in the index", name))?
+        .versions;
+    versions.sort_by_key(|v| std::cmp::Reverse(parse_version(v)));
+    Ok(versions)
+}
+
+/// Decides what to do for one library given all requirements on it.
+pub async fn resolve_library(
+    toolchain: &dyn ArduinoToolchain,
+    name: &str,
+    requirements: &[&LibraryRequirement],
+    installed: Option<&str>,
+) -> LibraryResolution {
+    if let Some(version) = installed.filter(|v| satisfies(v, requirements)) {
+        return LibraryResolution::Satisfied { name: name.to_string(), version: version.to_string() };
+    }
+    // Bare names need no index lookup, as before versions were supported
+    if installed.is_none() && requirements.iter().all(|r| r.req == VersionReq::STAR) {
+        return LibraryResolution::Install { name: name.to_string(), version: None, installed: None };
+    }
+    let candidate = index_versions(toolchain, name)
+        .await
+        .unwrap_or_else(|e| {
+            println!("[Arduino] ⚠️ {}", e);
+            vec![]
+        })
+        .into_iter()
+        .find(|v| satisfies(v, requirements));
+    match candidate {
+        Some(version) => LibraryResolution::Install {
+            name: name.to_string(),
+            version: Some(version),
+            installed: installed.map(|v| v.to_string()),
+        },
+        None => LibraryResolution::Conflict {
+            name: name.to_string(),
+            installed: installed.map(|v| v.to_string()),
+            requirements: requirements.iter().map(|r| (*r).clone()).collect(),
+        },
+    }
+}
+
+/// Resolves every library required by the extensions against the installed versions (name -> version).
+pub async fn resolve_all(
+    toolchain: &dyn ArduinoToolchain,
+    requirements: &[LibraryRequirement],
+    installed: &HashMap<String, String>,
+) -> Vec<LibraryResolution> {
+    let mut by_name: BTreeMap<&str, Vec<&LibraryRequirement>> = BTreeMap::new();
+    for req in requirements {
+        by_name.entry(&req.name).or_default().push(req);
+    }
+    let mut resolutions = Vec::new();
+    for (name, reqs) in by_name {
+        resolutions.push(resolve_library(toolchain, name, &reqs, installed.get(name).map(|s| s.as_str())).await);
+    }
+    resolutions
+}
+
+/// Reports how the extensions' library requirements would be resolved, without installing anything.
+#[tauri::command]
+pub async fn check_arduino_library_requirements(app_handle: tauri::AppHandle) -> Result<Vec<LibraryResolution>, String> {
+    // Index searches may fall back to running arduino-cli, keep them off the async workers
+    tauri::async_runtime::spawn_blocking(move || {
+        let requirements = collect_requirements(&get_platform_extensions_dir(&app_handle, "arduino"));
+        let installed = list_installed_libraries(&app_handle)?;
+        let toolchain = toolchain::current(&app_handle);
+        Ok(daemon::blocking(async move { resolve_all(toolchain.as_ref(), &requirements, &installed).await }))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Installs, upgrades or downgrades libraries so every extension's requirements hold.
+/// Returns the resolutions, conflicts are left untouched.
+#[tauri::command]
+pub async fn resolve_arduino_library_requirements(app_handle: tauri::AppHandle) -> Result<Vec<LibraryResolution>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let requirements = collect_requirements(&get_platform_extensions_dir(&app_handle, "arduino"));
+        let installed = list_installed_libraries(&app_handle)?;
+        let toolchain = toolchain::current(&app_handle);
+        let resolutions = daemon::blocking(async move { resolve_all(toolchain.as_ref(), &requirements, &installed).await });
+
+        for resolution in &resolutions {
+            match resolution {
+                LibraryResolution::Install { name, version, .. } => {
+                    println!("[Arduino] 📦 Installing library: {}@{}", name, version.as_deref().unwrap_or("latest"));
+                    install_library(&app_handle, name, version.as_deref())?;
+                }
+                LibraryResolution::Conflict { name, .. } => println!("[Arduino] ⚠️ Unresolvable requirements for library {}", name),
+                LibraryResolution::Satisfied { .. } => {}
+            }
+        }
+
+        let session = app_handle.state::<crate::SessionState>();
+        *session.arduino_libraries_cache.lock().unwrap() = None;
+        Ok(resolutions)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[cfg(test)]
+mod tests {
+    use tauri::async_runtime::block_on;
+    use crate::cmd::arduino::toolchain::{FakeToolchain, IndexEntry};
+    use super::*;
+
+    fn fake() -> FakeToolchain {
+        let fake = FakeToolchain::new();
+        fake.state().index = vec![IndexEntry {
+            name: "Servo".to_string(),
+            latest: "2.0.0".to_string(),
+            versions: ["1.1.8", "1.2.1", "2.0.0", "1.2.2"].map(String::from).to_vec(),
+            provides_includes: vec!["Servo.h".to_string()],
+        }];
+        fake
+    }
+
+    /// `(extension, libraries.txt line)` pairs
+    fn requirements(lines: &[(&str, &str)]) -> Vec<LibraryRequirement> {
+        lines.iter().map(|(ext, line)| parse_requirement(line, ext).unwrap().unwrap()).collect()
+    }
+
+    fn resolve(fake: &FakeToolchain, lines: &[(&str, &str)], installed: &[(&str, &str)]) -> Vec<LibraryResolution> {
+        let installed = installed.iter().map(|(n, v)| (n.to_string(), v.to_string())).collect();
+        block_on(resolve_all(fake, &requirements(lines), &installed))
+    }
+
+    #[test]
+    fn keeps_an_installed_version_inside_every_range() {
+        let fake = fake();
+        let resolutions = resolve(&fake, &[("a", "Servo@>=1.0, <2.0"), ("b", "Servo@~1.2.0")], &[("Servo", "1.2.1")]);
+
+        assert!(matches!(&resolutions[..], [LibraryResolution::Satisfied { version, .. }] if version == "1.2.1"));
+        assert!(fake.state().calls.is_empty());
+    }
+
+    #[test]
+    fn installs_the_newest_release_every_range_allows() {
+        let fake = fake();
+
+        let downgrade = resolve(&fake, &[("a", "Servo@^1.2"), ("b", "Servo@<1.2.2")], &[("Servo", "2.0.0")]);
+        assert!(matches!(&downgrade[..], [LibraryResolution::Install { version: Some(v), installed: Some(old), .. }]
+            if v == "1.2.1" && old == "2.0.0"));
+
+        let exact = resolve(&fake, &[("a", "Servo@1.1.8")], &[]);
+        assert!(matches!(&exact[..], [LibraryResolution::Install { version: Some(v), installed: None, .. }] if v == "1.1.8"));
+        assert_eq!(fake.state().calls, ["lib search Servo", "lib search Servo"]);
+    }
+
+    #[test]
+    fn bare_names_install_the_latest_without_searching() {
+        let fake = fake();
+        let resolutions = resolve(&fake, &[("a", "Wire"), ("b", "Servo")], &[("Servo", "custom")]);
+
+        assert!(matches!(&resolutions[0], LibraryResolution::Satisfied { name, .. } if name == "Servo"));
+        assert!(matches!(&resolutions[1], LibraryResolution::Install { name, version: None, .. } if name == "Wire"));
+        assert!(fake.state().calls.is_empty());
+    }
+
+    #[test]
+    fn reports_ranges_no_release_satisfies_as_conflicts() {
+        let fake = fake();
+        let resolutions = resolve(&fake, &[("a", "Servo@^1.0"), ("b", "Servo@>=2.0"), ("c", "NoSuchLib@^1.0")], &[("Servo", "1.2.1")]);
+
+        match &resolutions[..] {
+            [LibraryResolution::Conflict { name: missing, installed: None, .. }, LibraryResolution::Conflict { name, installed, requirements }] => {
+                assert_eq!(missing, "NoSuchLib");
+                assert_eq!(name, "Servo");
+                assert_eq!(installed.as_deref(), Some("1.2.1"));
+                let wanted: Vec<(&str, &str)> = requirements.iter().map(|r| (r.extension.as_str(), r.spec.as_str())).collect();
+                assert_eq!(wanted, [("a", "^1.0"), ("b", ">=2.0")]);
+            }
+            other => panic!("unexpected resolutions: {:?}", other),
+        }
+    }
+}
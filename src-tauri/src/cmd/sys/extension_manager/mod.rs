@@ -4,6 +4,7 @@ use std::path::PathBuf;
 pub mod lifecycle;
 pub mod python;
 pub mod arduino;
+pub mod library_requirements;
 
 use self::lifecycle::ExtensionLifecycle;
 use self::python::PythonExtensionLifecycle;
@@ -1,9 +1,7 @@
 use tauri::{Runtime, AppHandle};
-use std::path::PathBuf;
-use std::process::{Command};
+use std::path::{Path, PathBuf};
 use super::lifecycle::ExtensionLifecycle;
-use crate::cmd::sys::env_manager::python::PythonEnvironment;
-use crate::cmd::sys::env_manager::EnvironmentImplementation;
+use crate::cmd::sys::env_manager::python::{ensure_env, get_python_bin, pip_command_for};
 
 pub struct PythonExtensionLifecycle;
 
@@ -42,30 +40,19 @@ impl<R: Runtime> ExtensionLifecycle<R> for PythonExtensionLifecycle {
 
              // Ensure python environment is ready
             println!("[Python] 🔧 Ensuring Python environment...");
-            PythonEnvironment.ensure_environment(app_handle)?;
-            let python_bin = PythonEnvironment.get_binary_path(app_handle);
-            println!("[Python] 🐍 Using Python: {:?}", python_bin);
-            
+            ensure_env(app_handle)?;
+            let python = get_python_bin(app_handle);
+            println!("[Python] 🐍 Using Python: {:?}", python);
+
             println!("[Python] ⏳ Installing dependencies...");
-            let output = Command::new(&python_bin)
-                .arg("-m")
-                .arg("pip")
-                .arg("install")
-                .arg("-r")
-                .arg(&requirements_path)
-                .output()
-                .map_err(|e| format!("Failed to run pip install: {}", e))?;
-            
-            if output.status.success() {
-                println!("[Python] ✅ Dependencies installed successfully");
-                // Save success hash
-                if let Err(e) = std::fs::write(&hash_path, current_hash) {
-                     println!("[Python] ⚠️ Failed to save dependency hash: {}", e);
-                }
-            } else {
-                let stderr = String::from_utf8_lossy(&output.stderr);
-                println!("[Python] ❌ Dependency install failed: {}", stderr);
-                return Err(format!("Failed to install python requirements: {}", stderr));
+            if let Err(e) = install_requirements(app_handle, &python, path) {
+                println!("[Python] ❌ Dependency install failed: {}", e);
+                return Err(format!("Failed to install python requirements: {}", e));
+            }
+            println!("[Python] ✅ Dependencies installed successfully");
+            // Save success hash
+            if let Err(e) = std::fs::write(&hash_path, current_hash) {
+                 println!("[Python] ⚠️ Failed to save dependency hash: {}", e);
             }
         } else {
             println!("[Python] ℹ️ No requirements.txt found, skipping");
@@ -79,3 +66,29 @@ impl<R: Runtime> ExtensionLifecycle<R> for PythonExtensionLifecycle {
         Ok(())
     }
 }
+
+/// Installs the `lib/requirements.txt` of the extension at `path` into the
+/// environment of `python`, from its `lib/wheels` alone when it ships them.
+pub fn install_requirements<R: Runtime>(app_handle: &AppHandle<R>, python: &Path, path: &Path) -> Result<(), String> {
+    let requirements_path = path.join("lib").join("requirements.txt");
+    if !requirements_path.exists() {
+        return Ok(());
+    }
+
+    let mut pip = pip_command_for(app_handle, python);
+    pip.arg("install").arg("-r").arg(&requirements_path);
+    // Shipped wheels make the extension installable offline
+    let wheels_dir = path.join("lib").join("wheels");
+    if wheels_dir.is_dir() {
+        println!("[Python] 📦 Installing from bundled wheels: {:?}", wheels_dir);
+        pip.arg("--no-index").arg("--find-links").arg(&wheels_dir);
+    }
+
+    let output = pip
+        .output()
+        .map_err(|e| format!("Failed to run pip install: {}", e))?;
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).to_string());
+    }
+    Ok(())
+}
@@ -1,7 +1,7 @@
 use std::fs;
 use std::path::PathBuf;
 use serde::{Deserialize, Serialize};
-use tauri::Manager;
+use tauri::{AppHandle, Manager, Runtime};
 use std::time::UNIX_EPOCH;
 use crate::cmd::sys::extension_manager;
 use crate::cmd::sys::constants::SUPPORTED_PLATFORMS;
@@ -33,7 +33,7 @@ pub struct ExtensionData {
     pub updated_at: Option<u64>,
 }
 
-pub fn get_extensions_dir(app_handle: &tauri::AppHandle) -> PathBuf {
+pub fn get_extensions_dir<R: Runtime>(app_handle: &AppHandle<R>) -> PathBuf {
     // 1. Try project root (working dir)
     let paths = vec![
         PathBuf::from("extensions"),
@@ -56,7 +56,7 @@ pub fn get_extensions_dir(app_handle: &tauri::AppHandle) -> PathBuf {
     path
 }
 
-pub fn get_platform_extensions_dir(app_handle: &tauri::AppHandle, platform: &str) -> PathBuf {
+pub fn get_platform_extensions_dir<R: Runtime>(app_handle: &AppHandle<R>, platform: &str) -> PathBuf {
     let mut path = get_extensions_dir(app_handle);
     path.push(platform);
     if !path.exists() {
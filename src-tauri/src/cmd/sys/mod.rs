@@ -3,3 +3,4 @@ pub mod extensions;
 pub mod env_manager;
 pub mod extension_manager;
 pub mod constants;
+pub mod project;
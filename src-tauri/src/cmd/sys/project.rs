@@ -0,0 +1,21 @@
+// project.rs - Per-project data stored next to the project file
+//
+// A project saved as `<dir>/<name>.<ext>` keeps its build settings, pinned
+// versions etc. in `<dir>/.hanx/<name>/`, so they travel with the project.
+
+use std::path::{Path, PathBuf};
+
+const PROJECT_DATA_DIR: &str = ".hanx";
+
+/// Returns the data directory of the project saved at `project_path`.
+/// It is not created, writers call `create_dir_all` themselves.
+pub fn project_data_dir(project_path: &str) -> Result<PathBuf, String> {
+    let path = Path::new(project_path);
+    let stem = path.file_stem()
+        .and_then(|s| s.to_str())
+        .filter(|s| !s.is_empty())
+        .ok_or_else(|| format!("Invalid project path: {}", project_path))?;
+    let parent = path.parent().unwrap_or_else(|| Path::new("."));
+
+    Ok(parent.join(PROJECT_DATA_DIR).join(stem))
+}
@@ -9,11 +9,11 @@ use tauri::menu::{Menu, Submenu, MenuItem, PredefinedMenuItem};
 use tauri::{Emitter, Manager};
 
 use std::sync::Mutex;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 struct SessionState {
   initialized_platforms: Mutex<HashSet<String>>,
-  arduino_libraries_cache: Mutex<Option<Vec<String>>>,
+  arduino_libraries_cache: Mutex<Option<HashMap<String, String>>>,
 }
 
 fn main() {
@@ -97,7 +97,52 @@ fn main() {
       cmd::arduino::upload_arduino,
       cmd::arduino::compile_arduino,
       cmd::arduino::discover_arduino_boards,
+      cmd::arduino::list_arduino_programmers,
+      cmd::arduino::burn_bootloader,
+      cmd::arduino::watch_arduino_boards,
+      cmd::arduino::usb_ids::remember_board_choice,
+      cmd::arduino::usb_ids::forget_board_choice,
+      cmd::arduino::install_arduino_core,
+      cmd::arduino::update_arduino_index,
+      cmd::arduino::daemon::get_arduino_backend,
+      cmd::arduino::daemon::set_arduino_backend,
+      cmd::arduino::emulator::run_arduino_emulator,
+      cmd::arduino::emulator::stop_arduino_emulator,
+      cmd::arduino::emulator::set_emulator_digital_input,
+      cmd::arduino::emulator::set_emulator_analog_input,
+      cmd::arduino::simulator::simulate_arduino,
+      cmd::arduino::profile::generate_arduino_profile,
+      cmd::arduino::profile::check_arduino_profile,
+      cmd::arduino::platformio::export_platformio_project,
+      cmd::arduino::build_config::get_build_config,
+      cmd::arduino::build_config::set_build_config,
+      cmd::arduino::libraries::install_arduino_libraries,
+      cmd::arduino::pins::get_board_pins,
+      cmd::arduino::uf2::list_uf2_volumes,
+      cmd::arduino::uf2::upload_uf2,
+      cmd::arduino::toolchain::get_arduino_toolchain,
+      cmd::arduino::toolchain::set_arduino_toolchain,
+      cmd::sys::extension_manager::library_requirements::check_arduino_library_requirements,
+      cmd::sys::extension_manager::library_requirements::resolve_arduino_library_requirements,
       cmd::python::run_python_code,
+      cmd::python::write_python_stdin,
+      cmd::python::debugger::debug_python_code,
+      cmd::python::debugger::python_debug_command,
+      cmd::python::debugger::set_python_breakpoints,
+      cmd::python::session::start_python_session,
+      cmd::python::session::run_python_session,
+      cmd::python::session::get_python_session_variables,
+      cmd::python::session::write_python_session_stdin,
+      cmd::python::session::stop_python_session,
+      cmd::python::packages::list_python_packages,
+      cmd::python::packages::search_python_packages,
+      cmd::python::packages::install_python_packages,
+      cmd::python::packages::uninstall_python_packages,
+      cmd::python::interpreters::list_python_interpreters,
+      cmd::python::project_env::get_python_project_config,
+      cmd::python::project_env::set_python_project_config,
+      cmd::python::project_env::recreate_python_env,
+      cmd::python::project_env::repair_python_env,
       cmd::sys::execution::stop_execution,
       cmd::sys::extensions::list_extensions,
       cmd::sys::extensions::import_extension,
@@ -105,6 +150,11 @@ fn main() {
       cmd::sys::extensions::install_extension_dependencies,
       cmd::sys::extensions::refresh_extensions,
       cmd::sys::env_manager::ensure_environment,
+      cmd::sys::env_manager::arduino::get_arduino_cli_config,
+      cmd::sys::env_manager::arduino::set_arduino_board_manager_urls,
+      cmd::sys::env_manager::arduino::set_arduino_proxy,
+      cmd::sys::env_manager::python::get_python_pip_settings,
+      cmd::sys::env_manager::python::set_python_pip_settings,
       cmd::ai::generate_blocks
     ])
     .plugin(tauri_plugin_shell::init())
@@ -120,6 +170,13 @@ fn main() {
       .level(log::LevelFilter::Info)
       .build()
     )
-    .run(tauri::generate_context!())
-    .expect("error while running tauri application");
+    .build(tauri::generate_context!())
+    .expect("error while building tauri application")
+    .run(|_app, event| {
+      if let tauri::RunEvent::Exit = event {
+        cmd::arduino::daemon::shutdown();
+        cmd::python::session::stop_all();
+        cmd::arduino::emulator::stop();
+      }
+    });
 }